@@ -0,0 +1,150 @@
+use std::{any::Any, ffi::c_void, ptr};
+
+use vvdec_sys::*;
+
+use crate::{DecoderContext, PlaneComponent};
+
+/// Allocator for the picture buffers VVdeC decodes into.
+///
+/// It is set with [`DecoderBuilder::frame_allocator`](crate::DecoderBuilder::frame_allocator). Buffers are requested
+/// per plane and are handed back to [`FrameAllocator::release`] once VVdeC no longer uses them, which may be after the
+/// corresponding [`Frame`](crate::Frame) is dropped, since VVdeC keeps reference pictures alive internally.
+///
+/// The buffer backing a plane can be retrieved with [`Plane::buffer`](crate::Plane::buffer).
+///
+/// ```
+/// use vvdec::{FrameAllocator, PlaneComponent};
+///
+/// struct AlignedBuffer {
+///     data: Vec<u8>,
+///     offset: usize,
+/// }
+///
+/// impl AsMut<[u8]> for AlignedBuffer {
+///     fn as_mut(&mut self) -> &mut [u8] {
+///         &mut self.data[self.offset..]
+///     }
+/// }
+///
+/// struct Allocator;
+///
+/// impl FrameAllocator for Allocator {
+///     type Buffer = AlignedBuffer;
+///
+///     fn allocate(&self, _: PlaneComponent, size: usize, alignment: usize) -> Option<AlignedBuffer> {
+///         let data = vec![0; size + alignment];
+///         let offset = data.as_ptr().align_offset(alignment);
+///         Some(AlignedBuffer { data, offset })
+///     }
+/// }
+/// ```
+pub trait FrameAllocator: Send + Sync + 'static {
+    /// Buffer type.
+    type Buffer: AsMut<[u8]> + Send + 'static;
+
+    /// Allocate a buffer of at least `size` bytes for the given plane component, whose start is aligned to
+    /// `alignment` bytes.
+    ///
+    /// Returning `None`, a buffer that is too small or a misaligned buffer makes decoding fail with
+    /// [`Error::Allocate`](crate::Error::Allocate).
+    fn allocate(
+        &self,
+        component: PlaneComponent,
+        size: usize,
+        alignment: usize,
+    ) -> Option<Self::Buffer>;
+
+    /// Release a buffer that is no longer used by VVdeC.
+    ///
+    /// The default implementation drops the buffer. Pooling allocators can return it to the pool instead.
+    fn release(&self, buffer: Self::Buffer) {
+        drop(buffer);
+    }
+}
+
+/// Object-safe counterpart of FrameAllocator, handing buffers to VVdeC as raw pointers.
+pub(crate) trait RawFrameAllocator: Send + Sync {
+    /// Returns the data pointer and the handle to pass to release().
+    fn allocate(
+        &self,
+        component: PlaneComponent,
+        size: usize,
+        alignment: usize,
+    ) -> Option<(*mut u8, *mut c_void)>;
+
+    /// Safety: the handle must have been returned by allocate() and not released yet.
+    unsafe fn release(&self, handle: *mut c_void);
+}
+
+type ErasedBuffer = Box<dyn Any + Send>;
+
+impl<A: FrameAllocator> RawFrameAllocator for A {
+    fn allocate(
+        &self,
+        component: PlaneComponent,
+        size: usize,
+        alignment: usize,
+    ) -> Option<(*mut u8, *mut c_void)> {
+        // The buffer is boxed before taking its data pointer, so that the pointer remains valid for buffer types
+        // storing their data inline.
+        let mut buffer = Box::new(FrameAllocator::allocate(self, component, size, alignment)?);
+        let (data, len) = {
+            let data = (*buffer).as_mut();
+            (data.as_mut_ptr(), data.len())
+        };
+        if len < size || data.align_offset(alignment.max(1)) != 0 {
+            FrameAllocator::release(self, *buffer);
+            return None;
+        }
+
+        let handle = Box::into_raw(Box::new(buffer as ErasedBuffer));
+        Some((data, handle as *mut c_void))
+    }
+
+    unsafe fn release(&self, handle: *mut c_void) {
+        let buffer: ErasedBuffer = *Box::from_raw(handle as *mut ErasedBuffer);
+        if let Ok(buffer) = buffer.downcast::<A::Buffer>() {
+            FrameAllocator::release(self, *buffer);
+        }
+    }
+}
+
+/// Safety: the handle must have been returned by RawFrameAllocator::allocate() and not released yet.
+pub(crate) unsafe fn buffer_from_handle<'a, B: 'static>(handle: *mut c_void) -> Option<&'a B> {
+    (handle as *const ErasedBuffer)
+        .as_ref()
+        .and_then(|buffer| buffer.downcast_ref())
+}
+
+pub(crate) unsafe extern "C" fn create_buffer_callback(
+    opaque: *mut c_void,
+    component: vvdecComponentType,
+    size: u32,
+    alignment: u32,
+    allocator: *mut *mut c_void,
+) -> *mut c_void {
+    let context = &*(opaque as *const DecoderContext);
+    let (Some(frame_allocator), Some(component)) = (
+        context.frame_allocator.as_ref(),
+        PlaneComponent::from_ffi(component),
+    ) else {
+        return ptr::null_mut();
+    };
+
+    match frame_allocator.allocate(component, size as usize, alignment as usize) {
+        Some((data, handle)) => {
+            *allocator = handle;
+            data as *mut c_void
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+pub(crate) unsafe extern "C" fn unref_buffer_callback(opaque: *mut c_void, allocator: *mut c_void) {
+    let context = &*(opaque as *const DecoderContext);
+    if let Some(frame_allocator) = context.frame_allocator.as_ref() {
+        if !allocator.is_null() {
+            frame_allocator.release(allocator);
+        }
+    }
+}
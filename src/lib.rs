@@ -41,15 +41,45 @@
 //! ## Vendored build
 //!
 //! If VVdeC is not installed in the system, a vendored copy will be built, which requires CMake.
+//! [`VENDORED`] tells which one is in use, and [`version()`] returns the version of the linked library.
+//!
+//! ## Logging
+//!
+//! VVdeC log messages can be received with [`Decoder::set_logger`]. With the `log` or `tracing` features enabled,
+//! [`forward_to_log`] or [`forward_to_tracing`] can be passed to it to route messages into those ecosystems.
 
 use std::{
-    mem,
+    collections::VecDeque,
+    ffi::{c_char, c_int, c_void, CStr},
+    fmt, mem,
     ops::Deref,
     ptr,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
 };
 use vvdec_sys::*;
 
+mod allocator;
+pub mod nal;
+mod picture_hash;
+mod sei;
+
+pub use allocator::FrameAllocator;
+pub use sei::*;
+
+/// Whether VVdeC was built from the vendored sources, rather than found in the system.
+pub const VENDORED: bool = vvdec_sys::VVDEC_VENDORED;
+
+/// Get the version of the VVdeC library in use.
+pub fn version() -> &'static str {
+    let version = unsafe { vvdec_get_version() };
+    if version.is_null() {
+        return "";
+    }
+    unsafe { CStr::from_ptr(version) }
+        .to_str()
+        .unwrap_or_default()
+}
+
 /// VVC decoder.
 #[derive(Debug, Clone)]
 pub struct Decoder {
@@ -59,6 +89,284 @@ pub struct Decoder {
 #[derive(Debug)]
 struct InnerDecoder {
     decoder: ptr::NonNull<vvdecDecoder>,
+    // Passed to VVdeC as the opaque pointer, so it must outlive the decoder.
+    context: Box<DecoderContext>,
+    verify_picture_hash: bool,
+    film_grain_synthesis: bool,
+    // Whether film grain characteristics persist from a previous picture, so that grain is also synthesised on the
+    // next pictures.
+    film_grain_persists: bool,
+    // Set when decoding fails due to damaged input or a damaged access unit is pushed, until the next IRAP picture
+    // is output.
+    damaged: bool,
+    // Profile, tier and level of the last parameter set received, which applies to output frames from the next
+    // IRAP or GDR picture.
+    received_profile_tier_level: Option<nal::ProfileTierLevel>,
+    output_profile_tier_level: Option<nal::ProfileTierLevel>,
+    // Parameter sets received, by ID, to parse picture headers.
+    sps: Vec<nal::Sps>,
+    pps: Vec<nal::Pps>,
+    // Picture header received in a PH NAL unit, for the next slice.
+    picture_header: Option<Result<nal::PictureHeader, nal::ParseError>>,
+    // Prefix SEI messages received since the last picture started, which belong to the next one.
+    prefix_sei_messages: Vec<SeiMessage>,
+    // Pictures started in the pushed access units and not output yet, in decoding order.
+    pending_pictures: VecDeque<PendingPicture>,
+    // Whether the last VCL NAL unit received belongs to the last pending picture, so that suffix SEI messages can be
+    // attached to it.
+    has_current_picture: bool,
+    access_unit_cts: AccessUnitCts,
+}
+
+// Pending pictures beyond this number are dropped, as VVdeC only holds a DPB worth of pictures and the oldest ones
+// were never output.
+const MAX_PENDING_PICTURES: usize = 64;
+
+// Access units beyond this number are forgotten, far more than VVdeC holds before outputting their pictures even
+// when NAL units are pushed one by one.
+const MAX_ACCESS_UNITS: usize = 1024;
+
+// Cts of the access units pushed, by ID. VVdeC is given the ID as cts, which also identifies the pending pictures
+// of output frames.
+#[derive(Debug, Default)]
+struct AccessUnitCts {
+    // ID of the first access unit in `cts`.
+    first_id: u64,
+    cts: VecDeque<Option<u64>>,
+}
+
+impl AccessUnitCts {
+    // Returns the ID of the new access unit.
+    fn push(&mut self, cts: Option<u64>) -> u64 {
+        let id = self.first_id + self.cts.len() as u64;
+        self.cts.push_back(cts);
+        if self.cts.len() > MAX_ACCESS_UNITS {
+            self.cts.pop_front();
+            self.first_id += 1;
+        }
+        id
+    }
+
+    fn get(&self, id: u64) -> Option<u64> {
+        let index = id.checked_sub(self.first_id)?;
+        *self.cts.get(usize::try_from(index).ok()?)?
+    }
+}
+
+// Picture started in a pushed access unit, with what VVdeC does not report on its output frame.
+#[derive(Debug)]
+struct PendingPicture {
+    // ID of the access unit containing the first slice of the picture, which VVdeC outputs as the frame's cts.
+    access_unit_id: u64,
+    // Picture order count LSBs and their modulus, if the picture header could be parsed.
+    pic_order_cnt_lsb: Option<(u32, i64)>,
+    // Size of the decoded picture, before cropping.
+    decoded_size: Option<(u32, u32)>,
+    // Size after cropping to the conformance window of the PPS referred to.
+    coded_size: Option<(u32, u32)>,
+    // Whether VVdeC synthesises film grain on the output picture.
+    has_film_grain: bool,
+    // Sublayer HRD parameters of the SPS referred to, which VVdeC does not report.
+    hrd_sublayers: Vec<SublayerHrd>,
+    sei_messages: Vec<SeiMessage>,
+}
+
+impl InnerDecoder {
+    fn receive_nal_units(&mut self, payload: &[u8], access_unit_id: u64) {
+        let mut has_vcl = false;
+        for nal_unit in nal::AnnexBSplitter::new(payload) {
+            let Some(header) = nal::NalUnitHeader::parse(nal_unit) else {
+                continue;
+            };
+            match header.nal_type {
+                NalType::Vps => {
+                    self.has_current_picture = false;
+                    let profile_tier_level = nal::Vps::parse(nal_unit)
+                        .ok()
+                        .and_then(|vps| vps.profile_tier_levels.into_iter().next());
+                    // Parameter sets precede the VCL NAL units of an access unit.
+                    if !has_vcl && profile_tier_level.is_some() {
+                        self.received_profile_tier_level = profile_tier_level;
+                    }
+                }
+                NalType::Sps => {
+                    self.has_current_picture = false;
+                    let Ok(sps) = nal::Sps::parse(nal_unit) else {
+                        continue;
+                    };
+                    if !has_vcl && sps.profile_tier_level.is_some() {
+                        self.received_profile_tier_level
+                            .clone_from(&sps.profile_tier_level);
+                    }
+                    self.sps.retain(|other| other.id != sps.id);
+                    self.sps.push(sps);
+                }
+                NalType::Pps => {
+                    let Ok(pps) = nal::Pps::parse(nal_unit) else {
+                        continue;
+                    };
+                    self.pps.retain(|other| other.id != pps.id);
+                    self.pps.push(pps);
+                }
+                NalType::Ph => {
+                    self.has_current_picture = false;
+                    self.picture_header =
+                        Some(nal::PictureHeader::parse(nal_unit, &self.pps, &self.sps));
+                }
+                NalType::PrefixSei => {
+                    self.has_current_picture = false;
+                    self.prefix_sei_messages.extend(parse_sei(nal_unit));
+                }
+                // Suffix SEI messages are dropped when the last VCL NAL unit may not belong to the last pending
+                // picture, rather than attached to the wrong picture.
+                NalType::SuffixSei => match self.pending_pictures.back_mut() {
+                    Some(picture) if self.has_current_picture => {
+                        picture.sei_messages.extend(parse_sei(nal_unit))
+                    }
+                    _ => {}
+                },
+                NalType::AccessUnitDelimiter | NalType::Eos | NalType::Eob => {
+                    self.has_current_picture = false;
+                }
+                nal_type if nal_type.is_vcl() => {
+                    has_vcl = true;
+                    // Without a PH NAL unit, the picture header is in the slice header of the first and only slice
+                    // of the picture.
+                    let picture_header = self.picture_header.take().or_else(|| {
+                        let slice_header =
+                            nal::strip_start_code(nal_unit).get(nal::NalUnitHeader::SIZE)?;
+                        (slice_header >> 7 == 1)
+                            .then(|| nal::PictureHeader::parse(nal_unit, &self.pps, &self.sps))
+                    });
+                    match picture_header {
+                        Some(picture_header) => {
+                            self.start_picture(access_unit_id, picture_header.ok())
+                        }
+                        // Too short to tell whether the slice starts a new picture.
+                        None if nal::strip_start_code(nal_unit).len()
+                            <= nal::NalUnitHeader::SIZE =>
+                        {
+                            self.has_current_picture = false
+                        }
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn start_picture(&mut self, access_unit_id: u64, picture_header: Option<nal::PictureHeader>) {
+        let parameter_sets = picture_header.as_ref().and_then(|picture_header| {
+            let pps = self
+                .pps
+                .iter()
+                .find(|pps| pps.id == picture_header.pps_id)?;
+            let sps = self.sps.iter().find(|sps| sps.id == pps.sps_id)?;
+            Some((pps, sps))
+        });
+        let pic_order_cnt_lsb =
+            picture_header
+                .zip(parameter_sets)
+                .map(|(picture_header, (_, sps))| {
+                    (
+                        picture_header.pic_order_cnt_lsb,
+                        1 << sps.log2_max_pic_order_cnt_lsb,
+                    )
+                });
+        let decoded_size = parameter_sets.map(|(pps, _)| (pps.width, pps.height));
+        let coded_size = parameter_sets.map(|(pps, sps)| pps.cropped_size(sps));
+        let hrd_sublayers = parameter_sets
+            .and_then(|(_, sps)| Some(sps.hrd.as_ref()?.sublayers.clone()))
+            .unwrap_or_default();
+
+        let sei_messages = mem::take(&mut self.prefix_sei_messages);
+        let film_grain = sei_messages.iter().find_map(|message| match message {
+            SeiMessage::FilmGrainCharacteristics(film_grain) => Some(film_grain),
+            _ => None,
+        });
+        let has_film_grain = match film_grain {
+            Some(film_grain) => {
+                self.film_grain_persists = !film_grain.cancel && film_grain.persistence;
+                !film_grain.cancel
+            }
+            None => self.film_grain_persists,
+        };
+
+        if self.pending_pictures.len() == MAX_PENDING_PICTURES {
+            self.pending_pictures.pop_front();
+        }
+        self.pending_pictures.push_back(PendingPicture {
+            access_unit_id,
+            pic_order_cnt_lsb,
+            decoded_size,
+            coded_size,
+            has_film_grain: self.film_grain_synthesis && has_film_grain,
+            hrd_sublayers,
+            sei_messages,
+        });
+        self.has_current_picture = true;
+    }
+
+    fn take_pending_picture(&mut self, frame: &vvdecFrame) -> Option<PendingPicture> {
+        let poc = unsafe { frame.picAttributes.as_ref() }.map(|pic_attributes| pic_attributes.poc);
+        let index = self.pending_pictures.iter().position(|picture| {
+            frame.ctsValid
+                && picture.access_unit_id == frame.cts
+                && picture
+                    .pic_order_cnt_lsb
+                    .zip(poc)
+                    .map_or(true, |((lsb, modulus), poc)| {
+                        poc.rem_euclid(modulus) == lsb as i64
+                    })
+        })?;
+        self.pending_pictures.remove(index)
+    }
+
+    fn clear_pending_pictures(&mut self) {
+        self.picture_header = None;
+        self.prefix_sei_messages.clear();
+        self.pending_pictures.clear();
+        self.has_current_picture = false;
+    }
+
+    fn output_state(
+        &mut self,
+        frame: &vvdecFrame,
+    ) -> (
+        bool,
+        Option<nal::ProfileTierLevel>,
+        Option<u64>,
+        Option<PendingPicture>,
+    ) {
+        let pic_attributes = unsafe { frame.picAttributes.as_ref() };
+        let nal_type = pic_attributes.map(|pic_attributes| NalType::new(pic_attributes.nalType));
+        if nal_type.is_some_and(|nal_type| nal_type.is_irap()) {
+            self.damaged = false;
+        }
+        if nal_type.is_some_and(|nal_type| nal_type.is_irap() || nal_type == NalType::CodedSliceGdr)
+        {
+            self.output_profile_tier_level
+                .clone_from(&self.received_profile_tier_level);
+        }
+        let cts = frame
+            .ctsValid
+            .then(|| self.access_unit_cts.get(frame.cts))
+            .flatten();
+        (
+            self.damaged,
+            self.output_profile_tier_level.clone(),
+            cts,
+            self.take_pending_picture(frame),
+        )
+    }
+}
+
+fn parse_sei(nal_unit: &[u8]) -> impl Iterator<Item = SeiMessage> {
+    nal::SeiPayload::parse_nal_unit(nal_unit)
+        .unwrap_or_default()
+        .into_iter()
+        .map(SeiMessage::new)
 }
 
 impl Drop for InnerDecoder {
@@ -69,6 +377,32 @@ impl Drop for InnerDecoder {
     }
 }
 
+type Logger = dyn Fn(LogLevel, &str) + Send + Sync;
+
+#[derive(Default)]
+struct DecoderContext {
+    logger: RwLock<Option<Box<Logger>>>,
+    frame_allocator: Option<Arc<dyn allocator::RawFrameAllocator>>,
+}
+
+impl fmt::Debug for DecoderContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecoderContext").finish_non_exhaustive()
+    }
+}
+
+unsafe extern "C" fn log_callback(opaque: *mut c_void, level: c_int, message: *const c_char) {
+    if opaque.is_null() || message.is_null() {
+        return;
+    }
+
+    let context = &*(opaque as *const DecoderContext);
+    if let Some(logger) = context.logger.read().unwrap().as_ref() {
+        let message = CStr::from_ptr(message).to_string_lossy();
+        logger(LogLevel::new(level), message.trim_end());
+    }
+}
+
 /// Access unit containing VVC bitstream data.
 ///
 /// VVdeC expects that the pushed access units follow the Annex-B format - prefixed by 0x000001 or 0x00000001.
@@ -84,16 +418,22 @@ pub struct AccessUnit<A> {
     pub dts: Option<u64>,
     /// Is it an random access point?
     pub is_random_access_point: bool,
+    /// Is the payload known to be incomplete, for example due to packet loss?
+    ///
+    /// Frames output after a damaged access unit are flagged by [`Frame::is_corrupted`] until the next IRAP
+    /// picture.
+    pub is_damaged: bool,
 }
 
 impl<A> AccessUnit<A> {
-    /// Create a new access unit with no cts or dts, that is also not a random access point.
+    /// Create a new access unit with no cts or dts, that is also not a random access point nor damaged.
     pub fn new(payload: A) -> Self {
         Self {
             payload,
             cts: None,
             dts: None,
             is_random_access_point: false,
+            is_damaged: false,
         }
     }
 }
@@ -115,16 +455,89 @@ impl Decoder {
         DecoderBuilder::new()
     }
 
-    fn with_params(params: &mut vvdecParams) -> Result<Self, Error> {
+    /// Get information about the decoder, as reported by VVdeC.
+    pub fn info(&self) -> String {
+        let info =
+            unsafe { vvdec_get_dec_information(self.inner.lock().unwrap().decoder.as_ptr()) };
+        if info.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(info) }
+            .to_string_lossy()
+            .trim_end()
+            .to_owned()
+    }
+
+    /// Get the number of pictures that did not match their decoded picture hash SEI message, as verified by VVdeC.
+    ///
+    /// VVdeC only verifies pictures when enabled with [`DecoderBuilder::verify_picture_hash`]. Unlike
+    /// [`Frame::picture_hash_status`], it verifies the decoded pictures before they are cropped, rescaled or have
+    /// film grain synthesised on them, but does not tell which pictures did not match.
+    pub fn picture_hash_error_count(&self) -> u32 {
+        let count =
+            unsafe { vvdec_get_hash_error_count(self.inner.lock().unwrap().decoder.as_ptr()) };
+        count.max(0) as u32
+    }
+
+    fn with_params(
+        params: &mut vvdecParams,
+        frame_allocator: Option<Arc<dyn allocator::RawFrameAllocator>>,
+    ) -> Result<Self, Error> {
+        let context = Box::new(DecoderContext {
+            frame_allocator,
+            ..Default::default()
+        });
+        params.opaque = &*context as *const DecoderContext as *mut c_void;
+
         let decoder = unsafe { vvdec_decoder_open(params) };
 
         ptr::NonNull::new(decoder)
             .map(|decoder| Self {
-                inner: Arc::new(Mutex::new(InnerDecoder { decoder })),
+                inner: Arc::new(Mutex::new(InnerDecoder {
+                    decoder,
+                    context,
+                    verify_picture_hash: params.verifyPictureHash,
+                    film_grain_synthesis: params.filmGrainSynthesis,
+                    film_grain_persists: false,
+                    damaged: false,
+                    received_profile_tier_level: None,
+                    output_profile_tier_level: None,
+                    sps: Vec::new(),
+                    pps: Vec::new(),
+                    picture_header: None,
+                    prefix_sei_messages: Vec::new(),
+                    pending_pictures: VecDeque::new(),
+                    has_current_picture: false,
+                    access_unit_cts: AccessUnitCts::default(),
+                })),
             })
             .ok_or(Error::FailedToOpen)
     }
 
+    /// Set a callback to receive VVdeC log messages.
+    ///
+    /// Messages above the level set with [`DecoderBuilder::log_level`] are filtered out by VVdeC. The callback may be
+    /// called from VVdeC's worker threads.
+    ///
+    /// Messages are formatted into a buffer of 4096 bytes, so longer messages are truncated to their first 4095
+    /// bytes.
+    pub fn set_logger<F>(&mut self, logger: F) -> Result<(), Error>
+    where
+        F: Fn(LogLevel, &str) + Send + Sync + 'static,
+    {
+        let inner = self.inner.lock().unwrap();
+        *inner.context.logger.write().unwrap() = Some(Box::new(logger));
+
+        let ret =
+            unsafe { vvdec_rs_set_logging_callback(inner.decoder.as_ptr(), Some(log_callback)) };
+
+        #[allow(non_upper_case_globals)]
+        match ret {
+            vvdecErrorCodes_VVDEC_OK => Ok(()),
+            _ => Err(Error::from_decoder(ret, inner.decoder.as_ptr())),
+        }
+    }
+
     /// Decode input data.
     ///
     /// The decode function takes VVC bitstream data in the Annex-B format, which is prefixed by 0x000001 or 0x00000001.
@@ -141,34 +554,41 @@ impl Decoder {
             cts,
             dts,
             is_random_access_point,
+            is_damaged,
         } = access_unit.into();
         let payload = payload.as_ref();
-        let mut au = vvdecAccessUnit {
-            payload: payload.as_ptr() as *mut u8,
-            payloadSize: payload.len() as i32,
-            payloadUsedSize: payload.len() as i32,
-            cts: cts.unwrap_or_default(),
-            dts: dts.unwrap_or_default(),
-            ctsValid: cts.is_some(),
-            dtsValid: dts.is_some(),
-            rap: is_random_access_point,
-        };
-
         let mut frame: *mut vvdecFrame = ptr::null_mut();
 
-        let ret = unsafe {
-            vvdec_decode(
-                self.inner.lock().unwrap().decoder.as_ptr(),
-                &mut au,
-                &mut frame,
-            )
+        let result = {
+            let mut inner = self.inner.lock().unwrap();
+            let access_unit_id = inner.access_unit_cts.push(cts);
+            inner.receive_nal_units(payload, access_unit_id);
+            inner.damaged |= is_damaged;
+
+            let mut au = vvdecAccessUnit {
+                payload: payload.as_ptr() as *mut u8,
+                payloadSize: payload.len() as i32,
+                payloadUsedSize: payload.len() as i32,
+                // The cts is restored on output frames from the access unit ID.
+                cts: access_unit_id,
+                dts: dts.unwrap_or_default(),
+                ctsValid: true,
+                dtsValid: dts.is_some(),
+                rap: is_random_access_point,
+            };
+            let ret = unsafe { vvdec_decode(inner.decoder.as_ptr(), &mut au, &mut frame) };
+            if ret == vvdecErrorCodes_VVDEC_ERR_DEC_INPUT {
+                inner.damaged = true;
+            }
+
+            #[allow(non_upper_case_globals)]
+            match ret {
+                vvdecErrorCodes_VVDEC_OK => Ok(()),
+                _ => Err(Error::from_decoder(ret, inner.decoder.as_ptr())),
+            }
         };
 
-        #[allow(non_upper_case_globals)]
-        match ret {
-            vvdecErrorCodes_VVDEC_OK => Ok(Frame::from_raw(self, frame)),
-            _ => Err(Error::new(ret)),
-        }
+        result.map(|()| Frame::from_raw(self, frame))
     }
 
     /// Flush the decoder.
@@ -180,14 +600,28 @@ impl Decoder {
     pub fn flush(&mut self) -> Result<Option<Frame>, Error> {
         let mut frame: *mut vvdecFrame = ptr::null_mut();
 
-        let ret = unsafe { vvdec_flush(self.inner.lock().unwrap().decoder.as_ptr(), &mut frame) };
+        let result = {
+            let mut inner = self.inner.lock().unwrap();
+            let ret = unsafe { vvdec_flush(inner.decoder.as_ptr(), &mut frame) };
+
+            #[allow(non_upper_case_globals)]
+            match ret {
+                vvdecErrorCodes_VVDEC_OK => Ok(true),
+                vvdecErrorCodes_VVDEC_EOF => {
+                    inner.clear_pending_pictures();
+                    Ok(false)
+                }
+                _ => Err(Error::from_decoder(ret, inner.decoder.as_ptr())),
+            }
+        };
 
-        #[allow(non_upper_case_globals)]
-        match ret {
-            vvdecErrorCodes_VVDEC_OK => Ok(Frame::from_raw(self, frame)),
-            vvdecErrorCodes_VVDEC_EOF => Ok(None),
-            _ => Err(Error::new(ret)),
-        }
+        result.map(|has_frame| {
+            if has_frame {
+                Frame::from_raw(self, frame)
+            } else {
+                None
+            }
+        })
     }
 }
 
@@ -197,6 +631,7 @@ unsafe impl Send for Decoder {}
 /// Decoder builder
 pub struct DecoderBuilder {
     params: vvdecParams,
+    frame_allocator: Option<Arc<dyn allocator::RawFrameAllocator>>,
 }
 
 impl DecoderBuilder {
@@ -207,7 +642,7 @@ impl DecoderBuilder {
 
     /// Build a Decoder instance.
     pub fn build(&mut self) -> Result<Decoder, Error> {
-        Decoder::with_params(&mut self.params)
+        Decoder::with_params(&mut self.params, self.frame_allocator.clone())
     }
 
     /// Set the number of threads.
@@ -221,6 +656,76 @@ impl DecoderBuilder {
         self.params.parseDelay = parse_delay;
         self
     }
+
+    /// Set the log level.
+    ///
+    /// Messages up to this level are passed to the callback set with [`Decoder::set_logger`].
+    pub fn log_level(&mut self, log_level: LogLevel) -> &mut Self {
+        self.params.logLevel = log_level.to_ffi();
+        self
+    }
+
+    /// Enable verification of decoded pictures against decoded picture hash SEI messages.
+    ///
+    /// The result for each frame is available from [`Frame::picture_hash_status`], and the number of pictures that
+    /// VVdeC found not to match from [`Decoder::picture_hash_error_count`].
+    pub fn verify_picture_hash(&mut self, verify_picture_hash: bool) -> &mut Self {
+        self.params.verifyPictureHash = verify_picture_hash;
+        self
+    }
+
+    /// Set the SIMD extension to use.
+    ///
+    /// By default, the best extension supported by the CPU is used. Forcing an extension that is not supported
+    /// makes decoding fail with [`Error::Cpu`].
+    pub fn simd(&mut self, simd: SimdExtension) -> &mut Self {
+        self.params.simd = simd.to_ffi();
+        self
+    }
+
+    /// Set how pictures coded with reference picture resampling (RPR) are output.
+    ///
+    /// By default, pictures are output with the size they were coded with, which may change within a sequence.
+    pub fn upscale_output(&mut self, upscale_output: UpscaleOutput) -> &mut Self {
+        self.params.upscaleOutput = upscale_output.to_ffi();
+        self
+    }
+
+    /// Enable synthesis of film grain on output pictures, as described by film grain characteristics SEI messages.
+    ///
+    /// When disabled, the film grain parameters are still available from [`Frame::film_grain_characteristics`], so
+    /// that the grain can be applied afterwards.
+    pub fn film_grain_synthesis(&mut self, film_grain_synthesis: bool) -> &mut Self {
+        self.params.filmGrainSynthesis = film_grain_synthesis;
+        self
+    }
+
+    /// Set how the decoder handles errors in the input bitstream.
+    ///
+    /// With [`ErrorHandling::TryContinue`], decoding can go on after [`Decoder::decode`] returns
+    /// [`Error::DecInput`], and frames that may be affected are flagged by [`Frame::is_corrupted`].
+    pub fn error_handling(&mut self, error_handling: ErrorHandling) -> &mut Self {
+        self.params.errHandlingFlags = error_handling.to_ffi();
+        self
+    }
+
+    /// Remove the padding from output pictures, so that the stride of each plane matches its width.
+    ///
+    /// This makes VVdeC copy each output picture into a new buffer.
+    pub fn remove_padding(&mut self, remove_padding: bool) -> &mut Self {
+        self.params.removePadding = remove_padding;
+        self
+    }
+
+    /// Set a custom allocator for the picture buffers.
+    ///
+    /// Decoders built afterwards share the allocator.
+    pub fn frame_allocator<A: FrameAllocator>(&mut self, frame_allocator: A) -> &mut Self {
+        self.frame_allocator = Some(Arc::new(frame_allocator));
+        self.params.createBufFunc = Some(allocator::create_buffer_callback);
+        self.params.unrefBufFunc = Some(allocator::unref_buffer_callback);
+        self
+    }
 }
 
 impl Default for DecoderBuilder {
@@ -228,69 +733,315 @@ impl Default for DecoderBuilder {
         unsafe {
             let mut params: vvdecParams = mem::zeroed();
             vvdec_params_default(&mut params);
-            Self { params }
+            Self {
+                params,
+                frame_allocator: None,
+            }
+        }
+    }
+}
+
+/// Log level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// No messages.
+    Silent,
+    /// Errors.
+    Error,
+    /// Warnings.
+    Warning,
+    /// Informational messages.
+    Info,
+    /// Notices.
+    Notice,
+    /// Verbose messages.
+    Verbose,
+    /// Detailed messages.
+    Details,
+}
+
+impl LogLevel {
+    fn new(level: c_int) -> Self {
+        use LogLevel::*;
+        #[allow(non_upper_case_globals)]
+        match vvdecLogLevel::try_from(level) {
+            Ok(vvdecLogLevel_VVDEC_SILENT) => Silent,
+            Ok(vvdecLogLevel_VVDEC_ERROR) => Error,
+            Ok(vvdecLogLevel_VVDEC_WARNING) => Warning,
+            Ok(vvdecLogLevel_VVDEC_INFO) => Info,
+            Ok(vvdecLogLevel_VVDEC_NOTICE) => Notice,
+            Ok(vvdecLogLevel_VVDEC_VERBOSE) => Verbose,
+            _ => Details,
+        }
+    }
+
+    #[inline]
+    fn to_ffi(self) -> vvdecLogLevel {
+        match self {
+            LogLevel::Silent => vvdecLogLevel_VVDEC_SILENT,
+            LogLevel::Error => vvdecLogLevel_VVDEC_ERROR,
+            LogLevel::Warning => vvdecLogLevel_VVDEC_WARNING,
+            LogLevel::Info => vvdecLogLevel_VVDEC_INFO,
+            LogLevel::Notice => vvdecLogLevel_VVDEC_NOTICE,
+            LogLevel::Verbose => vvdecLogLevel_VVDEC_VERBOSE,
+            LogLevel::Details => vvdecLogLevel_VVDEC_DETAILS,
+        }
+    }
+}
+
+/// SIMD extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdExtension {
+    /// The best extension supported by the CPU.
+    Default,
+    /// No SIMD, scalar code only.
+    Scalar,
+    /// SSE4.1.
+    Sse41,
+    /// SSE4.2.
+    Sse42,
+    /// AVX.
+    Avx,
+    /// AVX2.
+    Avx2,
+    /// AVX-512.
+    Avx512,
+}
+
+impl SimdExtension {
+    #[inline]
+    fn to_ffi(self) -> vvdecSIMD_Extension {
+        match self {
+            SimdExtension::Default => vvdecSIMD_Extension_VVDEC_SIMD_DEFAULT,
+            SimdExtension::Scalar => vvdecSIMD_Extension_VVDEC_SIMD_SCALAR,
+            SimdExtension::Sse41 => vvdecSIMD_Extension_VVDEC_SIMD_SSE41,
+            SimdExtension::Sse42 => vvdecSIMD_Extension_VVDEC_SIMD_SSE42,
+            SimdExtension::Avx => vvdecSIMD_Extension_VVDEC_SIMD_AVX,
+            SimdExtension::Avx2 => vvdecSIMD_Extension_VVDEC_SIMD_AVX2,
+            SimdExtension::Avx512 => vvdecSIMD_Extension_VVDEC_SIMD_AVX512,
+        }
+    }
+}
+
+/// Output mode for pictures coded with reference picture resampling (RPR).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpscaleOutput {
+    /// Output pictures with the size they were coded with.
+    Off,
+    /// Copy pictures into the top-left corner of a frame with the maximum size of the sequence, without rescaling.
+    CopyWithoutRescale,
+    /// Rescale pictures to the maximum size of the sequence.
+    Rescale,
+}
+
+impl UpscaleOutput {
+    #[inline]
+    fn to_ffi(self) -> vvdecRPRUpscaling {
+        match self {
+            UpscaleOutput::Off => vvdecRPRUpscaling_VVDEC_UPSCALING_OFF,
+            UpscaleOutput::CopyWithoutRescale => vvdecRPRUpscaling_VVDEC_UPSCALING_COPY_ONLY,
+            UpscaleOutput::Rescale => vvdecRPRUpscaling_VVDEC_UPSCALING_RESCALE,
+        }
+    }
+}
+
+/// How the decoder handles errors in the input bitstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorHandling {
+    /// Stop decoding on errors. The decoder needs to be restarted afterwards.
+    Strict,
+    /// Try to continue decoding after errors, concealing damaged pictures where possible.
+    TryContinue,
+}
+
+impl ErrorHandling {
+    #[inline]
+    fn to_ffi(self) -> vvdecErrHandlingFlags {
+        match self {
+            ErrorHandling::Strict => vvdecErrHandlingFlags_VVDEC_ERR_HANDLING_OFF,
+            ErrorHandling::TryContinue => vvdecErrHandlingFlags_VVDEC_ERR_HANDLING_TRY_CONTINUE,
         }
     }
 }
 
+/// Forward a VVdeC log message to the `log` crate, under the `vvdec` target.
+///
+/// It can be passed directly to [`Decoder::set_logger`].
+#[cfg(feature = "log")]
+pub fn forward_to_log(level: LogLevel, message: &str) {
+    let level = match level {
+        LogLevel::Silent => return,
+        LogLevel::Error => log::Level::Error,
+        LogLevel::Warning => log::Level::Warn,
+        LogLevel::Info | LogLevel::Notice => log::Level::Info,
+        LogLevel::Verbose => log::Level::Debug,
+        LogLevel::Details => log::Level::Trace,
+    };
+    log::log!(target: "vvdec", level, "{message}");
+}
+
+/// Forward a VVdeC log message to the `tracing` crate, under the `vvdec` target.
+///
+/// It can be passed directly to [`Decoder::set_logger`].
+#[cfg(feature = "tracing")]
+pub fn forward_to_tracing(level: LogLevel, message: &str) {
+    match level {
+        LogLevel::Silent => {}
+        LogLevel::Error => tracing::error!(target: "vvdec", "{message}"),
+        LogLevel::Warning => tracing::warn!(target: "vvdec", "{message}"),
+        LogLevel::Info | LogLevel::Notice => tracing::info!(target: "vvdec", "{message}"),
+        LogLevel::Verbose => tracing::debug!(target: "vvdec", "{message}"),
+        LogLevel::Details => tracing::trace!(target: "vvdec", "{message}"),
+    }
+}
+
 /// An error that has occurred in VVdeC.
+///
+/// Errors returned by a decoder carry the [`ErrorDetails`] reported by VVdeC, except [`Error::TryAgain`] and
+/// [`Error::Eof`] whose details are always empty.
 #[derive(Debug, PartialEq, thiserror::Error)]
 pub enum Error {
     /// Failed to open decoder.
     #[error("failed to open decoder")]
     FailedToOpen,
     /// Unspecified malfunction.
-    #[error("unspecified malfunction")]
-    Unspecified,
+    #[error("unspecified malfunction{0}")]
+    Unspecified(ErrorDetails),
     /// Internal allocation error.
-    #[error("internal allocation error")]
-    Allocate,
+    #[error("internal allocation error{0}")]
+    Allocate(ErrorDetails),
     /// Decoder input error.
-    #[error("decoder input error")]
-    DecInput,
+    #[error("decoder input error{0}")]
+    DecInput(ErrorDetails),
     /// Allocated memory too small to receive decoded data. After allocating sufficient memory the failed call can be repeated.
-    #[error("allocated memory too small to receive decoded data. After allocating sufficient memory the failed call can be repeated.")]
-    EnoughMem,
+    #[error("allocated memory too small to receive decoded data. After allocating sufficient memory the failed call can be repeated.{0}")]
+    EnoughMem(ErrorDetails),
     /// Inconsistent or invalid parameters.
-    #[error("inconsistent or invalid parameters")]
-    Parameter,
+    #[error("inconsistent or invalid parameters{0}")]
+    Parameter(ErrorDetails),
     /// Unsupported request.
-    #[error("unsupported request")]
-    NotSupported,
+    #[error("unsupported request{0}")]
+    NotSupported(ErrorDetails),
     /// Decoder requires restart.
-    #[error("decoder requires restart")]
-    RestartRequired,
+    #[error("decoder requires restart{0}")]
+    RestartRequired(ErrorDetails),
     /// Unsupported CPU.
-    #[error("unsupported CPU")]
-    Cpu,
+    #[error("unsupported CPU{0}")]
+    Cpu(ErrorDetails),
     /// Decoder needs more input and cannot return a picture.
-    #[error("decoder needs more input and cannot return a picture")]
-    TryAgain,
+    #[error("decoder needs more input and cannot return a picture{0}")]
+    TryAgain(ErrorDetails),
     /// End of file.
-    #[error("end of file")]
-    Eof,
+    #[error("end of file{0}")]
+    Eof(ErrorDetails),
     /// Unknown error.
-    #[error("unknown error with code {0}")]
-    Unknown(i32),
+    #[error("unknown error with code {0}{1}")]
+    Unknown(i32, ErrorDetails),
 }
 
 impl Error {
-    fn new(code: i32) -> Error {
+    fn new(code: i32, details: ErrorDetails) -> Error {
         use Error::*;
         #[allow(non_upper_case_globals)]
         match code {
-            vvdecErrorCodes_VVDEC_ERR_UNSPECIFIED => Unspecified,
-            vvdecErrorCodes_VVDEC_ERR_ALLOCATE => Allocate,
-            vvdecErrorCodes_VVDEC_ERR_DEC_INPUT => DecInput,
-            vvdecErrorCodes_VVDEC_NOT_ENOUGH_MEM => EnoughMem,
-            vvdecErrorCodes_VVDEC_ERR_PARAMETER => Parameter,
-            vvdecErrorCodes_VVDEC_ERR_NOT_SUPPORTED => NotSupported,
-            vvdecErrorCodes_VVDEC_ERR_RESTART_REQUIRED => RestartRequired,
-            vvdecErrorCodes_VVDEC_ERR_CPU => Cpu,
-            vvdecErrorCodes_VVDEC_TRY_AGAIN => TryAgain,
-            vvdecErrorCodes_VVDEC_EOF => Eof,
-            _ => Unknown(code),
+            vvdecErrorCodes_VVDEC_ERR_UNSPECIFIED => Unspecified(details),
+            vvdecErrorCodes_VVDEC_ERR_INITIALIZE => FailedToOpen,
+            vvdecErrorCodes_VVDEC_ERR_ALLOCATE => Allocate(details),
+            vvdecErrorCodes_VVDEC_ERR_DEC_INPUT => DecInput(details),
+            vvdecErrorCodes_VVDEC_NOT_ENOUGH_MEM => EnoughMem(details),
+            vvdecErrorCodes_VVDEC_ERR_PARAMETER => Parameter(details),
+            vvdecErrorCodes_VVDEC_ERR_NOT_SUPPORTED => NotSupported(details),
+            vvdecErrorCodes_VVDEC_ERR_RESTART_REQUIRED => RestartRequired(details),
+            vvdecErrorCodes_VVDEC_ERR_CPU => Cpu(details),
+            vvdecErrorCodes_VVDEC_TRY_AGAIN => TryAgain(details),
+            vvdecErrorCodes_VVDEC_EOF => Eof(details),
+            _ => Unknown(code, details),
+        }
+    }
+
+    fn from_decoder(code: i32, decoder: *mut vvdecDecoder) -> Error {
+        // VVdeC does not clear the last error on success, so it would be stale for these non-errors.
+        if code == vvdecErrorCodes_VVDEC_TRY_AGAIN || code == vvdecErrorCodes_VVDEC_EOF {
+            return Error::new(code, ErrorDetails::default());
+        }
+
+        let to_string = |message: *const c_char| {
+            if message.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(message) }
+                    .to_string_lossy()
+                    .trim_end()
+                    .to_owned()
+            }
+        };
+
+        let details = unsafe {
+            ErrorDetails {
+                message: to_string(vvdec_get_last_error(decoder)),
+                additional: to_string(vvdec_get_last_additional_error(decoder)),
+            }
+        };
+        Error::new(code, details)
+    }
+
+    /// Get the raw VVdeC error code.
+    pub fn code(&self) -> i32 {
+        use Error::*;
+        match self {
+            FailedToOpen => vvdecErrorCodes_VVDEC_ERR_INITIALIZE,
+            Unspecified(_) => vvdecErrorCodes_VVDEC_ERR_UNSPECIFIED,
+            Allocate(_) => vvdecErrorCodes_VVDEC_ERR_ALLOCATE,
+            DecInput(_) => vvdecErrorCodes_VVDEC_ERR_DEC_INPUT,
+            EnoughMem(_) => vvdecErrorCodes_VVDEC_NOT_ENOUGH_MEM,
+            Parameter(_) => vvdecErrorCodes_VVDEC_ERR_PARAMETER,
+            NotSupported(_) => vvdecErrorCodes_VVDEC_ERR_NOT_SUPPORTED,
+            RestartRequired(_) => vvdecErrorCodes_VVDEC_ERR_RESTART_REQUIRED,
+            Cpu(_) => vvdecErrorCodes_VVDEC_ERR_CPU,
+            TryAgain(_) => vvdecErrorCodes_VVDEC_TRY_AGAIN,
+            Eof(_) => vvdecErrorCodes_VVDEC_EOF,
+            Unknown(code, _) => *code,
+        }
+    }
+
+    /// Get the details reported by VVdeC.
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        use Error::*;
+        match self {
+            FailedToOpen => None,
+            Unspecified(details)
+            | Allocate(details)
+            | DecInput(details)
+            | EnoughMem(details)
+            | Parameter(details)
+            | NotSupported(details)
+            | RestartRequired(details)
+            | Cpu(details)
+            | TryAgain(details)
+            | Eof(details)
+            | Unknown(_, details) => Some(details),
+        }
+    }
+}
+
+/// Details reported by VVdeC about an error.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorDetails {
+    /// Error message, from `vvdec_get_last_error`.
+    pub message: String,
+    /// Additional information, from `vvdec_get_last_additional_error`.
+    pub additional: String,
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+        if !self.additional.is_empty() {
+            write!(f, " ({})", self.additional)?;
         }
+        Ok(())
     }
 }
 
@@ -302,8 +1053,22 @@ pub struct Frame {
 
 impl Frame {
     fn from_raw(decoder: &Decoder, raw_frame: *mut vvdecFrame) -> Option<Self> {
-        ptr::NonNull::new(raw_frame).map(|f| Frame {
-            inner: Arc::new(InnerFrame::new(decoder.clone(), f)),
+        ptr::NonNull::new(raw_frame).map(|f| {
+            let (is_corrupted, profile_tier_level, cts, picture) = decoder
+                .inner
+                .lock()
+                .unwrap()
+                .output_state(unsafe { f.as_ref() });
+            Frame {
+                inner: Arc::new(InnerFrame::new(
+                    decoder.clone(),
+                    f,
+                    is_corrupted,
+                    profile_tier_level,
+                    cts,
+                    picture,
+                )),
+            }
         })
     }
 
@@ -318,15 +1083,37 @@ impl Frame {
     }
 
     /// Get the frame's width.
+    ///
+    /// With [`DecoderBuilder::upscale_output`] enabled, this is the maximum width of the sequence, regardless of
+    /// the width the picture was coded with.
     pub fn width(&self) -> u32 {
         self.inner.width
     }
 
     /// Get the frame's height.
+    ///
+    /// With [`DecoderBuilder::upscale_output`] enabled, this is the maximum height of the sequence, regardless of
+    /// the height the picture was coded with.
     pub fn height(&self) -> u32 {
         self.inner.height
     }
 
+    /// Get the width the picture was coded with, after cropping to the conformance window.
+    ///
+    /// It differs from [`Frame::width`] when the picture is upscaled with [`DecoderBuilder::upscale_output`]. It is
+    /// taken from the PPS referred to by the picture, and `None` is returned if it could not be parsed.
+    pub fn coded_width(&self) -> Option<u32> {
+        Some(self.inner.picture.as_ref()?.coded_size?.0)
+    }
+
+    /// Get the height the picture was coded with, after cropping to the conformance window.
+    ///
+    /// It differs from [`Frame::height`] when the picture is upscaled with [`DecoderBuilder::upscale_output`]. It is
+    /// taken from the PPS referred to by the picture, and `None` is returned if it could not be parsed.
+    pub fn coded_height(&self) -> Option<u32> {
+        Some(self.inner.picture.as_ref()?.coded_size?.1)
+    }
+
     /// Get the frame's bit depth.
     pub fn bit_depth(&self) -> u32 {
         self.inner.bitDepth
@@ -341,7 +1128,7 @@ impl Frame {
     ///
     /// This will match the cts that was set in the matching AccessUnit containing this frame.
     pub fn cts(&self) -> Option<u64> {
-        self.inner.ctsValid.then_some(self.inner.cts)
+        self.inner.cts
     }
 
     /// Get the frame's format.
@@ -356,8 +1143,158 @@ impl Frame {
 
     /// Get the frames's picture attributes.
     pub fn picture_attributes(&self) -> Option<PictureAttributes> {
-        ptr::NonNull::new(self.inner.picAttributes).map(PictureAttributes::new)
+        ptr::NonNull::new(self.inner.picAttributes).map(|pic_attributes| {
+            let mut picture_attributes = PictureAttributes {
+                profile_tier_level: self.profile_tier_level(),
+                ..PictureAttributes::new(pic_attributes)
+            };
+            if let (Some(hrd), Some(picture)) = (&mut picture_attributes.hrd, &self.inner.picture) {
+                hrd.sublayers.clone_from(&picture.hrd_sublayers);
+            }
+            picture_attributes
+        })
+    }
+
+    /// Get the profile, tier and level of the frame's sequence.
+    ///
+    /// They are parsed from the SPS, or from the VPS if the SPS does not carry them, passed to [`Decoder::decode`]
+    /// before the last IRAP or GDR picture was output. Returns `None` if no parameter set carried them.
+    pub fn profile_tier_level(&self) -> Option<nal::ProfileTierLevel> {
+        self.inner.profile_tier_level.clone()
+    }
+
+    /// Verify the frame against its decoded picture hash SEI message.
+    ///
+    /// Verification must be enabled with [`DecoderBuilder::verify_picture_hash`], otherwise
+    /// [`PictureHashStatus::NotVerified`] is returned.
+    ///
+    /// The hash is computed over the output planes, so it can only be verified when they hold the whole decoded
+    /// picture. Frames cropped to a conformance window, rescaled or copied into a larger frame by
+    /// [`DecoderBuilder::upscale_output`], or with film grain synthesised by
+    /// [`DecoderBuilder::film_grain_synthesis`] are reported as [`PictureHashStatus::NotVerified`], as are frames
+    /// whose picture could not be found in the pushed NAL units. VVdeC still verifies them, as reported by
+    /// [`Decoder::picture_hash_error_count`].
+    pub fn picture_hash_status(&self) -> PictureHashStatus {
+        if !self.inner.decoder.inner.lock().unwrap().verify_picture_hash {
+            return PictureHashStatus::NotVerified;
+        }
+
+        let Some(hash) = self.sei_messages().find_map(|message| match message {
+            SeiMessage::DecodedPictureHash(hash) => Some(hash),
+            _ => None,
+        }) else {
+            return PictureHashStatus::Absent;
+        };
+        let Some(method) = hash.method else {
+            return PictureHashStatus::Absent;
+        };
+        let is_decoded_picture = self.inner.picture.as_ref().is_some_and(|picture| {
+            picture.decoded_size == Some((self.width(), self.height())) && !picture.has_film_grain
+        });
+        if !is_decoded_picture {
+            return PictureHashStatus::NotVerified;
+        }
+
+        let num_planes = hash.digests.len().min(self.num_planes() as usize);
+        let matches =
+            (0..num_planes).all(|index| self.plane_digest(index, method) == hash.digests[index]);
+
+        if matches {
+            PictureHashStatus::Match
+        } else {
+            PictureHashStatus::Mismatch
+        }
+    }
+
+    fn plane_digest(&self, index: usize, method: PictureHashMethod) -> Vec<u8> {
+        let plane = self.inner.planes[index];
+        let samples = picture_hash::PlaneSamples {
+            data: unsafe {
+                std::slice::from_raw_parts(
+                    plane.ptr as *const u8,
+                    plane.stride as usize * plane.height as usize,
+                )
+            },
+            width: plane.width as usize,
+            height: plane.height as usize,
+            stride: plane.stride as usize,
+            bytes_per_sample: plane.bytesPerSample as usize,
+            bit_depth: self.bit_depth(),
+        };
+        picture_hash::compute(method, &samples)
+    }
+
+    /// Get the SEI messages attached to the frame, in bitstream order.
+    ///
+    /// They are parsed from the prefix SEI NAL units preceding the first slice of the picture and the suffix SEI
+    /// NAL units following it, as pushed to [`Decoder::decode`].
+    ///
+    /// If the picture could not be found in the pushed NAL units, for example because its picture header could not
+    /// be parsed, the messages that VVdeC attached to the frame are returned instead. VVdeC only keeps the first
+    /// message of each payload type, and only messages with a typed representation are returned then.
+    pub fn sei_messages(&self) -> impl Iterator<Item = SeiMessage> + '_ {
+        let frame_sei_messages = if self.inner.picture.is_none() {
+            self.frame_sei_messages()
+        } else {
+            Vec::new()
+        };
+        self.inner
+            .picture
+            .iter()
+            .flat_map(|picture| picture.sei_messages.iter().cloned())
+            .chain(frame_sei_messages)
+    }
+
+    fn frame_sei_messages(&self) -> Vec<SeiMessage> {
+        let decoder = self.inner.decoder.inner.lock().unwrap();
+        sei::VVDEC_PAYLOAD_TYPES
+            .iter()
+            .filter_map(|&payload_type| {
+                let sei = unsafe {
+                    vvdec_find_frame_sei(
+                        decoder.decoder.as_ptr(),
+                        payload_type,
+                        self.inner.frame.as_ptr(),
+                    )
+                };
+                SeiMessage::from_vvdec(unsafe { sei.as_ref()? })
+            })
+            .collect()
     }
+
+    /// Could the frame be corrupted by errors in the input bitstream?
+    ///
+    /// VVdeC does not report which pictures are affected by an error, so a frame is flagged when
+    /// [`Decoder::decode`] returned [`Error::DecInput`] or was given an [`AccessUnit::is_damaged`] access unit since
+    /// the last IRAP picture was output. Decoding errors are only reported with [`ErrorHandling::TryContinue`], as
+    /// the decoder stops on errors otherwise.
+    pub fn is_corrupted(&self) -> bool {
+        self.inner.is_corrupted
+    }
+
+    /// Get the film grain characteristics SEI message attached to the frame.
+    ///
+    /// Only the message carried in the frame's access unit is returned. Messages with
+    /// [`FilmGrainCharacteristics::persistence`] set also apply to subsequent frames, until cancelled.
+    pub fn film_grain_characteristics(&self) -> Option<FilmGrainCharacteristics> {
+        self.sei_messages().find_map(|message| match message {
+            SeiMessage::FilmGrainCharacteristics(film_grain) => Some(film_grain),
+            _ => None,
+        })
+    }
+}
+
+/// Result of verifying a frame against its decoded picture hash SEI message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictureHashStatus {
+    /// Verification is disabled, or the output frame is not the decoded picture that the hash applies to.
+    NotVerified,
+    /// The frame has no decoded picture hash SEI message.
+    Absent,
+    /// The frame matches the signalled hash.
+    Match,
+    /// The frame doesn't match the signalled hash.
+    Mismatch,
 }
 
 unsafe impl Send for Frame {}
@@ -367,6 +1304,10 @@ unsafe impl Sync for Frame {}
 struct InnerFrame {
     decoder: Decoder,
     frame: ptr::NonNull<vvdecFrame>,
+    is_corrupted: bool,
+    profile_tier_level: Option<nal::ProfileTierLevel>,
+    cts: Option<u64>,
+    picture: Option<PendingPicture>,
 }
 
 impl Deref for InnerFrame {
@@ -378,8 +1319,22 @@ impl Deref for InnerFrame {
 }
 
 impl InnerFrame {
-    fn new(decoder: Decoder, frame: ptr::NonNull<vvdecFrame>) -> Self {
-        Self { decoder, frame }
+    fn new(
+        decoder: Decoder,
+        frame: ptr::NonNull<vvdecFrame>,
+        is_corrupted: bool,
+        profile_tier_level: Option<nal::ProfileTierLevel>,
+        cts: Option<u64>,
+        picture: Option<PendingPicture>,
+    ) -> Self {
+        Self {
+            decoder,
+            frame,
+            is_corrupted,
+            profile_tier_level,
+            cts,
+            picture,
+        }
     }
 }
 
@@ -433,6 +1388,66 @@ impl Plane {
     pub fn bytes_per_sample(&self) -> u32 {
         self.inner().bytesPerSample
     }
+
+    /// Iterate over the rows of the plane, excluding the padding at the end of each row.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        let data = self.as_ref();
+        let stride = self.stride() as usize;
+        let row_len = (self.width() * self.bytes_per_sample()) as usize;
+        (0..self.height() as usize).map(move |row| &data[row * stride..row * stride + row_len])
+    }
+
+    /// Copy the plane into a contiguous buffer without padding.
+    pub fn to_contiguous(&self) -> Vec<u8> {
+        let mut data =
+            Vec::with_capacity((self.width() * self.height() * self.bytes_per_sample()) as usize);
+        for row in self.rows() {
+            data.extend_from_slice(row);
+        }
+        data
+    }
+
+    /// Get a typed view of the plane's samples, including the padding.
+    ///
+    /// Returns `None` if the size of `T` doesn't match [`Plane::bytes_per_sample`] or if the data is not aligned for
+    /// `T`. Rows start every `stride() / bytes_per_sample()` samples.
+    pub fn samples<T: Sample>(&self) -> Option<&[T]> {
+        let data = self.as_ref();
+        let sample_size = mem::size_of::<T>();
+        (self.bytes_per_sample() as usize == sample_size
+            && self.stride() as usize % sample_size == 0
+            && data.as_ptr().align_offset(mem::align_of::<T>()) == 0)
+            .then(|| unsafe {
+                std::slice::from_raw_parts(data.as_ptr() as *const T, data.len() / sample_size)
+            })
+    }
+
+    /// Get a view of the plane's 16-bit samples, as used for bit depths above 8.
+    ///
+    /// See [`Plane::samples`].
+    pub fn as_u16(&self) -> Option<&[u16]> {
+        self.samples()
+    }
+
+    /// Iterate over the rows of the plane as typed samples, excluding the padding at the end of each row.
+    ///
+    /// See [`Plane::samples`] for when `None` is returned.
+    pub fn sample_rows<T: Sample>(&self) -> Option<impl Iterator<Item = &[T]> + '_> {
+        let samples = self.samples::<T>()?;
+        let stride = self.stride() as usize / mem::size_of::<T>();
+        let width = self.width() as usize;
+        Some(
+            (0..self.height() as usize)
+                .map(move |row| &samples[row * stride..row * stride + width]),
+        )
+    }
+
+    /// Get the buffer backing the plane, if it was allocated by a [`FrameAllocator`] with buffers of type `B`.
+    pub fn buffer<B: 'static>(&self) -> Option<&B> {
+        let decoder = self.frame.inner.decoder.inner.lock().unwrap();
+        decoder.context.frame_allocator.as_ref()?;
+        unsafe { allocator::buffer_from_handle(self.inner().allocator) }
+    }
 }
 
 impl AsRef<[u8]> for Plane {
@@ -457,6 +1472,18 @@ impl Deref for Plane {
 unsafe impl Send for Plane {}
 unsafe impl Sync for Plane {}
 
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+}
+
+/// Type of the samples in a plane, either `u8` or `u16` in native endianness.
+pub trait Sample: private::Sealed + Copy + 'static {}
+
+impl Sample for u8 {}
+impl Sample for u16 {}
+
 /// A plane component
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlaneComponent {
@@ -469,6 +1496,16 @@ pub enum PlaneComponent {
 }
 
 impl PlaneComponent {
+    fn from_ffi(component: vvdecComponentType) -> Option<Self> {
+        #[allow(non_upper_case_globals)]
+        match component {
+            vvdecComponentType_VVDEC_CT_Y => Some(PlaneComponent::Y),
+            vvdecComponentType_VVDEC_CT_U => Some(PlaneComponent::U),
+            vvdecComponentType_VVDEC_CT_V => Some(PlaneComponent::V),
+            _ => None,
+        }
+    }
+
     #[inline]
     fn to_ffi(self) -> vvdecComponentType {
         match self {
@@ -504,6 +1541,8 @@ pub struct PictureAttributes {
     pub vui: Option<Vui>,
     /// HRD parameters
     pub hrd: Option<Hrd>,
+    /// Profile, tier and level, see [`Frame::profile_tier_level`]
+    pub profile_tier_level: Option<nal::ProfileTierLevel>,
 }
 
 impl PictureAttributes {
@@ -528,12 +1567,13 @@ impl PictureAttributes {
             num_compressed_bits: bits,
             vui: ptr::NonNull::new(vui).map(Vui::new),
             hrd: ptr::NonNull::new(hrd).map(Hrd::new),
+            profile_tier_level: None,
         }
     }
 }
 
 /// NAL type.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NalType {
     /// Coded slice trail.
     CodedSliceTrail,
@@ -613,6 +1653,34 @@ impl NalType {
             _ => Unknown(nal_type.try_into().unwrap()),
         }
     }
+
+    /// Is it an intra random access point (IRAP) picture?
+    pub fn is_irap(&self) -> bool {
+        use NalType::*;
+        // 11 is reserved for IRAP VCL NAL unit types.
+        matches!(
+            self,
+            CodedSliceIdrWRadl | CodedSliceIdrNLp | CodedSliceCra | Unknown(11)
+        )
+    }
+
+    /// Is it a video coding layer (VCL) NAL unit, containing a coded slice?
+    pub fn is_vcl(&self) -> bool {
+        use NalType::*;
+        // 4 to 6 and 11 are reserved for VCL NAL unit types.
+        matches!(
+            self,
+            CodedSliceTrail
+                | CodedSliceStsa
+                | CodedSliceRadl
+                | CodedSliceRasl
+                | CodedSliceIdrWRadl
+                | CodedSliceIdrNLp
+                | CodedSliceCra
+                | CodedSliceGdr
+                | Unknown(4..=6 | 11)
+        )
+    }
 }
 
 /// Slice type.
@@ -701,7 +1769,7 @@ impl FrameFormat {
 }
 
 /// Color format.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorFormat {
     /// Invalid.
     Invalid,
@@ -733,12 +1801,38 @@ impl ColorFormat {
 }
 
 /// HRD parameters.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Hrd {
     /// Number of units in tick.
     pub num_units_in_tick: u32,
     /// Time scale.
     pub time_scale: u32,
+    /// Are NAL HRD parameters present?
+    pub has_nal_hrd_params: bool,
+    /// Are VCL HRD parameters present?
+    pub has_vcl_hrd_params: bool,
+    /// Is the same picture-level timing information used in all output layer sets?
+    ///
+    /// This is `general_same_pic_timing_in_all_ols_flag`, which VVdeC reports as
+    /// `generalSameParamsForNalAndVclFlag`.
+    pub is_same_pic_timing_in_all_ols: bool,
+    /// Are decoding unit HRD parameters present?
+    pub has_decoding_unit_hrd_params: bool,
+    /// Tick divisor for decoding units, if decoding unit HRD parameters are present.
+    pub tick_divisor: Option<u32>,
+    /// Bit rate scale.
+    pub bit_rate_scale: u32,
+    /// CPB size scale.
+    pub cpb_size_scale: u32,
+    /// CPB size scale for decoding units, if decoding unit HRD parameters are present.
+    pub cpb_size_du_scale: Option<u32>,
+    /// Number of alternative CPB delivery schedules.
+    pub cpb_count: u32,
+    /// Timing and HRD parameters of the signalled temporal sublayers, from the lowest to the highest.
+    ///
+    /// As VVdeC does not report them, they are parsed from the SPS referred to by the frame's picture in the NAL
+    /// units pushed to [`Decoder::decode`]. They are empty if the SPS or the picture could not be parsed.
+    pub sublayers: Vec<SublayerHrd>,
 }
 
 impl Hrd {
@@ -747,16 +1841,89 @@ impl Hrd {
         let vvdecHrd {
             numUnitsInTick,
             timeScale,
+            generalNalHrdParamsPresentFlag,
+            generalVclHrdParamsPresentFlag,
+            generalSameParamsForNalAndVclFlag,
+            tickDivisorMinus2,
+            generalDecodingUnitHrdParamsPresentFlag,
+            bitRateScale,
+            cpbSizeScale,
+            cpbSizeDuScale,
+            hrdCpbCntMinus1,
             ..
         } = *hrd;
 
         Self {
             num_units_in_tick: numUnitsInTick,
             time_scale: timeScale,
+            has_nal_hrd_params: generalNalHrdParamsPresentFlag,
+            has_vcl_hrd_params: generalVclHrdParamsPresentFlag,
+            // H.266 has no flag for the same NAL and VCL HRD parameters: VVdeC reports the flag that follows
+            // the NAL and VCL presence flags in general_timing_hrd_parameters() under this name.
+            is_same_pic_timing_in_all_ols: generalSameParamsForNalAndVclFlag,
+            has_decoding_unit_hrd_params: generalDecodingUnitHrdParamsPresentFlag,
+            tick_divisor: generalDecodingUnitHrdParamsPresentFlag.then_some(tickDivisorMinus2 + 2),
+            bit_rate_scale: bitRateScale,
+            cpb_size_scale: cpbSizeScale,
+            cpb_size_du_scale: generalDecodingUnitHrdParamsPresentFlag.then_some(cpbSizeDuScale),
+            cpb_count: hrdCpbCntMinus1 + 1,
+            sublayers: Vec::new(),
+        }
+    }
+
+    /// Get the frame rate as a (numerator, denominator) pair.
+    ///
+    /// `elemental_duration_in_tc` is the number of clock ticks per picture, as signalled in the OLS timing and HRD
+    /// parameters, see [`SublayerHrd::elemental_duration_in_tc`]. It is 1 for streams with fixed picture rate and no
+    /// field coding.
+    ///
+    /// Returns `None` if the time scale, the number of units in tick or `elemental_duration_in_tc` is 0.
+    pub fn frame_rate(&self, elemental_duration_in_tc: u32) -> Option<(u64, u64)> {
+        if self.time_scale == 0 || self.num_units_in_tick == 0 || elemental_duration_in_tc == 0 {
+            return None;
         }
+        Some((
+            self.time_scale as u64,
+            self.num_units_in_tick as u64 * elemental_duration_in_tc as u64,
+        ))
     }
 }
 
+/// Timing and HRD parameters of a temporal sublayer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SublayerHrd {
+    /// Temporal ID of the sublayer.
+    pub temporal_id: u8,
+    /// Is the picture rate fixed across CVSs?
+    pub is_fixed_pic_rate_general: bool,
+    /// Is the picture rate fixed within a CVS?
+    pub is_fixed_pic_rate_within_cvs: bool,
+    /// Number of clock ticks per picture, if the picture rate is fixed within a CVS.
+    pub elemental_duration_in_tc: Option<u32>,
+    /// Is the HRD operating in low-delay mode?
+    pub is_low_delay: bool,
+    /// NAL HRD parameters of each CPB delivery schedule, if present.
+    pub nal_cpb_params: Vec<CpbParams>,
+    /// VCL HRD parameters of each CPB delivery schedule, if present.
+    pub vcl_cpb_params: Vec<CpbParams>,
+}
+
+/// HRD parameters of a CPB delivery schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpbParams {
+    /// Maximum input bit rate of the CPB, in bits per second.
+    pub bit_rate: u64,
+    /// CPB size, in bits.
+    pub cpb_size: u64,
+    /// CPB size for decoding units, in bits, if decoding unit HRD parameters are present.
+    pub cpb_size_du: Option<u64>,
+    /// Maximum input bit rate of the CPB for decoding units, in bits per second, if decoding unit HRD parameters
+    /// are present.
+    pub bit_rate_du: Option<u64>,
+    /// Does the HRD operate in constant bit rate mode?
+    pub is_cbr: bool,
+}
+
 /// Sample Aspect Ratio.
 #[derive(Debug, PartialEq)]
 pub enum SampleAspectRatio {
@@ -791,6 +1958,20 @@ pub struct Vui {
     pub sample_aspect_ratio: Option<SampleAspectRatio>,
     /// Is sample aspect ratio constant?
     pub is_aspect_ratio_constant: bool,
+    /// Is the source progressive?
+    pub is_progressive_source: bool,
+    /// Is the source interlaced?
+    pub is_interlaced_source: bool,
+    /// Are frame packing arrangement SEI messages absent from the stream?
+    pub has_non_packed_constraint: bool,
+    /// Are projection SEI messages absent from the stream?
+    pub has_non_projected_constraint: bool,
+    /// Is the picture suitable for display with overscan, if signalled?
+    pub is_overscan_appropriate: Option<bool>,
+    /// Colour description.
+    pub colour_description: Option<ColourDescription>,
+    /// Chroma sample location.
+    pub chroma_sample_location: Option<ChromaSampleLocation>,
 }
 
 impl Vui {
@@ -800,14 +1981,39 @@ impl Vui {
         let vvdecVui {
             aspectRatioInfoPresentFlag,
             aspectRatioConstantFlag,
-            nonPackedFlag: _,
-            nonProjectedFlag: _,
+            nonPackedFlag,
+            nonProjectedFlag,
             aspectRatioIdc,
             sarWidth,
             sarHeight,
+            colourDescriptionPresentFlag,
+            colourPrimaries,
+            transferCharacteristics,
+            matrixCoefficients,
+            progressiveSourceFlag,
+            interlacedSourceFlag,
+            chromaLocInfoPresentFlag,
+            chromaSampleLocTypeTopField,
+            chromaSampleLocTypeBottomField,
+            chromaSampleLocType,
+            overscanInfoPresentFlag,
+            overscanAppropriateFlag,
+            videoFullRangeFlag,
             ..
         } = *vui;
 
+        // The chroma sample location is signalled once per frame for progressive sources, and per field otherwise.
+        let chroma_sample_location = chromaLocInfoPresentFlag.then(|| {
+            if progressiveSourceFlag && !interlacedSourceFlag {
+                ChromaSampleLocation::Frame(ChromaLocation::new(chromaSampleLocType))
+            } else {
+                ChromaSampleLocation::Fields {
+                    top_field: ChromaLocation::new(chromaSampleLocTypeTopField),
+                    bottom_field: ChromaLocation::new(chromaSampleLocTypeBottomField),
+                }
+            }
+        });
+
         Self {
             sample_aspect_ratio: aspectRatioInfoPresentFlag.then_some(SampleAspectRatio::new(
                 aspectRatioIdc,
@@ -815,6 +2021,256 @@ impl Vui {
                 sarHeight,
             )),
             is_aspect_ratio_constant: aspectRatioConstantFlag,
+            is_progressive_source: progressiveSourceFlag,
+            is_interlaced_source: interlacedSourceFlag,
+            has_non_packed_constraint: nonPackedFlag,
+            has_non_projected_constraint: nonProjectedFlag,
+            is_overscan_appropriate: overscanInfoPresentFlag.then_some(overscanAppropriateFlag),
+            colour_description: colourDescriptionPresentFlag.then_some(ColourDescription {
+                colour_primaries: ColourPrimaries::new(colourPrimaries),
+                transfer_characteristics: TransferCharacteristics::new(transferCharacteristics),
+                matrix_coefficients: MatrixCoefficients::new(matrixCoefficients),
+                is_full_range: videoFullRangeFlag,
+            }),
+            chroma_sample_location,
+        }
+    }
+}
+
+/// Colour description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColourDescription {
+    /// Colour primaries.
+    pub colour_primaries: ColourPrimaries,
+    /// Transfer characteristics.
+    pub transfer_characteristics: TransferCharacteristics,
+    /// Matrix coefficients.
+    pub matrix_coefficients: MatrixCoefficients,
+    /// Is it full range?
+    pub is_full_range: bool,
+}
+
+/// Colour primaries, as specified in Rec. ITU-T H.273.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourPrimaries {
+    /// Rec. ITU-R BT.709.
+    Bt709,
+    /// Unspecified.
+    Unspecified,
+    /// Rec. ITU-R BT.470 System M.
+    Bt470M,
+    /// Rec. ITU-R BT.470 System B, G.
+    Bt470Bg,
+    /// Rec. ITU-R BT.601 525 lines, also SMPTE 170M.
+    Bt601,
+    /// SMPTE 240M.
+    Smpte240,
+    /// Generic film.
+    GenericFilm,
+    /// Rec. ITU-R BT.2020 and BT.2100.
+    Bt2020,
+    /// SMPTE ST 428-1 (CIE 1931 XYZ).
+    Xyz,
+    /// SMPTE RP 431-2 (DCI-P3).
+    Smpte431,
+    /// SMPTE EG 432-1 (Display P3).
+    Smpte432,
+    /// EBU Tech. 3213-E.
+    Ebu3213,
+    /// Unknown or reserved.
+    Unknown(i32),
+}
+
+impl ColourPrimaries {
+    fn new(colour_primaries: i32) -> Self {
+        use ColourPrimaries::*;
+        match colour_primaries {
+            1 => Bt709,
+            2 => Unspecified,
+            4 => Bt470M,
+            5 => Bt470Bg,
+            6 => Bt601,
+            7 => Smpte240,
+            8 => GenericFilm,
+            9 => Bt2020,
+            10 => Xyz,
+            11 => Smpte431,
+            12 => Smpte432,
+            22 => Ebu3213,
+            _ => Unknown(colour_primaries),
+        }
+    }
+}
+
+/// Transfer characteristics, as specified in Rec. ITU-T H.273.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferCharacteristics {
+    /// Rec. ITU-R BT.709.
+    Bt709,
+    /// Unspecified.
+    Unspecified,
+    /// Rec. ITU-R BT.470 System M (gamma 2.2).
+    Bt470M,
+    /// Rec. ITU-R BT.470 System B, G (gamma 2.8).
+    Bt470Bg,
+    /// Rec. ITU-R BT.601, also SMPTE 170M.
+    Bt601,
+    /// SMPTE 240M.
+    Smpte240,
+    /// Linear.
+    Linear,
+    /// Logarithmic, 100:1 range.
+    Log100,
+    /// Logarithmic, 100 * sqrt(10):1 range.
+    Log100Sqrt10,
+    /// IEC 61966-2-4.
+    Iec61966_2_4,
+    /// Rec. ITU-R BT.1361 extended colour gamut.
+    Bt1361,
+    /// IEC 61966-2-1 (sRGB or sYCC).
+    Srgb,
+    /// Rec. ITU-R BT.2020 for 10-bit systems.
+    Bt2020_10,
+    /// Rec. ITU-R BT.2020 for 12-bit systems.
+    Bt2020_12,
+    /// SMPTE ST 2084, also Rec. ITU-R BT.2100 perceptual quantization (PQ).
+    Pq,
+    /// SMPTE ST 428-1.
+    Smpte428,
+    /// ARIB STD-B67, also Rec. ITU-R BT.2100 hybrid log-gamma (HLG).
+    Hlg,
+    /// Unknown or reserved.
+    Unknown(i32),
+}
+
+impl TransferCharacteristics {
+    fn new(transfer_characteristics: i32) -> Self {
+        use TransferCharacteristics::*;
+        match transfer_characteristics {
+            1 => Bt709,
+            2 => Unspecified,
+            4 => Bt470M,
+            5 => Bt470Bg,
+            6 => Bt601,
+            7 => Smpte240,
+            8 => Linear,
+            9 => Log100,
+            10 => Log100Sqrt10,
+            11 => Iec61966_2_4,
+            12 => Bt1361,
+            13 => Srgb,
+            14 => Bt2020_10,
+            15 => Bt2020_12,
+            16 => Pq,
+            17 => Smpte428,
+            18 => Hlg,
+            _ => Unknown(transfer_characteristics),
+        }
+    }
+}
+
+/// Matrix coefficients, as specified in Rec. ITU-T H.273.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixCoefficients {
+    /// Identity (GBR or RGB).
+    Identity,
+    /// Rec. ITU-R BT.709.
+    Bt709,
+    /// Unspecified.
+    Unspecified,
+    /// United States Federal Communications Commission.
+    Fcc,
+    /// Rec. ITU-R BT.470 System B, G.
+    Bt470Bg,
+    /// Rec. ITU-R BT.601, also SMPTE 170M.
+    Bt601,
+    /// SMPTE 240M.
+    Smpte240,
+    /// YCgCo.
+    YCgCo,
+    /// Rec. ITU-R BT.2020 non-constant luminance.
+    Bt2020Ncl,
+    /// Rec. ITU-R BT.2020 constant luminance.
+    Bt2020Cl,
+    /// SMPTE ST 2085.
+    Smpte2085,
+    /// Chromaticity-derived non-constant luminance.
+    ChromaDerivedNcl,
+    /// Chromaticity-derived constant luminance.
+    ChromaDerivedCl,
+    /// Rec. ITU-R BT.2100 ICtCp.
+    ICtCp,
+    /// Unknown or reserved.
+    Unknown(i32),
+}
+
+impl MatrixCoefficients {
+    fn new(matrix_coefficients: i32) -> Self {
+        use MatrixCoefficients::*;
+        match matrix_coefficients {
+            0 => Identity,
+            1 => Bt709,
+            2 => Unspecified,
+            4 => Fcc,
+            5 => Bt470Bg,
+            6 => Bt601,
+            7 => Smpte240,
+            8 => YCgCo,
+            9 => Bt2020Ncl,
+            10 => Bt2020Cl,
+            11 => Smpte2085,
+            12 => ChromaDerivedNcl,
+            13 => ChromaDerivedCl,
+            14 => ICtCp,
+            _ => Unknown(matrix_coefficients),
+        }
+    }
+}
+
+/// Chroma sample location.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChromaSampleLocation {
+    /// Location for progressive sources.
+    Frame(ChromaLocation),
+    /// Location for each field.
+    Fields {
+        /// Location in the top field.
+        top_field: ChromaLocation,
+        /// Location in the bottom field.
+        bottom_field: ChromaLocation,
+    },
+}
+
+/// Location of chroma samples relative to luma samples, as specified in Rec. ITU-T H.273.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChromaLocation {
+    /// Left.
+    Left,
+    /// Center.
+    Center,
+    /// Top-left.
+    TopLeft,
+    /// Top.
+    Top,
+    /// Bottom-left.
+    BottomLeft,
+    /// Bottom.
+    Bottom,
+    /// Unknown.
+    Unknown(i32),
+}
+
+impl ChromaLocation {
+    fn new(chroma_sample_loc_type: i32) -> Self {
+        use ChromaLocation::*;
+        match chroma_sample_loc_type {
+            0 => Left,
+            1 => Center,
+            2 => TopLeft,
+            3 => Top,
+            4 => BottomLeft,
+            5 => Bottom,
+            _ => Unknown(chroma_sample_loc_type),
         }
     }
 }
@@ -827,4 +2283,260 @@ mod tests {
     fn test_decoder_builder() {
         DecoderBuilder::new().num_threads(4).build().unwrap();
     }
+
+    #[test]
+    fn test_scalar_decoder() {
+        const DATA: &[u8] = include_bytes!("../tests/short.vvc");
+        let decode = |simd| {
+            let mut decoder = DecoderBuilder::new().simd(simd).build().unwrap();
+            let _ = decoder.decode(DATA);
+            let mut planes = Vec::new();
+            while let Some(frame) = decoder.flush().unwrap() {
+                for component in [PlaneComponent::Y, PlaneComponent::U, PlaneComponent::V] {
+                    planes.push(frame.plane(component).unwrap().to_contiguous());
+                }
+            }
+            planes
+        };
+
+        // The scalar code must decode the same samples as the SIMD code.
+        let planes = decode(SimdExtension::Scalar);
+        assert_eq!(planes.len(), 9);
+        assert!(planes == decode(SimdExtension::Default));
+    }
+
+    #[test]
+    fn test_picture_hash_status_with_sei() {
+        const DATA: &[u8] = include_bytes!("../tests/short.vvc");
+        // Hash type of the methods, used for the pictures of order count 0, 1 and 2.
+        let methods = [
+            (PictureHashMethod::Md5, 0),
+            (PictureHashMethod::Crc, 1),
+            (PictureHashMethod::Checksum, 2),
+        ];
+
+        // Digests of the pictures computed from a first decoding, in output order. VVdeC verifies the decoded
+        // pictures against them independently below.
+        let mut decoder = Decoder::new().unwrap();
+        let _ = decoder.decode(DATA);
+        let mut digests = Vec::new();
+        while let Some(frame) = decoder.flush().unwrap() {
+            let (method, _) = methods[digests.len()];
+            digests.push(
+                (0..frame.num_planes() as usize)
+                    .map(|index| frame.plane_digest(index, method))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(digests.len(), 3);
+
+        // Appends a decoded picture hash SEI NAL unit to each slice, with a digest byte of a picture flipped.
+        let with_hash_sei = |flipped_poc: Option<usize>| {
+            let nal_units: Vec<_> = nal::AnnexBSplitter::new(DATA).collect();
+            let mut data = nal_units[..3].concat();
+            for (slice, poc) in nal_units[3..].iter().zip([0, 2, 1]) {
+                let (_, hash_type) = methods[poc];
+                let mut payload = vec![hash_type, 0];
+                for digest in &digests[poc] {
+                    payload.extend_from_slice(digest);
+                }
+                if flipped_poc == Some(poc) {
+                    payload[2] ^= 1;
+                }
+
+                let header = nal::strip_start_code(slice);
+                let mut rbsp = vec![132, payload.len() as u8];
+                rbsp.extend_from_slice(&payload);
+                rbsp.push(0x80);
+                data.extend_from_slice(slice);
+                data.extend_from_slice(&[0, 0, 0, 1, header[0], 24 << 3 | header[1] & 0x7]);
+                let mut num_zeros = 0;
+                for byte in rbsp {
+                    if num_zeros == 2 && byte <= 3 {
+                        data.push(3);
+                        num_zeros = 0;
+                    }
+                    data.push(byte);
+                    num_zeros = if byte == 0 { num_zeros + 1 } else { 0 };
+                }
+            }
+            data
+        };
+
+        for flipped_poc in [None, Some(2)] {
+            let mut decoder = Decoder::builder()
+                .verify_picture_hash(true)
+                .build()
+                .unwrap();
+            let _ = decoder.decode(with_hash_sei(flipped_poc));
+            let mut statuses = Vec::new();
+            while let Some(frame) = decoder.flush().unwrap() {
+                statuses.push(frame.picture_hash_status());
+            }
+            let mut expected = [PictureHashStatus::Match; 3];
+            if let Some(poc) = flipped_poc {
+                expected[poc] = PictureHashStatus::Mismatch;
+            }
+            assert_eq!(statuses, expected);
+            assert_eq!(
+                decoder.picture_hash_error_count(),
+                flipped_poc.is_some() as u32
+            );
+        }
+    }
+
+    #[test]
+    fn test_cts_without_picture_header() {
+        const DATA: &[u8] = include_bytes!("../tests/short.vvc");
+        let mut decoder = Decoder::new().unwrap();
+        let mut cts = Vec::new();
+        for (index, nal_unit) in nal::AnnexBSplitter::new(DATA).enumerate() {
+            if index == 3 {
+                // Forget the PPS so that picture headers cannot be parsed.
+                decoder.inner.lock().unwrap().pps.clear();
+            }
+            let access_unit = AccessUnit {
+                cts: Some(100 + index as u64),
+                ..AccessUnit::new(nal_unit)
+            };
+            if let Some(frame) = decoder.decode(access_unit).unwrap() {
+                cts.push(frame.cts());
+            }
+        }
+        while let Some(frame) = decoder.flush().unwrap() {
+            cts.push(frame.cts());
+        }
+        // The pictures of order count 0, 2 and 1 are in the NAL units 3, 4 and 5.
+        assert_eq!(cts, [Some(103), Some(105), Some(104)]);
+    }
+
+    #[test]
+    fn test_access_unit_cts() {
+        let mut access_unit_cts = AccessUnitCts::default();
+        let first_id = access_unit_cts.push(Some(42));
+        let second_id = access_unit_cts.push(None);
+        assert_eq!(access_unit_cts.get(first_id), Some(42));
+        assert_eq!(access_unit_cts.get(second_id), None);
+        assert_eq!(access_unit_cts.get(second_id + 1), None);
+
+        for cts in 0..MAX_ACCESS_UNITS as u64 {
+            access_unit_cts.push(Some(cts));
+        }
+        assert_eq!(access_unit_cts.get(first_id), None);
+        assert_eq!(access_unit_cts.get(second_id + 1), Some(0));
+    }
+
+    #[test]
+    fn test_log_level_roundtrip() {
+        for level in [
+            LogLevel::Silent,
+            LogLevel::Error,
+            LogLevel::Warning,
+            LogLevel::Info,
+            LogLevel::Notice,
+            LogLevel::Verbose,
+            LogLevel::Details,
+        ] {
+            assert_eq!(LogLevel::new(level.to_ffi() as c_int), level);
+        }
+    }
+
+    #[test]
+    fn test_error_code_roundtrip() {
+        for code in [
+            vvdecErrorCodes_VVDEC_ERR_UNSPECIFIED,
+            vvdecErrorCodes_VVDEC_ERR_INITIALIZE,
+            vvdecErrorCodes_VVDEC_ERR_ALLOCATE,
+            vvdecErrorCodes_VVDEC_ERR_DEC_INPUT,
+            vvdecErrorCodes_VVDEC_NOT_ENOUGH_MEM,
+            vvdecErrorCodes_VVDEC_ERR_PARAMETER,
+            vvdecErrorCodes_VVDEC_ERR_NOT_SUPPORTED,
+            vvdecErrorCodes_VVDEC_ERR_RESTART_REQUIRED,
+            vvdecErrorCodes_VVDEC_ERR_CPU,
+            vvdecErrorCodes_VVDEC_TRY_AGAIN,
+            vvdecErrorCodes_VVDEC_EOF,
+            -100,
+        ] {
+            assert_eq!(Error::new(code, ErrorDetails::default()).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_error_from_decoder_without_details() {
+        // The decoder is not queried for these codes.
+        for code in [vvdecErrorCodes_VVDEC_TRY_AGAIN, vvdecErrorCodes_VVDEC_EOF] {
+            let error = Error::from_decoder(code, ptr::null_mut());
+            assert_eq!(error.details(), Some(&ErrorDetails::default()));
+        }
+    }
+
+    #[test]
+    fn test_error_details_display() {
+        let error = Error::new(
+            vvdecErrorCodes_VVDEC_ERR_DEC_INPUT,
+            ErrorDetails {
+                message: "invalid NAL unit".to_owned(),
+                additional: "nal type 31".to_owned(),
+            },
+        );
+        assert_eq!(
+            error.to_string(),
+            "decoder input error: invalid NAL unit (nal type 31)"
+        );
+        assert_eq!(error.details().unwrap().message, "invalid NAL unit");
+
+        let error = Error::new(vvdecErrorCodes_VVDEC_TRY_AGAIN, ErrorDetails::default());
+        assert_eq!(
+            error.to_string(),
+            "decoder needs more input and cannot return a picture"
+        );
+    }
+
+    #[test]
+    fn test_vui_colour_description() {
+        let mut vui: vvdecVui = unsafe { mem::zeroed() };
+        vui.colourDescriptionPresentFlag = true;
+        vui.colourPrimaries = 9;
+        vui.transferCharacteristics = 16;
+        vui.matrixCoefficients = 9;
+        vui.videoFullRangeFlag = false;
+        vui.progressiveSourceFlag = true;
+        vui.chromaLocInfoPresentFlag = true;
+        vui.chromaSampleLocType = 2;
+
+        let vui = Vui::new(ptr::NonNull::from(&mut vui));
+        assert_eq!(
+            vui.colour_description,
+            Some(ColourDescription {
+                colour_primaries: ColourPrimaries::Bt2020,
+                transfer_characteristics: TransferCharacteristics::Pq,
+                matrix_coefficients: MatrixCoefficients::Bt2020Ncl,
+                is_full_range: false,
+            })
+        );
+        assert_eq!(
+            vui.chroma_sample_location,
+            Some(ChromaSampleLocation::Frame(ChromaLocation::TopLeft))
+        );
+        assert_eq!(vui.is_overscan_appropriate, None);
+    }
+
+    #[test]
+    fn test_hrd() {
+        let mut hrd: vvdecHrd = unsafe { mem::zeroed() };
+        hrd.numUnitsInTick = 1001;
+        hrd.timeScale = 60000;
+        hrd.generalNalHrdParamsPresentFlag = true;
+        hrd.tickDivisorMinus2 = 3;
+        hrd.hrdCpbCntMinus1 = 1;
+
+        let hrd = Hrd::new(ptr::NonNull::from(&mut hrd));
+        assert!(hrd.has_nal_hrd_params);
+        assert!(!hrd.has_vcl_hrd_params);
+        assert_eq!(hrd.tick_divisor, None);
+        assert_eq!(hrd.cpb_count, 2);
+        assert!(hrd.sublayers.is_empty());
+        assert_eq!(hrd.frame_rate(2), Some((60000, 2002)));
+        assert_eq!(hrd.frame_rate(0), None);
+    }
 }
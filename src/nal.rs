@@ -0,0 +1,546 @@
+//! Utilities for working with VVC NAL units and parsing parameter sets, picture headers and SEI messages.
+
+use std::{
+    io::{self, Read},
+    mem,
+};
+
+use crate::{AccessUnit, NalType};
+
+mod length_prefixed;
+mod parameter_sets;
+mod picture_header;
+mod profile_tier_level;
+mod rbsp;
+mod rtp;
+mod sei;
+
+pub use length_prefixed::*;
+pub use parameter_sets::*;
+pub use picture_header::*;
+pub use profile_tier_level::*;
+pub use rbsp::{ParseError, RbspReader};
+pub use rtp::*;
+pub use sei::*;
+
+const START_CODE: &[u8] = &[0, 0, 1];
+const PAGE_SIZE: usize = 16 * 1024;
+
+/// Splitter of an Annex-B byte stream into NAL units.
+///
+/// Each NAL unit is yielded with its start code, either 0x000001 or 0x00000001, so that it can be passed directly to
+/// [`Decoder::decode`](crate::Decoder::decode). Leading and trailing zero bytes are not included, and data before
+/// the first start code is skipped.
+///
+/// ```
+/// use vvdec::nal::AnnexBSplitter;
+///
+/// let data = [0, 0, 0, 1, 0, 0x79, 0xaa, 0, 0, 1, 0, 0x81, 0xbb, 0];
+/// let nal_units: Vec<&[u8]> = AnnexBSplitter::new(&data).collect();
+/// assert_eq!(nal_units, [&data[..7], &data[7..13]]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnnexBSplitter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> AnnexBSplitter<'a> {
+    /// Create a splitter over a complete byte stream.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Create a splitter reading the byte stream from a reader.
+    pub fn from_reader<R: Read>(reader: R) -> AnnexBReader<R> {
+        AnnexBReader::new(reader)
+    }
+}
+
+impl<'a> Iterator for AnnexBSplitter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(start_code) = find_start_code(self.data, 0) else {
+                self.data = &[];
+                return None;
+            };
+
+            let payload_start = start_code + START_CODE.len();
+            let next_start_code =
+                find_start_code(self.data, payload_start).unwrap_or(self.data.len());
+            let end = trim_trailing_zeros(self.data, payload_start, next_start_code);
+
+            let data = self.data;
+            self.data = &data[end..];
+            if end > payload_start {
+                return Some(&data[unit_start(data, start_code)..end]);
+            }
+        }
+    }
+}
+
+/// Splitter of an Annex-B byte stream read from a [`Read`] source into NAL units.
+///
+/// NAL units are split the same way as with [`AnnexBSplitter`]. They are borrowed from an internal buffer, which
+/// grows to fit the largest NAL unit of the stream.
+#[derive(Debug)]
+pub struct AnnexBReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    // Start of the data not yielded yet.
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> AnnexBReader<R> {
+    /// Create a reader from a byte stream source.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::with_capacity(PAGE_SIZE),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Read the next NAL unit.
+    ///
+    /// Returns `Ok(None)` at the end of the stream.
+    pub fn next_nal_unit(&mut self) -> io::Result<Option<&[u8]>> {
+        loop {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+
+            let start_code = loop {
+                if let Some(start_code) = find_start_code(&self.buffer, 0) {
+                    break start_code;
+                }
+                if self.eof {
+                    self.buffer.clear();
+                    return Ok(None);
+                }
+                // Keep the bytes that may belong to a four-byte start code split across reads.
+                let garbage = self.buffer.len().saturating_sub(START_CODE.len());
+                self.buffer.drain(..garbage);
+                self.fill()?;
+            };
+
+            let payload_start = start_code + START_CODE.len();
+            let mut scan_from = payload_start;
+            let next_start_code = loop {
+                if let Some(next_start_code) = find_start_code(&self.buffer, scan_from) {
+                    break next_start_code;
+                }
+                if self.eof {
+                    break self.buffer.len();
+                }
+                scan_from = scan_from.max(self.buffer.len().saturating_sub(START_CODE.len() - 1));
+                self.fill()?;
+            };
+
+            let end = trim_trailing_zeros(&self.buffer, payload_start, next_start_code);
+            self.pos = end;
+            if end > payload_start {
+                return Ok(Some(
+                    &self.buffer[unit_start(&self.buffer, start_code)..end],
+                ));
+            }
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let len = self.buffer.len();
+        self.buffer.resize(len + PAGE_SIZE, 0);
+        let result = loop {
+            match self.reader.read(&mut self.buffer[len..]) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let num_read = *result.as_ref().unwrap_or(&0);
+        self.buffer.truncate(len + num_read);
+        self.eof = num_read == 0;
+        result.map(|_| ())
+    }
+}
+
+/// NAL unit header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NalUnitHeader {
+    /// NAL unit type.
+    pub nal_type: NalType,
+    /// Layer ID (`nuh_layer_id`).
+    pub layer_id: u8,
+    /// Temporal ID (`TemporalId`).
+    pub temporal_id: u8,
+}
+
+impl NalUnitHeader {
+    /// Size of the header in bytes.
+    pub const SIZE: usize = 2;
+
+    /// Parse the header of a NAL unit, with or without its start code.
+    ///
+    /// Returns `None` if the data is too short or the header is invalid.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let &[first, second, ..] = strip_start_code(data) else {
+            return None;
+        };
+
+        let forbidden_zero_bit = first >> 7;
+        let temporal_id_plus1 = second & 0x7;
+        if forbidden_zero_bit != 0 || temporal_id_plus1 == 0 {
+            return None;
+        }
+
+        Some(Self {
+            nal_type: NalType::new((second >> 3).into()),
+            layer_id: first & 0x3f,
+            temporal_id: temporal_id_plus1 - 1,
+        })
+    }
+}
+
+/// Assembler of NAL units into access units.
+///
+/// Access unit boundaries are detected following H.266 section 7.4.2.4: an access unit delimiter, a parameter set, a
+/// prefix APS, a picture header, a prefix SEI message or the first slice of a picture that follow a slice of the
+/// current access unit start a new one. Access units containing an IRAP or GDR picture are flagged as random access
+/// points.
+///
+/// ```
+/// use vvdec::nal::{AccessUnitAssembler, AnnexBSplitter};
+/// # let data = &[];
+///
+/// let mut assembler = AccessUnitAssembler::new();
+/// for nal_unit in AnnexBSplitter::new(data) {
+///     if let Some(access_unit) = assembler.push(nal_unit) {
+///         // Decode the access unit.
+///     }
+/// }
+/// if let Some(access_unit) = assembler.flush() {
+///     // Decode the last access unit.
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct AccessUnitAssembler {
+    payload: Vec<u8>,
+    is_random_access_point: bool,
+    // Layer ID of the last slice of the current access unit, if any.
+    last_vcl_layer_id: Option<u8>,
+    // Whether a picture header was pushed after the last slice.
+    has_picture_header: bool,
+}
+
+impl AccessUnitAssembler {
+    /// Create a new assembler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Push a NAL unit, with or without its start code.
+    ///
+    /// Returns the previous access unit if the NAL unit starts a new one. NAL units with an invalid header are added
+    /// to the current access unit.
+    pub fn push(&mut self, nal_unit: &[u8]) -> Option<AccessUnit<Vec<u8>>> {
+        let nal_unit = strip_start_code(nal_unit);
+        let mut access_unit = None;
+        if let Some(header) = NalUnitHeader::parse(nal_unit) {
+            if self.starts_access_unit(&header, nal_unit) {
+                access_unit = self.flush();
+            }
+            self.update(&header);
+        }
+
+        self.payload.extend_from_slice(&[0, 0, 0, 1]);
+        self.payload.extend_from_slice(nal_unit);
+        access_unit
+    }
+
+    /// Take the current access unit, if any NAL unit was pushed since the last one.
+    pub fn flush(&mut self) -> Option<AccessUnit<Vec<u8>>> {
+        if self.payload.is_empty() {
+            return None;
+        }
+
+        let access_unit = AccessUnit {
+            payload: mem::take(&mut self.payload),
+            cts: None,
+            dts: None,
+            is_random_access_point: self.is_random_access_point,
+            is_damaged: false,
+        };
+        self.is_random_access_point = false;
+        self.last_vcl_layer_id = None;
+        self.has_picture_header = false;
+        Some(access_unit)
+    }
+
+    fn starts_access_unit(&self, header: &NalUnitHeader, nal_unit: &[u8]) -> bool {
+        use NalType::*;
+
+        // NAL units of a picture in a higher layer belong to the same access unit.
+        let follows_vcl = self
+            .last_vcl_layer_id
+            .is_some_and(|layer_id| header.layer_id <= layer_id);
+
+        match header.nal_type {
+            AccessUnitDelimiter => !self.payload.is_empty(),
+            nal_type if nal_type.is_vcl() => {
+                // sh_picture_header_in_slice_header_flag is only set for the first and only slice of a picture.
+                let picture_header_in_slice_header = nal_unit
+                    .get(NalUnitHeader::SIZE)
+                    .is_some_and(|byte| byte >> 7 == 1);
+                follows_vcl && picture_header_in_slice_header && !self.has_picture_header
+            }
+            // 12 is OPI, 26 is reserved, and 28 and 29 are unspecified non-VCL NAL unit types.
+            Dci | Vps | Sps | Pps | PrefixAps | Ph | PrefixSei | Unknown(12 | 26 | 28 | 29) => {
+                follows_vcl
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, header: &NalUnitHeader) {
+        if header.nal_type.is_vcl() {
+            self.last_vcl_layer_id = Some(header.layer_id);
+            self.has_picture_header = false;
+            self.is_random_access_point |=
+                header.nal_type.is_irap() || header.nal_type == NalType::CodedSliceGdr;
+        } else if header.nal_type == NalType::Ph {
+            self.has_picture_header = true;
+        }
+    }
+}
+
+/// Strip the start code from an Annex-B NAL unit.
+///
+/// Data that does not start with a start code is returned unchanged.
+pub fn strip_start_code(data: &[u8]) -> &[u8] {
+    data.strip_prefix(&[0, 0, 0, 1])
+        .or_else(|| data.strip_prefix(START_CODE))
+        .unwrap_or(data)
+}
+
+fn find_start_code(data: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(START_CODE.len())
+        .position(|window| window == START_CODE)
+        .map(|position| position + from)
+}
+
+// Start codes may be 0x000001 or 0x00000001.
+fn unit_start(data: &[u8], start_code: usize) -> usize {
+    if start_code > 0 && data[start_code - 1] == 0 {
+        start_code - 1
+    } else {
+        start_code
+    }
+}
+
+// NAL units never end with a zero byte, so any zero belongs to the trailing zeros or the next start code.
+fn trim_trailing_zeros(data: &[u8], start: usize, mut end: usize) -> usize {
+    while end > start && data[end - 1] == 0 {
+        end -= 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = include_bytes!("../tests/short.vvc");
+
+    /// Reader returning a single byte per read, to exercise streaming boundaries.
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn read_all<R: Read>(reader: R) -> Vec<Vec<u8>> {
+        let mut reader = AnnexBSplitter::from_reader(reader);
+        let mut nal_units = Vec::new();
+        while let Some(nal_unit) = reader.next_nal_unit().unwrap() {
+            nal_units.push(nal_unit.to_vec());
+        }
+        nal_units
+    }
+
+    #[test]
+    fn test_split_start_codes() {
+        const INPUT: &[u8] = &[0, 0, 0, 1, 1, 2, 3, 4, 0, 0, 0, 1, 5, 6, 7, 8, 0, 0, 1, 9];
+        let nal_units: Vec<_> = AnnexBSplitter::new(INPUT).collect();
+        assert_eq!(
+            nal_units,
+            [
+                &[0, 0, 0, 1, 1, 2, 3, 4][..],
+                &[0, 0, 0, 1, 5, 6, 7, 8],
+                &[0, 0, 1, 9]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_zeros() {
+        // Leading zeros, trailing zeros, an empty NAL unit and data before the first start code.
+        const INPUT: &[u8] = &[7, 0, 0, 0, 0, 1, 1, 2, 0, 0, 0, 0, 0, 1, 0, 0, 1, 3, 0, 0];
+        let nal_units: Vec<_> = AnnexBSplitter::new(INPUT).collect();
+        assert_eq!(nal_units, [&[0, 0, 0, 1, 1, 2][..], &[0, 0, 1, 3]]);
+    }
+
+    #[test]
+    fn test_split_no_start_code() {
+        assert_eq!(AnnexBSplitter::new(&[1, 2, 3, 0, 0]).next(), None);
+        assert_eq!(AnnexBSplitter::new(&[]).next(), None);
+        assert!(read_all(&[1, 2, 3, 0, 0][..]).is_empty());
+    }
+
+    #[test]
+    fn test_split_file() {
+        let sizes: Vec<_> = AnnexBSplitter::new(DATA).map(<[u8]>::len).collect();
+        assert_eq!(sizes, [249, 17, 23, 1374, 66, 25]);
+    }
+
+    #[test]
+    fn test_reader_matches_splitter() {
+        let expected: Vec<_> = AnnexBSplitter::new(DATA).map(<[u8]>::to_vec).collect();
+        assert_eq!(read_all(DATA), expected);
+        assert_eq!(read_all(ByteReader(DATA)), expected);
+
+        const INPUT: &[u8] = &[7, 0, 0, 0, 0, 1, 1, 2, 0, 0, 0, 0, 0, 1, 0, 0, 1, 3, 0, 0];
+        let expected: Vec<_> = AnnexBSplitter::new(INPUT).map(<[u8]>::to_vec).collect();
+        assert_eq!(read_all(ByteReader(INPUT)), expected);
+    }
+
+    #[test]
+    fn test_parse_nal_unit_header() {
+        let headers: Vec<_> = AnnexBSplitter::new(DATA)
+            .map(|nal_unit| NalUnitHeader::parse(nal_unit).unwrap())
+            .map(|header| (header.nal_type, header.layer_id, header.temporal_id))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                (NalType::Sps, 0, 0),
+                (NalType::Pps, 0, 0),
+                (NalType::PrefixAps, 0, 0),
+                (NalType::CodedSliceIdrNLp, 0, 0),
+                (NalType::CodedSliceStsa, 0, 4),
+                (NalType::CodedSliceStsa, 0, 5),
+            ]
+        );
+        assert!(headers[3].0.is_irap());
+        assert!(headers[4].0.is_vcl() && !headers[4].0.is_irap());
+        assert!(!headers[0].0.is_vcl());
+    }
+
+    #[test]
+    fn test_parse_invalid_nal_unit_header() {
+        assert_eq!(NalUnitHeader::parse(&[0, 0, 1, 0]), None);
+        // forbidden_zero_bit set
+        assert_eq!(NalUnitHeader::parse(&[0x80, 0x01]), None);
+        // TemporalId + 1 equal to zero
+        assert_eq!(NalUnitHeader::parse(&[0x00, 0x08]), None);
+        assert_eq!(
+            NalUnitHeader::parse(&[0x01, 0x4b]),
+            Some(NalUnitHeader {
+                nal_type: NalType::CodedSliceCra,
+                layer_id: 1,
+                temporal_id: 2,
+            })
+        );
+    }
+
+    fn assemble(nal_units: &[&[u8]]) -> Vec<(Vec<u8>, bool)> {
+        let mut assembler = AccessUnitAssembler::new();
+        let mut access_units: Vec<_> = nal_units
+            .iter()
+            .filter_map(|nal_unit| assembler.push(nal_unit))
+            .collect();
+        access_units.extend(assembler.flush());
+        access_units
+            .into_iter()
+            .map(|access_unit| (access_unit.payload, access_unit.is_random_access_point))
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_file() {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).collect();
+        let access_units = assemble(&nal_units);
+
+        let rap_flags: Vec<_> = access_units.iter().map(|(_, rap)| *rap).collect();
+        assert_eq!(rap_flags, [true, false, false]);
+        let access_unit_nal_units: Vec<Vec<&[u8]>> = access_units
+            .iter()
+            .map(|(payload, _)| AnnexBSplitter::new(payload).map(strip_start_code).collect())
+            .collect();
+        let nal_units: Vec<_> = nal_units.into_iter().map(strip_start_code).collect();
+        assert_eq!(
+            access_unit_nal_units,
+            [
+                nal_units[..4].to_vec(),
+                vec![nal_units[4]],
+                vec![nal_units[5]]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assemble_boundaries() {
+        const AUD: &[u8] = &[0, 0, 1, 0x00, 0xa1, 0x10];
+        const PH: &[u8] = &[0, 0, 1, 0x00, 0x99, 0x40];
+        const SPS: &[u8] = &[0, 0, 1, 0x00, 0x79, 0x00];
+        const PREFIX_SEI: &[u8] = &[0, 0, 1, 0x00, 0xb9, 0x01];
+        const SUFFIX_SEI: &[u8] = &[0, 0, 1, 0x00, 0xc1, 0x01];
+        // CRA and TRAIL slices, with and without the picture header in the slice header.
+        const CRA: &[u8] = &[0, 0, 1, 0x00, 0x49, 0x80];
+        const TRAIL_FIRST: &[u8] = &[0, 0, 1, 0x00, 0x01, 0x80];
+        const TRAIL: &[u8] = &[0, 0, 1, 0x00, 0x01, 0x00];
+        // TRAIL slice of a second layer.
+        const TRAIL_LAYER_1: &[u8] = &[0, 0, 1, 0x01, 0x01, 0x80];
+
+        let boundaries = |nal_units: &[&[u8]]| -> Vec<usize> {
+            assemble(nal_units)
+                .into_iter()
+                .map(|(payload, _)| AnnexBSplitter::new(&payload).count())
+                .collect()
+        };
+
+        assert_eq!(
+            boundaries(&[AUD, SPS, CRA, SUFFIX_SEI, AUD, TRAIL_FIRST]),
+            [4, 2]
+        );
+        assert_eq!(
+            boundaries(&[PH, TRAIL, TRAIL, PH, TRAIL, PREFIX_SEI, TRAIL_FIRST]),
+            [3, 2, 2]
+        );
+        assert_eq!(
+            boundaries(&[TRAIL_FIRST, TRAIL_LAYER_1, TRAIL_FIRST, TRAIL_LAYER_1]),
+            [2, 2]
+        );
+        let rap_flags: Vec<_> = assemble(&[SPS, CRA, TRAIL_FIRST])
+            .into_iter()
+            .map(|(_, rap)| rap)
+            .collect();
+        assert_eq!(rap_flags, [true, false]);
+        assert!(assemble(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_strip_start_code() {
+        assert_eq!(strip_start_code(&[0, 0, 0, 1, 5]), &[5]);
+        assert_eq!(strip_start_code(&[0, 0, 1, 5]), &[5]);
+        assert_eq!(strip_start_code(&[5]), &[5]);
+    }
+}
@@ -0,0 +1,428 @@
+use super::{Level, NalUnitHeader, ParseError, Profile, Tier};
+use crate::{AccessUnit, ColorFormat, NalType};
+
+/// VVC decoder configuration record, as stored in the `vvcC` box of MP4 files and in the codec private data of
+/// Matroska files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecoderConfigurationRecord {
+    /// Size of the length prefix of NAL units in samples, in bytes: 1, 2 or 4.
+    pub length_size: usize,
+    /// Stream information, if present.
+    pub stream_info: Option<StreamInfo>,
+    /// Parameter sets and SEI messages, without start codes.
+    pub nal_units: Vec<Vec<u8>>,
+}
+
+impl DecoderConfigurationRecord {
+    /// Parse a decoder configuration record.
+    ///
+    /// For MP4 files, `data` is the content of the `vvcC` box following its version and flags.
+    pub fn parse(data: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = ByteReader { data };
+
+        let byte = reader.read_u8()?;
+        let length_size = check_length_size(((byte >> 1) & 0b11) as usize + 1)?;
+        let ptl_present = byte & 1 == 1;
+        let stream_info = if ptl_present {
+            Some(StreamInfo::parse(&mut reader)?)
+        } else {
+            None
+        };
+
+        let mut nal_units = Vec::new();
+        let num_arrays = reader.read_u8()?;
+        for _ in 0..num_arrays {
+            let nal_type = reader.read_u8()? & 0b1_1111;
+            // DCI and OPI arrays contain a single NAL unit.
+            let num_nal_units = if matches!(nal_type, 12 | 13) {
+                1
+            } else {
+                reader.read_u16()?
+            };
+            for _ in 0..num_nal_units {
+                let len = reader.read_u16()? as usize;
+                nal_units.push(reader.read_bytes(len)?.to_vec());
+            }
+        }
+
+        Ok(Self {
+            length_size,
+            stream_info,
+            nal_units,
+        })
+    }
+
+    /// Serialize the record, with each NAL unit in its own array and without stream information.
+    ///
+    /// This is meant for tests building MP4 and Matroska files.
+    #[doc(hidden)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let length_size_minus1 = (self.length_size as u8).saturating_sub(1) & 0b11;
+        let mut data = vec![0xf8 | length_size_minus1 << 1, self.nal_units.len() as u8];
+        for nal_unit in &self.nal_units {
+            let nal_type = nal_unit.get(1).map_or(0, |byte| byte >> 3);
+            data.push(0x80 | nal_type);
+            // DCI and OPI arrays contain a single NAL unit.
+            if !matches!(nal_type, 12 | 13) {
+                data.extend_from_slice(&1u16.to_be_bytes());
+            }
+            data.extend_from_slice(&(nal_unit.len() as u16).to_be_bytes());
+            data.extend_from_slice(nal_unit);
+        }
+        data
+    }
+}
+
+/// Stream information from a decoder configuration record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// Index of the output layer set described.
+    pub ols_idx: u16,
+    /// Number of temporal sublayers.
+    pub num_sublayers: u8,
+    /// Constant frame rate indicator: 0 if unspecified, 1 if constant, 2 if constant for each temporal sublayer.
+    pub constant_frame_rate: u8,
+    /// Chroma format.
+    pub color_format: ColorFormat,
+    /// Bit depth.
+    pub bit_depth: u32,
+    /// Profile.
+    pub profile: Profile,
+    /// Tier.
+    pub tier: Tier,
+    /// Level.
+    pub level: Level,
+    /// Maximum picture width in luma samples.
+    pub max_width: u16,
+    /// Maximum picture height in luma samples.
+    pub max_height: u16,
+    /// Average frame rate in frames per 256 seconds, or 0 if unspecified.
+    pub avg_frame_rate: u16,
+}
+
+impl StreamInfo {
+    fn parse(reader: &mut ByteReader) -> Result<Self, ParseError> {
+        let bits = reader.read_u16()?;
+        let ols_idx = bits >> 7;
+        let num_sublayers = ((bits >> 4) & 0b111) as u8;
+        let constant_frame_rate = ((bits >> 2) & 0b11) as u8;
+        let color_format = match bits & 0b11 {
+            0 => ColorFormat::Yuv400Planar,
+            1 => ColorFormat::Yuv420Planar,
+            2 => ColorFormat::Yuv422Planar,
+            _ => ColorFormat::Yuv444Planar,
+        };
+        let bit_depth = (reader.read_u8()? >> 5) as u32 + 8;
+
+        // VvcPTLRecord
+        let num_bytes_constraint_info = (reader.read_u8()? & 0b11_1111) as usize;
+        let byte = reader.read_u8()?;
+        let profile = Profile::new(byte >> 1);
+        let tier = if byte & 1 == 1 {
+            Tier::High
+        } else {
+            Tier::Main
+        };
+        let level = Level::new(reader.read_u8()?);
+        reader.read_bytes(num_bytes_constraint_info)?;
+        if num_sublayers > 1 {
+            let sublayer_level_present = reader.read_u8()?;
+            reader.read_bytes(sublayer_level_present.count_ones() as usize)?;
+        }
+        let num_sub_profiles = reader.read_u8()? as usize;
+        reader.read_bytes(num_sub_profiles * 4)?;
+
+        Ok(Self {
+            ols_idx,
+            num_sublayers,
+            constant_frame_rate,
+            color_format,
+            bit_depth,
+            profile,
+            tier,
+            level,
+            max_width: reader.read_u16()?,
+            max_height: reader.read_u16()?,
+            avg_frame_rate: reader.read_u16()?,
+        })
+    }
+}
+
+/// Converter of length-prefixed samples, as stored in MP4 and Matroska files, to Annex-B access units.
+///
+/// The parameter sets and SEI messages of the decoder configuration record are inserted in each sample containing
+/// an IRAP or GDR picture, so that decoding can start, or restart after [`Decoder::flush`](crate::Decoder::flush),
+/// at any random access point.
+///
+/// ```no_run
+/// use vvdec::{nal::{DecoderConfigurationRecord, LengthPrefixedConverter}, Decoder};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let (vvcc, samples): (&[u8], Vec<&[u8]>) = (&[], vec![]);
+///
+/// let record = DecoderConfigurationRecord::parse(vvcc)?;
+/// let converter = LengthPrefixedConverter::from_configuration_record(&record)?;
+/// let mut decoder = Decoder::new()?;
+/// for sample in samples {
+///     let frame = decoder.decode(converter.convert(sample)?)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LengthPrefixedConverter {
+    length_size: usize,
+    // Annex-B NAL units inserted at random access points.
+    parameter_sets: Vec<u8>,
+}
+
+impl LengthPrefixedConverter {
+    /// Create a converter for samples with NAL unit length prefixes of `length_size` bytes: 1, 2 or 4.
+    ///
+    /// Parameter sets must be carried in the samples.
+    pub fn new(length_size: usize) -> Result<Self, ParseError> {
+        Ok(Self {
+            length_size: check_length_size(length_size)?,
+            parameter_sets: Vec::new(),
+        })
+    }
+
+    /// Create a converter from a decoder configuration record.
+    pub fn from_configuration_record(
+        record: &DecoderConfigurationRecord,
+    ) -> Result<Self, ParseError> {
+        let mut converter = Self::new(record.length_size)?;
+        for nal_unit in &record.nal_units {
+            converter.parameter_sets.extend_from_slice(&[0, 0, 0, 1]);
+            converter.parameter_sets.extend_from_slice(nal_unit);
+        }
+        Ok(converter)
+    }
+
+    /// Convert a sample to an Annex-B access unit.
+    ///
+    /// The access unit is flagged as a random access point if it contains an IRAP or GDR picture. Its timestamps
+    /// are left unset.
+    pub fn convert(&self, sample: &[u8]) -> Result<AccessUnit<Vec<u8>>, ParseError> {
+        let mut nal_units = Vec::new();
+        let mut reader = ByteReader { data: sample };
+        while !reader.data.is_empty() {
+            let len = reader
+                .read_bytes(self.length_size)?
+                .iter()
+                .fold(0, |len, &byte| len << 8 | byte as usize);
+            nal_units.push(reader.read_bytes(len)?);
+        }
+
+        let nal_types: Vec<_> = nal_units
+            .iter()
+            .map(|nal_unit| NalUnitHeader::parse(nal_unit).map(|header| header.nal_type))
+            .collect();
+        let is_random_access_point = nal_types
+            .iter()
+            .flatten()
+            .any(|nal_type| nal_type.is_irap() || *nal_type == NalType::CodedSliceGdr);
+        // An access unit delimiter must remain the first NAL unit.
+        let insert_at = is_random_access_point
+            .then(|| (nal_types.first() == Some(&Some(NalType::AccessUnitDelimiter))) as usize);
+
+        let mut payload = Vec::with_capacity(sample.len() + self.parameter_sets.len());
+        for (index, nal_unit) in nal_units.iter().enumerate() {
+            if insert_at == Some(index) {
+                payload.extend_from_slice(&self.parameter_sets);
+            }
+            payload.extend_from_slice(&[0, 0, 0, 1]);
+            payload.extend_from_slice(nal_unit);
+        }
+
+        Ok(AccessUnit {
+            payload,
+            cts: None,
+            dts: None,
+            is_random_access_point,
+            is_damaged: false,
+        })
+    }
+}
+
+fn check_length_size(length_size: usize) -> Result<usize, ParseError> {
+    match length_size {
+        1 | 2 | 4 => Ok(length_size),
+        _ => Err(ParseError::InvalidValue("length_size")),
+    }
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        if self.data.len() < len {
+            return Err(ParseError::EndOfData);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ParseError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nal::{strip_start_code, AnnexBSplitter};
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+
+    fn length_prefixed(nal_units: &[&[u8]], length_size: usize) -> Vec<u8> {
+        let mut sample = Vec::new();
+        for nal_unit in nal_units {
+            sample.extend_from_slice(&nal_unit.len().to_be_bytes()[8 - length_size..]);
+            sample.extend_from_slice(nal_unit);
+        }
+        sample
+    }
+
+    fn nal_units() -> Vec<&'static [u8]> {
+        AnnexBSplitter::new(DATA).map(strip_start_code).collect()
+    }
+
+    // Record without stream information, holding the SPS, PPS and APS of short.vvc.
+    fn record(length_size: usize) -> Vec<u8> {
+        DecoderConfigurationRecord {
+            length_size,
+            stream_info: None,
+            nal_units: nal_units()[..3]
+                .iter()
+                .map(|nal_unit| nal_unit.to_vec())
+                .collect(),
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let record = DecoderConfigurationRecord::parse(&record(4)).unwrap();
+        assert_eq!(record.length_size, 4);
+        assert_eq!(record.stream_info, None);
+        assert_eq!(record.nal_units, nal_units()[..3]);
+
+        let mut data = record_with_stream_info();
+        let record = DecoderConfigurationRecord::parse(&data).unwrap();
+        assert_eq!(record.length_size, 2);
+        assert_eq!(
+            record.stream_info,
+            Some(StreamInfo {
+                ols_idx: 0,
+                num_sublayers: 6,
+                constant_frame_rate: 1,
+                color_format: ColorFormat::Yuv420Planar,
+                bit_depth: 10,
+                profile: Profile::Main10,
+                tier: Tier::Main,
+                level: Level::Level2_1,
+                max_width: 320,
+                max_height: 240,
+                avg_frame_rate: 25 * 256,
+            })
+        );
+        assert!(record.nal_units.is_empty());
+
+        data.pop();
+        assert_eq!(
+            DecoderConfigurationRecord::parse(&data),
+            Err(ParseError::EndOfData)
+        );
+        assert_eq!(
+            DecoderConfigurationRecord::parse(&[0xfd, 0]),
+            Err(ParseError::InvalidValue("length_size"))
+        );
+    }
+
+    fn record_with_stream_info() -> Vec<u8> {
+        vec![
+            0xfb, // Length size of 2, stream information present
+            0x00, 0x65, // OLS 0, 6 sublayers, constant frame rate, 4:2:0
+            0x5f, // 10 bits
+            0x01, 0x02, 0x23,
+            0x40, // Main 10, main tier, level 2.1, 1 byte of constraint info
+            0x10, 0xaa, // Sublayer level present for 1 sublayer
+            0x00, // No sub-profiles
+            0x01, 0x40, 0x00, 0xf0, 0x19, 0x00, // 320x240 at 25 fps
+            0x00, // No arrays
+        ]
+    }
+
+    #[test]
+    fn test_convert() {
+        let nal_units = nal_units();
+        for length_size in [1, 2, 4] {
+            let record = DecoderConfigurationRecord::parse(&record(length_size)).unwrap();
+            let converter = LengthPrefixedConverter::from_configuration_record(&record).unwrap();
+
+            // The IDR picture is too large for 1-byte length prefixes.
+            if length_size > 1 {
+                // The IDR picture gets the parameter sets inserted.
+                let access_unit = converter
+                    .convert(&length_prefixed(&nal_units[3..4], length_size))
+                    .unwrap();
+                assert!(access_unit.is_random_access_point);
+                let converted: Vec<_> = AnnexBSplitter::new(&access_unit.payload)
+                    .map(strip_start_code)
+                    .collect();
+                assert_eq!(converted, nal_units[..4]);
+            }
+
+            let access_unit = converter
+                .convert(&length_prefixed(&nal_units[4..5], length_size))
+                .unwrap();
+            assert!(!access_unit.is_random_access_point);
+            assert_eq!(access_unit.payload[..4], [0, 0, 0, 1]);
+            assert_eq!(access_unit.payload[4..], *nal_units[4]);
+        }
+    }
+
+    #[test]
+    fn test_convert_with_access_unit_delimiter() {
+        let nal_units = nal_units();
+        let aud: &[u8] = &[0, 0xa1, 0x10];
+        let converter =
+            LengthPrefixedConverter::from_configuration_record(&DecoderConfigurationRecord {
+                length_size: 4,
+                stream_info: None,
+                nal_units: vec![nal_units[0].to_vec()],
+            })
+            .unwrap();
+        let access_unit = converter
+            .convert(&length_prefixed(&[aud, nal_units[3]], 4))
+            .unwrap();
+        let converted: Vec<_> = AnnexBSplitter::new(&access_unit.payload)
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(converted, [aud, nal_units[0], nal_units[3]]);
+    }
+
+    #[test]
+    fn test_convert_invalid() {
+        assert_eq!(
+            LengthPrefixedConverter::new(3).err(),
+            Some(ParseError::InvalidValue("length_size"))
+        );
+
+        let converter = LengthPrefixedConverter::new(2).unwrap();
+        assert_eq!(converter.convert(&[0]).err(), Some(ParseError::EndOfData));
+        assert_eq!(
+            converter.convert(&[0, 3, 0, 0x81]).err(),
+            Some(ParseError::EndOfData)
+        );
+        assert_eq!(converter.convert(&[]).map(|au| au.payload), Ok(vec![]));
+    }
+}
@@ -0,0 +1,1519 @@
+use super::{strip_start_code, NalUnitHeader, ParseError, ProfileTierLevel, RbspReader};
+use crate::{ColorFormat, CpbParams, Hrd, NalType, SublayerHrd};
+
+// Maximum number of tile columns, tiles and slices in a picture, from the limits of H.266 Table A.1 for
+// level 6.3, the highest level but 15.5.
+const MAX_TILE_COLUMNS: u32 = 30;
+const MAX_TILES: u32 = 990;
+const MAX_SLICES: u32 = 1000;
+
+/// Video parameter set (VPS).
+///
+/// Only the syntax elements up to the profile, tier and level information are parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vps {
+    /// VPS ID.
+    pub id: u8,
+    /// Maximum number of layers.
+    pub max_layers: u8,
+    /// Maximum number of temporal sublayers.
+    pub max_sublayers: u8,
+    /// Layer IDs.
+    pub layer_ids: Vec<u8>,
+    /// Are all layers coded independently?
+    pub all_independent_layers: bool,
+    /// Profile, tier and level structures.
+    pub profile_tier_levels: Vec<ProfileTierLevel>,
+}
+
+impl Vps {
+    /// Parse a VPS NAL unit, with or without its start code.
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, ParseError> {
+        Self::parse_rbsp(&mut RbspReader::new(payload(nal_unit, NalType::Vps)?))
+    }
+
+    fn parse_rbsp(reader: &mut RbspReader) -> Result<Self, ParseError> {
+        let id = reader.read_bits(4)? as u8;
+        let max_layers_minus1 = reader.read_bits(6)?;
+        let max_sublayers_minus1 = reader.read_bits(3)?;
+        let default_ptl_dpb_hrd_max_tid = if max_layers_minus1 > 0 && max_sublayers_minus1 > 0 {
+            reader.read_flag()?
+        } else {
+            true
+        };
+        let all_independent_layers = if max_layers_minus1 > 0 {
+            reader.read_flag()?
+        } else {
+            true
+        };
+
+        let mut layer_ids = Vec::new();
+        for i in 0..=max_layers_minus1 {
+            layer_ids.push(reader.read_bits(6)? as u8);
+            if i > 0 && !all_independent_layers {
+                let independent_layer = reader.read_flag()?;
+                if !independent_layer {
+                    let max_tid_ref_present = reader.read_flag()?;
+                    for _ in 0..i {
+                        let direct_ref_layer = reader.read_flag()?;
+                        if max_tid_ref_present && direct_ref_layer {
+                            reader.skip_bits(3)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut num_ptls = 1;
+        if max_layers_minus1 > 0 {
+            let each_layer_is_an_ols = all_independent_layers && reader.read_flag()?;
+            if !each_layer_is_an_ols {
+                let ols_mode_idc = if all_independent_layers {
+                    2
+                } else {
+                    reader.read_bits(2)?
+                };
+                if ols_mode_idc == 2 {
+                    let num_output_layer_sets_minus2 = reader.read_bits(8)? as usize;
+                    reader.skip_bits((num_output_layer_sets_minus2 + 1) * layer_ids.len())?;
+                }
+            }
+            num_ptls = reader.read_bits(8)? + 1;
+        }
+
+        let mut ptl_headers = Vec::new();
+        for i in 0..num_ptls {
+            let pt_present = i == 0 || reader.read_flag()?;
+            let max_tid = if default_ptl_dpb_hrd_max_tid {
+                max_sublayers_minus1
+            } else {
+                reader.read_bits(3)?
+            };
+            ptl_headers.push((pt_present, max_tid));
+        }
+        reader.byte_align()?;
+
+        let mut profile_tier_levels: Vec<ProfileTierLevel> = Vec::new();
+        for (pt_present, max_tid) in ptl_headers {
+            let previous = profile_tier_levels.last();
+            let profile_tier_level =
+                ProfileTierLevel::parse(reader, pt_present, max_tid as u8, previous)?;
+            profile_tier_levels.push(profile_tier_level);
+        }
+
+        Ok(Self {
+            id,
+            max_layers: max_layers_minus1 as u8 + 1,
+            max_sublayers: max_sublayers_minus1 as u8 + 1,
+            layer_ids,
+            all_independent_layers,
+            profile_tier_levels,
+        })
+    }
+}
+
+/// Sequence parameter set (SPS).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sps {
+    /// SPS ID.
+    pub id: u8,
+    /// ID of the VPS referred to, or 0 if none.
+    pub vps_id: u8,
+    /// Maximum number of temporal sublayers.
+    pub max_sublayers: u8,
+    /// Chroma format.
+    pub color_format: ColorFormat,
+    /// CTU size in luma samples.
+    pub ctu_size: u32,
+    /// Profile, tier and level, if present.
+    pub profile_tier_level: Option<ProfileTierLevel>,
+    /// Are GDR pictures enabled?
+    pub gdr_enabled: bool,
+    /// Is reference picture resampling enabled?
+    pub ref_pic_resampling_enabled: bool,
+    /// Can the picture size change within the coded layer video sequence?
+    pub res_change_in_clvs_allowed: bool,
+    /// Maximum picture width in luma samples.
+    pub max_width: u32,
+    /// Maximum picture height in luma samples.
+    pub max_height: u32,
+    /// Conformance window, if present.
+    pub conformance_window: Option<ConformanceWindow>,
+    /// Number of subpictures.
+    pub num_subpics: u32,
+    /// Bit depth of luma and chroma samples.
+    pub bit_depth: u32,
+    /// Number of bits of the picture order count LSBs.
+    pub log2_max_pic_order_cnt_lsb: u32,
+    /// Decoded picture buffer parameters for the highest temporal sublayer, if present.
+    pub dpb_parameters: Option<DpbParameters>,
+    /// Timing and HRD parameters, if present.
+    pub hrd: Option<Hrd>,
+    /// Number of clock ticks per picture for the highest temporal sublayer, if the picture rate is fixed.
+    pub elemental_duration_in_tc: Option<u32>,
+    /// Does the sequence convey fields rather than frames?
+    pub field_seq: bool,
+    /// Are VUI parameters present?
+    pub has_vui: bool,
+}
+
+impl Sps {
+    /// Parse an SPS NAL unit, with or without its start code.
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, ParseError> {
+        Self::parse_rbsp(&mut RbspReader::new(payload(nal_unit, NalType::Sps)?))
+    }
+
+    /// Get the picture size after cropping to the conformance window, in luma samples.
+    pub fn cropped_size(&self) -> (u32, u32) {
+        cropped_size(
+            self.max_width,
+            self.max_height,
+            self.conformance_window.as_ref(),
+            &self.color_format,
+        )
+    }
+
+    fn parse_rbsp(reader: &mut RbspReader) -> Result<Self, ParseError> {
+        let id = reader.read_bits(4)? as u8;
+        let vps_id = reader.read_bits(4)? as u8;
+        let max_sublayers_minus1 = reader.read_bits(3)?;
+        let chroma_format_idc = reader.read_bits(2)?;
+        let ctu_size = 1 << (reader.read_bits(2)? + 5);
+        let ptl_dpb_hrd_params_present = reader.read_flag()?;
+        let profile_tier_level = if ptl_dpb_hrd_params_present {
+            Some(ProfileTierLevel::parse(
+                reader,
+                true,
+                max_sublayers_minus1 as u8,
+                None,
+            )?)
+        } else {
+            None
+        };
+
+        let gdr_enabled = reader.read_flag()?;
+        let ref_pic_resampling_enabled = reader.read_flag()?;
+        let res_change_in_clvs_allowed = ref_pic_resampling_enabled && reader.read_flag()?;
+        let max_width = reader.read_ue()?;
+        let max_height = reader.read_ue()?;
+        let conformance_window = ConformanceWindow::parse(reader)?;
+
+        let mut num_subpics = 1;
+        let subpic_info_present = reader.read_flag()?;
+        if subpic_info_present {
+            // Each subpicture contains at least one slice.
+            let num_subpics_minus1 = reader.read_ue()?;
+            if num_subpics_minus1 >= MAX_SLICES {
+                return Err(ParseError::InvalidValue("sps_num_subpics_minus1"));
+            }
+            num_subpics = num_subpics_minus1 + 1;
+            let (independent_subpics, subpic_same_size) = if num_subpics_minus1 > 0 {
+                (reader.read_flag()?, reader.read_flag()?)
+            } else {
+                (true, false)
+            };
+
+            let ctb_bits = |size: u32| ceil_log2(size.div_ceil(ctu_size));
+            let (width_bits, height_bits) = (ctb_bits(max_width), ctb_bits(max_height));
+            for i in 0..num_subpics {
+                if num_subpics_minus1 == 0 {
+                    break;
+                }
+                if !subpic_same_size || i == 0 {
+                    if i > 0 && max_width > ctu_size {
+                        reader.read_bits(width_bits)?;
+                    }
+                    if i > 0 && max_height > ctu_size {
+                        reader.read_bits(height_bits)?;
+                    }
+                    if i < num_subpics_minus1 && max_width > ctu_size {
+                        reader.read_bits(width_bits)?;
+                    }
+                    if i < num_subpics_minus1 && max_height > ctu_size {
+                        reader.read_bits(height_bits)?;
+                    }
+                }
+                if !independent_subpics {
+                    reader.skip_bits(2)?;
+                }
+            }
+
+            let subpic_id_len = reader.read_ue()? + 1;
+            if subpic_id_len > 16 {
+                return Err(ParseError::InvalidValue("sps_subpic_id_len_minus1"));
+            }
+            let subpic_id_mapping_explicitly_signalled = reader.read_flag()?;
+            if subpic_id_mapping_explicitly_signalled {
+                let subpic_id_mapping_present = reader.read_flag()?;
+                if subpic_id_mapping_present {
+                    reader.skip_bits(num_subpics as usize * subpic_id_len as usize)?;
+                }
+            }
+        }
+
+        let bit_depth = reader.read_ue()? + 8;
+        let _entropy_coding_sync_enabled = reader.read_flag()?;
+        let _entry_point_offsets_present = reader.read_flag()?;
+        let log2_max_pic_order_cnt_lsb = reader.read_bits(4)? + 4;
+        let poc_msb_cycle = reader.read_flag()?;
+        if poc_msb_cycle {
+            reader.read_ue()?;
+        }
+        let num_extra_ph_bytes = reader.read_bits(2)?;
+        reader.skip_bits(num_extra_ph_bytes as usize * 8)?;
+        let num_extra_sh_bytes = reader.read_bits(2)?;
+        reader.skip_bits(num_extra_sh_bytes as usize * 8)?;
+
+        let dpb_parameters = if ptl_dpb_hrd_params_present {
+            let sublayer_dpb_params = max_sublayers_minus1 > 0 && reader.read_flag()?;
+            Some(DpbParameters::parse(
+                reader,
+                max_sublayers_minus1,
+                sublayer_dpb_params,
+            )?)
+        } else {
+            None
+        };
+
+        let _log2_min_luma_coding_block_size_minus2 = reader.read_ue()?;
+        let _partition_constraints_override_enabled = reader.read_flag()?;
+        let _log2_diff_min_qt_min_cb_intra_slice_luma = reader.read_ue()?;
+        let max_mtt_hierarchy_depth_intra_slice_luma = reader.read_ue()?;
+        if max_mtt_hierarchy_depth_intra_slice_luma != 0 {
+            reader.read_ue()?;
+            reader.read_ue()?;
+        }
+        let qtbtt_dual_tree_intra = chroma_format_idc != 0 && reader.read_flag()?;
+        if qtbtt_dual_tree_intra {
+            let _log2_diff_min_qt_min_cb_intra_slice_chroma = reader.read_ue()?;
+            let max_mtt_hierarchy_depth_intra_slice_chroma = reader.read_ue()?;
+            if max_mtt_hierarchy_depth_intra_slice_chroma != 0 {
+                reader.read_ue()?;
+                reader.read_ue()?;
+            }
+        }
+        let _log2_diff_min_qt_min_cb_inter_slice = reader.read_ue()?;
+        let max_mtt_hierarchy_depth_inter_slice = reader.read_ue()?;
+        if max_mtt_hierarchy_depth_inter_slice != 0 {
+            reader.read_ue()?;
+            reader.read_ue()?;
+        }
+        let max_luma_transform_size_64 = ctu_size > 32 && reader.read_flag()?;
+
+        let transform_skip_enabled = reader.read_flag()?;
+        if transform_skip_enabled {
+            let _log2_transform_skip_max_size_minus2 = reader.read_ue()?;
+            let _bdpcm_enabled = reader.read_flag()?;
+        }
+        let mts_enabled = reader.read_flag()?;
+        if mts_enabled {
+            reader.skip_bits(2)?;
+        }
+        let lfnst_enabled = reader.read_flag()?;
+
+        if chroma_format_idc != 0 {
+            let joint_cbcr_enabled = reader.read_flag()?;
+            let same_qp_table_for_chroma = reader.read_flag()?;
+            let num_qp_tables = match (same_qp_table_for_chroma, joint_cbcr_enabled) {
+                (true, _) => 1,
+                (false, true) => 3,
+                (false, false) => 2,
+            };
+            for _ in 0..num_qp_tables {
+                let _qp_table_start_minus26 = reader.read_se()?;
+                let num_points_in_qp_table_minus1 = reader.read_ue()?;
+                if num_points_in_qp_table_minus1 > 63 {
+                    return Err(ParseError::InvalidValue(
+                        "sps_num_points_in_qp_table_minus1",
+                    ));
+                }
+                for _ in 0..=num_points_in_qp_table_minus1 {
+                    reader.read_ue()?;
+                    reader.read_ue()?;
+                }
+            }
+        }
+
+        let _sao_enabled = reader.read_flag()?;
+        let alf_enabled = reader.read_flag()?;
+        if alf_enabled && chroma_format_idc != 0 {
+            let _ccalf_enabled = reader.read_flag()?;
+        }
+        let _lmcs_enabled = reader.read_flag()?;
+        let weighted_pred = reader.read_flag()?;
+        let weighted_bipred = reader.read_flag()?;
+        let long_term_ref_pics = reader.read_flag()?;
+        let inter_layer_prediction_enabled = vps_id > 0 && reader.read_flag()?;
+        let _idr_rpl_present = reader.read_flag()?;
+        let rpl1_same_as_rpl0 = reader.read_flag()?;
+        let rpl_context = RefPicListContext {
+            long_term_ref_pics,
+            inter_layer_prediction_enabled,
+            weighted_prediction: weighted_pred || weighted_bipred,
+            log2_max_pic_order_cnt_lsb,
+        };
+        for _ in 0..(if rpl1_same_as_rpl0 { 1 } else { 2 }) {
+            let num_ref_pic_lists = reader.read_ue()?;
+            if num_ref_pic_lists > 64 {
+                return Err(ParseError::InvalidValue("sps_num_ref_pic_lists"));
+            }
+            for _ in 0..num_ref_pic_lists {
+                rpl_context.skip_ref_pic_list_struct(reader)?;
+            }
+        }
+
+        let _ref_wraparound_enabled = reader.read_flag()?;
+        let temporal_mvp_enabled = reader.read_flag()?;
+        if temporal_mvp_enabled {
+            let _sbtmvp_enabled = reader.read_flag()?;
+        }
+        let amvr_enabled = reader.read_flag()?;
+        let bdof_enabled = reader.read_flag()?;
+        if bdof_enabled {
+            let _bdof_control_present_in_ph = reader.read_flag()?;
+        }
+        let _smvd_enabled = reader.read_flag()?;
+        let dmvr_enabled = reader.read_flag()?;
+        if dmvr_enabled {
+            let _dmvr_control_present_in_ph = reader.read_flag()?;
+        }
+        let mmvd_enabled = reader.read_flag()?;
+        if mmvd_enabled {
+            let _mmvd_fullpel_only_enabled = reader.read_flag()?;
+        }
+        let max_num_merge_cand = 6u32
+            .checked_sub(reader.read_ue()?)
+            .ok_or(ParseError::InvalidValue("sps_six_minus_max_num_merge_cand"))?;
+        let _sbt_enabled = reader.read_flag()?;
+        let affine_enabled = reader.read_flag()?;
+        if affine_enabled {
+            let _five_minus_max_num_subblock_merge_cand = reader.read_ue()?;
+            let _6param_affine_enabled = reader.read_flag()?;
+            if amvr_enabled {
+                let _affine_amvr_enabled = reader.read_flag()?;
+            }
+            let affine_prof_enabled = reader.read_flag()?;
+            if affine_prof_enabled {
+                let _prof_control_present_in_ph = reader.read_flag()?;
+            }
+        }
+        let _bcw_enabled = reader.read_flag()?;
+        let _ciip_enabled = reader.read_flag()?;
+        if max_num_merge_cand >= 2 {
+            let gpm_enabled = reader.read_flag()?;
+            if gpm_enabled && max_num_merge_cand >= 3 {
+                let _max_num_merge_cand_minus_max_num_gpm_cand = reader.read_ue()?;
+            }
+        }
+        let _log2_parallel_merge_level_minus2 = reader.read_ue()?;
+        let _isp_enabled = reader.read_flag()?;
+        let _mrl_enabled = reader.read_flag()?;
+        let _mip_enabled = reader.read_flag()?;
+        if chroma_format_idc != 0 {
+            let _cclm_enabled = reader.read_flag()?;
+        }
+        if chroma_format_idc == 1 {
+            let _chroma_horizontal_collocated = reader.read_flag()?;
+            let _chroma_vertical_collocated = reader.read_flag()?;
+        }
+        let palette_enabled = reader.read_flag()?;
+        let act_enabled =
+            chroma_format_idc == 3 && !max_luma_transform_size_64 && reader.read_flag()?;
+        if transform_skip_enabled || palette_enabled {
+            let _min_qp_prime_ts = reader.read_ue()?;
+        }
+        let ibc_enabled = reader.read_flag()?;
+        if ibc_enabled {
+            let _six_minus_max_num_ibc_merge_cand = reader.read_ue()?;
+        }
+        let ladf_enabled = reader.read_flag()?;
+        if ladf_enabled {
+            let num_ladf_intervals_minus2 = reader.read_bits(2)?;
+            let _ladf_lowest_interval_qp_offset = reader.read_se()?;
+            for _ in 0..=num_ladf_intervals_minus2 {
+                reader.read_se()?;
+                reader.read_ue()?;
+            }
+        }
+        let explicit_scaling_list_enabled = reader.read_flag()?;
+        if lfnst_enabled && explicit_scaling_list_enabled {
+            let _scaling_matrix_for_lfnst_disabled = reader.read_flag()?;
+        }
+        let scaling_matrix_for_alternative_colour_space_disabled =
+            act_enabled && explicit_scaling_list_enabled && reader.read_flag()?;
+        if scaling_matrix_for_alternative_colour_space_disabled {
+            let _scaling_matrix_designated_colour_space = reader.read_flag()?;
+        }
+        let _dep_quant_enabled = reader.read_flag()?;
+        let _sign_data_hiding_enabled = reader.read_flag()?;
+        let virtual_boundaries_enabled = reader.read_flag()?;
+        if virtual_boundaries_enabled {
+            let virtual_boundaries_present = reader.read_flag()?;
+            if virtual_boundaries_present {
+                for _ in 0..2 {
+                    let num_virtual_boundaries = reader.read_ue()?;
+                    if num_virtual_boundaries > 3 {
+                        return Err(ParseError::InvalidValue("sps_num_ver_virtual_boundaries"));
+                    }
+                    for _ in 0..num_virtual_boundaries {
+                        reader.read_ue()?;
+                    }
+                }
+            }
+        }
+
+        let mut hrd = None;
+        let mut elemental_duration_in_tc = None;
+        if ptl_dpb_hrd_params_present {
+            let timing_hrd_params_present = reader.read_flag()?;
+            if timing_hrd_params_present {
+                let mut general_hrd = parse_general_timing_hrd_parameters(reader)?;
+                let sublayer_cpb_params_present = max_sublayers_minus1 > 0 && reader.read_flag()?;
+                let first_sublayer = if sublayer_cpb_params_present {
+                    0
+                } else {
+                    max_sublayers_minus1
+                };
+                general_hrd.sublayers = parse_ols_timing_hrd_parameters(
+                    reader,
+                    &general_hrd,
+                    first_sublayer,
+                    max_sublayers_minus1,
+                )?;
+                elemental_duration_in_tc = general_hrd
+                    .sublayers
+                    .last()
+                    .and_then(|sublayer| sublayer.elemental_duration_in_tc);
+                hrd = Some(general_hrd);
+            }
+        }
+
+        let field_seq = reader.read_flag()?;
+        let has_vui = reader.read_flag()?;
+        if has_vui {
+            let vui_payload_size = reader.read_ue()? as usize + 1;
+            reader.byte_align()?;
+            reader.skip_bits(vui_payload_size * 8)?;
+        }
+        let _extension = reader.read_flag()?;
+
+        Ok(Self {
+            id,
+            vps_id,
+            max_sublayers: max_sublayers_minus1 as u8 + 1,
+            color_format: color_format(chroma_format_idc),
+            ctu_size,
+            profile_tier_level,
+            gdr_enabled,
+            ref_pic_resampling_enabled,
+            res_change_in_clvs_allowed,
+            max_width,
+            max_height,
+            conformance_window,
+            num_subpics,
+            bit_depth,
+            log2_max_pic_order_cnt_lsb,
+            dpb_parameters,
+            hrd,
+            elemental_duration_in_tc,
+            field_seq,
+            has_vui,
+        })
+    }
+}
+
+/// Picture parameter set (PPS).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pps {
+    /// PPS ID.
+    pub id: u8,
+    /// ID of the SPS referred to.
+    pub sps_id: u8,
+    /// Can pictures contain slices of different NAL unit types?
+    pub mixed_nal_unit_types_in_pic: bool,
+    /// Picture width in luma samples.
+    pub width: u32,
+    /// Picture height in luma samples.
+    pub height: u32,
+    /// Conformance window, if present.
+    pub conformance_window: Option<ConformanceWindow>,
+    /// Is the picture output flag present in picture headers?
+    pub output_flag_present: bool,
+    /// Number of tile columns.
+    pub num_tile_columns: u32,
+    /// Number of tile rows.
+    pub num_tile_rows: u32,
+    /// Are slices rectangular, rather than raster-scan?
+    pub rect_slices: bool,
+    /// Is CABAC initialization signalled in slice headers?
+    pub cabac_init_present: bool,
+    /// Initial luma quantization parameter.
+    pub init_qp: i32,
+    /// Is weighted prediction enabled for P slices?
+    pub weighted_pred: bool,
+    /// Is weighted prediction enabled for B slices?
+    pub weighted_bipred: bool,
+    /// Is the deblocking filter disabled by default?
+    pub deblocking_filter_disabled: bool,
+}
+
+impl Pps {
+    /// Parse a PPS NAL unit, with or without its start code.
+    pub fn parse(nal_unit: &[u8]) -> Result<Self, ParseError> {
+        Self::parse_rbsp(&mut RbspReader::new(payload(nal_unit, NalType::Pps)?))
+    }
+
+    /// Get the picture size after cropping to the conformance window, in luma samples.
+    ///
+    /// The chroma format is taken from the SPS referred to, as is the conformance window when absent from the PPS
+    /// and the picture has the maximum size of the SPS.
+    pub fn cropped_size(&self, sps: &Sps) -> (u32, u32) {
+        let conformance_window = self.conformance_window.as_ref().or_else(|| {
+            ((self.width, self.height) == (sps.max_width, sps.max_height))
+                .then_some(sps.conformance_window.as_ref())
+                .flatten()
+        });
+        cropped_size(
+            self.width,
+            self.height,
+            conformance_window,
+            &sps.color_format,
+        )
+    }
+
+    fn parse_rbsp(reader: &mut RbspReader) -> Result<Self, ParseError> {
+        let id = reader.read_bits(6)? as u8;
+        let sps_id = reader.read_bits(4)? as u8;
+        let mixed_nal_unit_types_in_pic = reader.read_flag()?;
+        let width = reader.read_ue()?;
+        let height = reader.read_ue()?;
+        let conformance_window = ConformanceWindow::parse(reader)?;
+        let scaling_window_explicit_signalling = reader.read_flag()?;
+        if scaling_window_explicit_signalling {
+            for _ in 0..4 {
+                reader.read_se()?;
+            }
+        }
+        let output_flag_present = reader.read_flag()?;
+        let no_pic_partition = reader.read_flag()?;
+        let subpic_id_mapping_present = reader.read_flag()?;
+        if subpic_id_mapping_present {
+            let num_subpics_minus1 = if no_pic_partition {
+                0
+            } else {
+                reader.read_ue()?
+            };
+            let subpic_id_len = reader.read_ue()? + 1;
+            if num_subpics_minus1 >= MAX_SLICES || subpic_id_len > 16 {
+                return Err(ParseError::InvalidValue("pps_subpic_id"));
+            }
+            reader.skip_bits((num_subpics_minus1 as usize + 1) * subpic_id_len as usize)?;
+        }
+
+        let mut num_tile_columns = 1;
+        let mut num_tile_rows = 1;
+        let mut rect_slices = true;
+        if !no_pic_partition {
+            let ctu_size = 1 << (reader.read_bits(2)? + 5);
+            let num_exp_tile_columns = reader.read_ue()? as usize + 1;
+            let num_exp_tile_rows = reader.read_ue()? as usize + 1;
+            let column_widths = parse_tile_sizes(
+                reader,
+                num_exp_tile_columns,
+                width,
+                ctu_size,
+                MAX_TILE_COLUMNS,
+            )?;
+            let row_heights =
+                parse_tile_sizes(reader, num_exp_tile_rows, height, ctu_size, MAX_TILES)?;
+            num_tile_columns = column_widths.len() as u32;
+            num_tile_rows = row_heights.len() as u32;
+            if num_tile_columns * num_tile_rows > MAX_TILES {
+                return Err(ParseError::InvalidValue("pps_num_exp_tile_rows_minus1"));
+            }
+
+            if num_tile_columns * num_tile_rows > 1 {
+                let _loop_filter_across_tiles_enabled = reader.read_flag()?;
+                rect_slices = reader.read_flag()?;
+            }
+            let single_slice_per_subpic = rect_slices && reader.read_flag()?;
+            let mut num_slices_in_pic_minus1 = 0;
+            if rect_slices && !single_slice_per_subpic {
+                num_slices_in_pic_minus1 = reader.read_ue()?;
+                skip_rect_slice_layout(
+                    reader,
+                    num_slices_in_pic_minus1,
+                    &row_heights,
+                    num_tile_columns,
+                )?;
+            }
+            if !rect_slices || single_slice_per_subpic || num_slices_in_pic_minus1 > 0 {
+                let _loop_filter_across_slices_enabled = reader.read_flag()?;
+            }
+        }
+
+        let cabac_init_present = reader.read_flag()?;
+        for _ in 0..2 {
+            let _num_ref_idx_default_active_minus1 = reader.read_ue()?;
+        }
+        let _rpl1_idx_present = reader.read_flag()?;
+        let weighted_pred = reader.read_flag()?;
+        let weighted_bipred = reader.read_flag()?;
+        let ref_wraparound_enabled = reader.read_flag()?;
+        if ref_wraparound_enabled {
+            let _pic_width_minus_wraparound_offset = reader.read_ue()?;
+        }
+        let init_qp = 26 + reader.read_se()?;
+        let _cu_qp_delta_enabled = reader.read_flag()?;
+        let chroma_tool_offsets_present = reader.read_flag()?;
+        if chroma_tool_offsets_present {
+            let _cb_qp_offset = reader.read_se()?;
+            let _cr_qp_offset = reader.read_se()?;
+            let joint_cbcr_qp_offset_present = reader.read_flag()?;
+            if joint_cbcr_qp_offset_present {
+                let _joint_cbcr_qp_offset_value = reader.read_se()?;
+            }
+            let _slice_chroma_qp_offsets_present = reader.read_flag()?;
+            let cu_chroma_qp_offset_list_enabled = reader.read_flag()?;
+            if cu_chroma_qp_offset_list_enabled {
+                let chroma_qp_offset_list_len_minus1 = reader.read_ue()?;
+                if chroma_qp_offset_list_len_minus1 > 5 {
+                    return Err(ParseError::InvalidValue(
+                        "pps_chroma_qp_offset_list_len_minus1",
+                    ));
+                }
+                let num_offsets = if joint_cbcr_qp_offset_present { 3 } else { 2 };
+                for _ in 0..=chroma_qp_offset_list_len_minus1 {
+                    for _ in 0..num_offsets {
+                        reader.read_se()?;
+                    }
+                }
+            }
+        }
+
+        let mut deblocking_filter_disabled = false;
+        let deblocking_filter_control_present = reader.read_flag()?;
+        if deblocking_filter_control_present {
+            let deblocking_filter_override_enabled = reader.read_flag()?;
+            deblocking_filter_disabled = reader.read_flag()?;
+            if !no_pic_partition && deblocking_filter_override_enabled {
+                let _dbf_info_in_ph = reader.read_flag()?;
+            }
+            if !deblocking_filter_disabled {
+                let num_offsets = if chroma_tool_offsets_present { 6 } else { 2 };
+                for _ in 0..num_offsets {
+                    reader.read_se()?;
+                }
+            }
+        }
+
+        if !no_pic_partition {
+            let rpl_info_in_ph = reader.read_flag()?;
+            let _sao_info_in_ph = reader.read_flag()?;
+            let _alf_info_in_ph = reader.read_flag()?;
+            if (weighted_pred || weighted_bipred) && rpl_info_in_ph {
+                let _wp_info_in_ph = reader.read_flag()?;
+            }
+            let _qp_delta_info_in_ph = reader.read_flag()?;
+        }
+        let _picture_header_extension_present = reader.read_flag()?;
+        let _slice_header_extension_present = reader.read_flag()?;
+        let _extension = reader.read_flag()?;
+
+        Ok(Self {
+            id,
+            sps_id,
+            mixed_nal_unit_types_in_pic,
+            width,
+            height,
+            conformance_window,
+            output_flag_present,
+            num_tile_columns,
+            num_tile_rows,
+            rect_slices,
+            cabac_init_present,
+            init_qp,
+            weighted_pred,
+            weighted_bipred,
+            deblocking_filter_disabled,
+        })
+    }
+}
+
+/// Conformance window, with offsets in units of chroma samples.
+///
+/// The offsets are multiplied by the chroma subsampling factors to get luma sample offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceWindow {
+    /// Left offset.
+    pub left_offset: u32,
+    /// Right offset.
+    pub right_offset: u32,
+    /// Top offset.
+    pub top_offset: u32,
+    /// Bottom offset.
+    pub bottom_offset: u32,
+}
+
+impl ConformanceWindow {
+    fn parse(reader: &mut RbspReader) -> Result<Option<Self>, ParseError> {
+        let present = reader.read_flag()?;
+        if !present {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            left_offset: reader.read_ue()?,
+            right_offset: reader.read_ue()?,
+            top_offset: reader.read_ue()?,
+            bottom_offset: reader.read_ue()?,
+        }))
+    }
+}
+
+/// Decoded picture buffer parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DpbParameters {
+    /// Maximum number of pictures in the decoded picture buffer.
+    pub max_dec_pic_buffering: u32,
+    /// Maximum number of pictures that can precede any picture in decoding order and follow it in output order.
+    pub max_num_reorder_pics: u32,
+    /// Maximum latency, in pictures, plus 1, or 0 if there is no limit.
+    pub max_latency_increase_plus1: u32,
+}
+
+impl DpbParameters {
+    // Only the parameters for the highest sublayer are kept.
+    fn parse(
+        reader: &mut RbspReader,
+        max_sublayers_minus1: u32,
+        sublayer_info: bool,
+    ) -> Result<Self, ParseError> {
+        let first_sublayer = if sublayer_info {
+            0
+        } else {
+            max_sublayers_minus1
+        };
+        let mut dpb_parameters = None;
+        for _ in first_sublayer..=max_sublayers_minus1 {
+            dpb_parameters = Some(Self {
+                max_dec_pic_buffering: reader.read_ue()?.saturating_add(1),
+                max_num_reorder_pics: reader.read_ue()?,
+                max_latency_increase_plus1: reader.read_ue()?,
+            });
+        }
+        Ok(dpb_parameters.expect("at least one sublayer"))
+    }
+}
+
+struct RefPicListContext {
+    long_term_ref_pics: bool,
+    inter_layer_prediction_enabled: bool,
+    weighted_prediction: bool,
+    log2_max_pic_order_cnt_lsb: u32,
+}
+
+impl RefPicListContext {
+    // ref_pic_list_struct() as signalled in the SPS.
+    fn skip_ref_pic_list_struct(&self, reader: &mut RbspReader) -> Result<(), ParseError> {
+        let num_ref_entries = reader.read_ue()?;
+        if num_ref_entries > 29 {
+            return Err(ParseError::InvalidValue("num_ref_entries"));
+        }
+        let ltrp_in_header =
+            self.long_term_ref_pics && num_ref_entries > 0 && reader.read_flag()?;
+        for i in 0..num_ref_entries {
+            let inter_layer_ref_pic = self.inter_layer_prediction_enabled && reader.read_flag()?;
+            if inter_layer_ref_pic {
+                let _ilrp_idx = reader.read_ue()?;
+                continue;
+            }
+
+            let st_ref_pic = !self.long_term_ref_pics || reader.read_flag()?;
+            if st_ref_pic {
+                let abs_delta_poc_st = reader.read_ue()?;
+                // AbsDeltaPocSt is abs_delta_poc_st + 1, except for entries which may refer to the same picture.
+                let may_be_zero = self.weighted_prediction && i != 0;
+                if !may_be_zero || abs_delta_poc_st > 0 {
+                    let _strp_entry_sign = reader.read_flag()?;
+                }
+            } else if !ltrp_in_header {
+                reader.skip_bits(self.log2_max_pic_order_cnt_lsb as usize)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_general_timing_hrd_parameters(reader: &mut RbspReader) -> Result<Hrd, ParseError> {
+    let num_units_in_tick = reader.read_bits(32)?;
+    let time_scale = reader.read_bits(32)?;
+    let has_nal_hrd_params = reader.read_flag()?;
+    let has_vcl_hrd_params = reader.read_flag()?;
+
+    let mut hrd = Hrd {
+        num_units_in_tick,
+        time_scale,
+        has_nal_hrd_params,
+        has_vcl_hrd_params,
+        is_same_pic_timing_in_all_ols: false,
+        has_decoding_unit_hrd_params: false,
+        tick_divisor: None,
+        bit_rate_scale: 0,
+        cpb_size_scale: 0,
+        cpb_size_du_scale: None,
+        cpb_count: 1,
+        sublayers: Vec::new(),
+    };
+    if has_nal_hrd_params || has_vcl_hrd_params {
+        hrd.is_same_pic_timing_in_all_ols = reader.read_flag()?;
+        hrd.has_decoding_unit_hrd_params = reader.read_flag()?;
+        if hrd.has_decoding_unit_hrd_params {
+            hrd.tick_divisor = Some(reader.read_bits(8)? + 2);
+        }
+        hrd.bit_rate_scale = reader.read_bits(4)?;
+        hrd.cpb_size_scale = reader.read_bits(4)?;
+        if hrd.has_decoding_unit_hrd_params {
+            hrd.cpb_size_du_scale = Some(reader.read_bits(4)?);
+        }
+        let cpb_count_minus1 = reader.read_ue()?;
+        if cpb_count_minus1 > 31 {
+            return Err(ParseError::InvalidValue("hrd_cpb_cnt_minus1"));
+        }
+        hrd.cpb_count = cpb_count_minus1 + 1;
+    }
+    Ok(hrd)
+}
+
+fn parse_ols_timing_hrd_parameters(
+    reader: &mut RbspReader,
+    hrd: &Hrd,
+    first_sublayer: u32,
+    max_sublayers_minus1: u32,
+) -> Result<Vec<SublayerHrd>, ParseError> {
+    let mut sublayers = Vec::new();
+    for temporal_id in first_sublayer..=max_sublayers_minus1 {
+        let is_fixed_pic_rate_general = reader.read_flag()?;
+        let is_fixed_pic_rate_within_cvs = is_fixed_pic_rate_general || reader.read_flag()?;
+        let mut elemental_duration_in_tc = None;
+        let mut is_low_delay = false;
+        if is_fixed_pic_rate_within_cvs {
+            elemental_duration_in_tc = Some(reader.read_ue()?.saturating_add(1));
+        } else if (hrd.has_nal_hrd_params || hrd.has_vcl_hrd_params) && hrd.cpb_count == 1 {
+            is_low_delay = reader.read_flag()?;
+        }
+
+        let mut nal_cpb_params = Vec::new();
+        if hrd.has_nal_hrd_params {
+            nal_cpb_params = parse_sublayer_hrd_parameters(reader, hrd)?;
+        }
+        let mut vcl_cpb_params = Vec::new();
+        if hrd.has_vcl_hrd_params {
+            vcl_cpb_params = parse_sublayer_hrd_parameters(reader, hrd)?;
+        }
+        sublayers.push(SublayerHrd {
+            temporal_id: temporal_id as u8,
+            is_fixed_pic_rate_general,
+            is_fixed_pic_rate_within_cvs,
+            elemental_duration_in_tc,
+            is_low_delay,
+            nal_cpb_params,
+            vcl_cpb_params,
+        });
+    }
+    Ok(sublayers)
+}
+
+// Bit rates and CPB sizes are derived from their value and scale as in H.266 section C.1.
+fn parse_sublayer_hrd_parameters(
+    reader: &mut RbspReader,
+    hrd: &Hrd,
+) -> Result<Vec<CpbParams>, ParseError> {
+    let bit_rate = |value_minus1: u32| (value_minus1 as u64 + 1) << (6 + hrd.bit_rate_scale);
+    let cpb_size = |value_minus1: u32, scale: u32| (value_minus1 as u64 + 1) << (4 + scale);
+
+    let mut cpb_params = Vec::new();
+    for _ in 0..hrd.cpb_count {
+        let bit_rate_value_minus1 = reader.read_ue()?;
+        let cpb_size_value_minus1 = reader.read_ue()?;
+        let mut cpb_size_du = None;
+        let mut bit_rate_du = None;
+        if let Some(cpb_size_du_scale) = hrd.cpb_size_du_scale {
+            cpb_size_du = Some(cpb_size(reader.read_ue()?, cpb_size_du_scale));
+            bit_rate_du = Some(bit_rate(reader.read_ue()?));
+        }
+        cpb_params.push(CpbParams {
+            bit_rate: bit_rate(bit_rate_value_minus1),
+            cpb_size: cpb_size(cpb_size_value_minus1, hrd.cpb_size_scale),
+            cpb_size_du,
+            bit_rate_du,
+            is_cbr: reader.read_flag()?,
+        });
+    }
+    Ok(cpb_params)
+}
+
+// Sizes of tile columns or rows in CTUs, as derived in H.266 section 6.5.1.
+//
+// The number of tiles is checked against `max_tiles` before allocating, as the picture size in CTUs
+// is only bounded by the width and height signalled in the PPS.
+fn parse_tile_sizes(
+    reader: &mut RbspReader,
+    num_explicit: usize,
+    size_in_samples: u32,
+    ctu_size: u32,
+    max_tiles: u32,
+) -> Result<Vec<u32>, ParseError> {
+    let size_in_ctus = size_in_samples.div_ceil(ctu_size);
+    if num_explicit > size_in_ctus.min(max_tiles) as usize {
+        return Err(ParseError::InvalidValue("pps_num_exp_tile_columns_minus1"));
+    }
+
+    let mut sizes = Vec::with_capacity(num_explicit);
+    let mut remaining = size_in_ctus;
+    for _ in 0..num_explicit {
+        let size = reader.read_ue()?.saturating_add(1);
+        remaining = remaining
+            .checked_sub(size)
+            .ok_or(ParseError::InvalidValue("pps_tile_column_width_minus1"))?;
+        sizes.push(size);
+    }
+    let uniform_size = *sizes.last().expect("at least one explicit tile size");
+    if num_explicit as u32 + remaining.div_ceil(uniform_size) > max_tiles {
+        return Err(ParseError::InvalidValue("pps_tile_column_width_minus1"));
+    }
+    while remaining >= uniform_size {
+        sizes.push(uniform_size);
+        remaining -= uniform_size;
+    }
+    if remaining > 0 {
+        sizes.push(remaining);
+    }
+    Ok(sizes)
+}
+
+// Rectangular slice layout, following the derivation of SliceTopLeftTileIdx in H.266 section 6.5.1.
+fn skip_rect_slice_layout(
+    reader: &mut RbspReader,
+    num_slices_in_pic_minus1: u32,
+    row_heights: &[u32],
+    num_tile_columns: u32,
+) -> Result<(), ParseError> {
+    let num_tile_rows = row_heights.len() as u32;
+    let num_tiles = num_tile_columns * num_tile_rows;
+    if num_slices_in_pic_minus1 >= MAX_SLICES {
+        return Err(ParseError::InvalidValue("pps_num_slices_in_pic_minus1"));
+    }
+
+    let tile_idx_delta_present = num_slices_in_pic_minus1 > 1 && reader.read_flag()?;
+    let mut tile_idx = 0u32;
+    let mut slice_height_in_tiles_minus1 = 0;
+    let mut i = 0;
+    while i < num_slices_in_pic_minus1 {
+        if tile_idx >= num_tiles {
+            return Err(ParseError::InvalidValue("pps_tile_idx_delta_val"));
+        }
+        let tile_x = tile_idx % num_tile_columns;
+        let tile_y = tile_idx / num_tile_columns;
+
+        let slice_width_in_tiles_minus1 = if tile_x != num_tile_columns - 1 {
+            reader.read_ue()?
+        } else {
+            0
+        };
+        slice_height_in_tiles_minus1 =
+            if tile_y != num_tile_rows - 1 && (tile_idx_delta_present || tile_x == 0) {
+                reader.read_ue()?
+            } else if tile_y == num_tile_rows - 1 {
+                0
+            } else {
+                slice_height_in_tiles_minus1
+            };
+
+        let row_height = row_heights[tile_y as usize];
+        if slice_width_in_tiles_minus1 == 0 && slice_height_in_tiles_minus1 == 0 && row_height > 1 {
+            let num_exp_slices_in_tile = reader.read_ue()?;
+            if num_exp_slices_in_tile > row_height {
+                return Err(ParseError::InvalidValue("pps_num_exp_slices_in_tile"));
+            }
+            let mut num_slices_in_tile = 0;
+            let mut remaining = row_height;
+            let mut uniform_height = 0;
+            for _ in 0..num_exp_slices_in_tile {
+                uniform_height = reader.read_ue()?.saturating_add(1);
+                remaining =
+                    remaining
+                        .checked_sub(uniform_height)
+                        .ok_or(ParseError::InvalidValue(
+                            "pps_exp_slice_height_in_ctus_minus1",
+                        ))?;
+                num_slices_in_tile += 1;
+            }
+            if num_exp_slices_in_tile == 0 {
+                num_slices_in_tile = 1;
+            } else {
+                while remaining >= uniform_height {
+                    remaining -= uniform_height;
+                    num_slices_in_tile += 1;
+                }
+                if remaining > 0 {
+                    num_slices_in_tile += 1;
+                }
+            }
+            i += num_slices_in_tile - 1;
+        }
+
+        if tile_idx_delta_present && i < num_slices_in_pic_minus1 {
+            let tile_idx_delta = reader.read_se()?;
+            tile_idx = tile_idx
+                .checked_add_signed(tile_idx_delta)
+                .ok_or(ParseError::InvalidValue("pps_tile_idx_delta_val"))?;
+        } else {
+            tile_idx += slice_width_in_tiles_minus1 + 1;
+            if tile_idx % num_tile_columns == 0 {
+                tile_idx += slice_height_in_tiles_minus1 * num_tile_columns;
+            }
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+fn payload(nal_unit: &[u8], nal_type: NalType) -> Result<&[u8], ParseError> {
+    let nal_unit = strip_start_code(nal_unit);
+    match NalUnitHeader::parse(nal_unit) {
+        Some(header) if header.nal_type == nal_type => Ok(&nal_unit[NalUnitHeader::SIZE..]),
+        _ => Err(ParseError::InvalidHeader),
+    }
+}
+
+fn color_format(chroma_format_idc: u32) -> ColorFormat {
+    match chroma_format_idc {
+        0 => ColorFormat::Yuv400Planar,
+        1 => ColorFormat::Yuv420Planar,
+        2 => ColorFormat::Yuv422Planar,
+        _ => ColorFormat::Yuv444Planar,
+    }
+}
+
+fn cropped_size(
+    width: u32,
+    height: u32,
+    conformance_window: Option<&ConformanceWindow>,
+    color_format: &ColorFormat,
+) -> (u32, u32) {
+    let Some(window) = conformance_window else {
+        return (width, height);
+    };
+    let (sub_width, sub_height) = match color_format {
+        ColorFormat::Yuv420Planar => (2, 2),
+        ColorFormat::Yuv422Planar => (2, 1),
+        _ => (1, 1),
+    };
+    (
+        width.saturating_sub(sub_width * (window.left_offset + window.right_offset)),
+        height.saturating_sub(sub_height * (window.top_offset + window.bottom_offset)),
+    )
+}
+
+fn ceil_log2(value: u32) -> u32 {
+    u32::BITS - value.saturating_sub(1).leading_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    use crate::nal::{AnnexBSplitter, Profile, Tier};
+
+    fn find_nal_unit(data: &[u8], nal_type: NalType) -> &[u8] {
+        AnnexBSplitter::new(data)
+            .find(|nal_unit| NalUnitHeader::parse(nal_unit).unwrap().nal_type == nal_type)
+            .unwrap()
+    }
+
+    // Parses the whole RBSP, checking that nothing is left but the trailing bits.
+    fn parse_all<T>(
+        nal_unit: &[u8],
+        nal_type: NalType,
+        parse_rbsp: fn(&mut RbspReader) -> Result<T, ParseError>,
+    ) -> T {
+        let mut reader = RbspReader::new(payload(nal_unit, nal_type).unwrap());
+        let parsed = parse_rbsp(&mut reader).unwrap();
+        assert!(!reader.more_rbsp_data());
+        parsed
+    }
+
+    #[derive(Default)]
+    struct BitWriter {
+        data: Vec<u8>,
+        len: usize,
+    }
+
+    impl BitWriter {
+        fn write_bits(&mut self, value: u32, n: usize) -> &mut Self {
+            for i in (0..n).rev() {
+                if self.len % 8 == 0 {
+                    self.data.push(0);
+                }
+                let bit = ((value >> i) & 1) as u8;
+                *self.data.last_mut().unwrap() |= bit << (7 - self.len % 8);
+                self.len += 1;
+            }
+            self
+        }
+
+        fn write_ue(&mut self, value: u32) -> &mut Self {
+            let n = 32 - (value + 1).leading_zeros() as usize;
+            self.write_bits(0, n - 1).write_bits(value + 1, n)
+        }
+
+        fn write_se(&mut self, value: i32) -> &mut Self {
+            let code = if value > 0 { 2 * value - 1 } else { -2 * value };
+            self.write_ue(code as u32)
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            self.write_bits(1, 1);
+            mem::take(&mut self.data)
+        }
+    }
+
+    #[test]
+    fn test_parse_sps() {
+        let sps = parse_all(
+            find_nal_unit(include_bytes!("../../tests/short.vvc"), NalType::Sps),
+            NalType::Sps,
+            Sps::parse_rbsp,
+        );
+        assert_eq!(sps.id, 0);
+        assert_eq!(sps.max_sublayers, 6);
+        assert_eq!(sps.color_format, ColorFormat::Yuv420Planar);
+        assert_eq!(sps.ctu_size, 128);
+        assert_eq!((sps.max_width, sps.max_height), (320, 240));
+        assert_eq!(sps.cropped_size(), (320, 240));
+        assert_eq!(sps.bit_depth, 10);
+        assert!(!sps.field_seq);
+        assert!(!sps.has_vui);
+
+        let profile_tier_level = sps.profile_tier_level.as_ref().unwrap();
+        assert_eq!(profile_tier_level.profile, Profile::Main10);
+        assert_eq!(profile_tier_level.tier, Tier::Main);
+        assert_eq!(profile_tier_level.sublayer_levels.len(), 6);
+
+        let hrd = sps.hrd.as_ref().unwrap();
+        assert_eq!((hrd.num_units_in_tick, hrd.time_scale), (1, 25));
+        assert_eq!(sps.elemental_duration_in_tc, Some(1));
+        assert_eq!(hrd.sublayers.len(), 1);
+        assert_eq!(hrd.sublayers[0].temporal_id, 5);
+        assert!(hrd.sublayers[0].is_fixed_pic_rate_general);
+        assert!(hrd.sublayers[0].nal_cpb_params.is_empty());
+        assert!(hrd.sublayers[0].vcl_cpb_params.is_empty());
+
+        let sps2 = parse_all(
+            find_nal_unit(include_bytes!("../../tests/short2.vvc"), NalType::Sps),
+            NalType::Sps,
+            Sps::parse_rbsp,
+        );
+        assert_eq!((sps2.max_width, sps2.max_height), (160, 120));
+
+        // The start code is optional.
+        let sps_nal_unit = find_nal_unit(include_bytes!("../../tests/short.vvc"), NalType::Sps);
+        assert_eq!(Sps::parse(sps_nal_unit), Ok(sps.clone()));
+        assert_eq!(Sps::parse(strip_start_code(sps_nal_unit)), Ok(sps));
+    }
+
+    #[test]
+    fn test_parse_pps() {
+        let data = include_bytes!("../../tests/short.vvc");
+        let pps = parse_all(
+            find_nal_unit(data, NalType::Pps),
+            NalType::Pps,
+            Pps::parse_rbsp,
+        );
+        assert_eq!((pps.id, pps.sps_id), (0, 0));
+        assert_eq!((pps.width, pps.height), (320, 240));
+        assert_eq!((pps.num_tile_columns, pps.num_tile_rows), (1, 1));
+        assert_eq!(pps.init_qp, 32);
+
+        let sps = Sps::parse(find_nal_unit(data, NalType::Sps)).unwrap();
+        assert_eq!(pps.cropped_size(&sps), (320, 240));
+        // The conformance window of the SPS applies to pictures of its maximum size.
+        let sps = Sps {
+            conformance_window: Some(ConformanceWindow {
+                left_offset: 0,
+                right_offset: 2,
+                top_offset: 0,
+                bottom_offset: 4,
+            }),
+            ..sps
+        };
+        assert_eq!(pps.cropped_size(&sps), (316, 232));
+
+        let pps2 = Pps::parse(find_nal_unit(
+            include_bytes!("../../tests/short2.vvc"),
+            NalType::Pps,
+        ));
+        assert_eq!(pps2.map(|pps| (pps.width, pps.height)), Ok((160, 120)));
+    }
+
+    #[test]
+    fn test_parse_pps_with_tiles() {
+        let mut writer = BitWriter::default();
+        writer
+            .write_bits(0, 6)
+            .write_bits(0, 4)
+            .write_bits(0, 1)
+            .write_ue(256)
+            .write_ue(128)
+            .write_bits(0, 5)
+            .write_bits(2, 2) // CTU size of 128
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0) // Uniform tile columns of 1 CTU
+            .write_ue(0)
+            .write_bits(0b01, 2) // Rectangular slices
+            .write_bits(0, 1)
+            .write_ue(1) // 2 slices
+            .write_ue(0)
+            .write_bits(0, 1)
+            .write_bits(1, 1) // CABAC init present
+            .write_ue(0)
+            .write_ue(0)
+            .write_bits(0, 4)
+            .write_se(-4)
+            .write_bits(0, 10);
+        let rbsp = writer.finish();
+
+        let mut reader = RbspReader::new(&rbsp);
+        let pps = Pps::parse_rbsp(&mut reader).unwrap();
+        assert!(!reader.more_rbsp_data());
+        assert_eq!((pps.num_tile_columns, pps.num_tile_rows), (2, 1));
+        assert!(pps.rect_slices);
+        assert!(pps.cabac_init_present);
+        assert_eq!(pps.init_qp, 22);
+    }
+
+    #[test]
+    fn test_parse_pps_with_too_many_tiles() {
+        let mut writer = BitWriter::default();
+        writer
+            .write_bits(0, 6)
+            .write_bits(0, 4)
+            .write_bits(0, 1)
+            .write_ue(u32::MAX - 1)
+            .write_ue(128)
+            .write_bits(0, 5)
+            .write_bits(0, 2) // CTU size of 32
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0); // Uniform tile columns of 1 CTU
+        let rbsp = writer.finish();
+
+        let mut reader = RbspReader::new(&rbsp);
+        assert_eq!(
+            Pps::parse_rbsp(&mut reader),
+            Err(ParseError::InvalidValue("pps_tile_column_width_minus1"))
+        );
+    }
+
+    #[test]
+    fn test_parse_sps_with_too_many_subpics() {
+        let mut writer = BitWriter::default();
+        writer
+            .write_bits(0, 4)
+            .write_bits(0, 4)
+            .write_bits(0, 3) // 1 sublayer
+            .write_bits(1, 2) // 4:2:0
+            .write_bits(2, 2) // CTU size of 128
+            .write_bits(0, 3) // No PTL, DPB and HRD parameters
+            .write_ue(320)
+            .write_ue(240)
+            .write_bits(0, 1)
+            .write_bits(1, 1) // Subpicture info present
+            .write_ue(1000);
+        let rbsp = writer.finish();
+
+        let mut reader = RbspReader::new(&rbsp);
+        assert_eq!(
+            Sps::parse_rbsp(&mut reader),
+            Err(ParseError::InvalidValue("sps_num_subpics_minus1"))
+        );
+    }
+
+    // SPS with HRD timing parameters but no NAL or VCL HRD parameters, and a picture rate that is not
+    // fixed, so that low_delay_hrd_flag is absent.
+    #[test]
+    fn test_parse_sps_with_timing_only_hrd() {
+        let mut writer = BitWriter::default();
+        writer
+            .write_bits(0, 4)
+            .write_bits(0, 4)
+            .write_bits(0, 3) // 1 sublayer
+            .write_bits(1, 2) // 4:2:0
+            .write_bits(2, 2) // CTU size of 128
+            .write_bits(1, 1) // PTL, DPB and HRD parameters present
+            .write_bits(1, 7) // Main 10
+            .write_bits(0, 1)
+            .write_bits(51, 8)
+            .write_bits(0, 2)
+            .write_bits(0, 1) // No general constraints info
+            .write_bits(0, 5)
+            .write_bits(0, 8) // No sub-profiles
+            .write_bits(0, 2)
+            .write_ue(320)
+            .write_ue(240)
+            .write_bits(0, 2)
+            .write_ue(2) // 10 bits
+            .write_bits(0, 2)
+            .write_bits(4, 4)
+            .write_bits(0, 5)
+            .write_ue(1) // DPB parameters
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0) // Partitioning
+            .write_bits(0, 1)
+            .write_ue(0)
+            .write_ue(0)
+            .write_bits(0, 1)
+            .write_ue(0)
+            .write_ue(0)
+            .write_bits(0, 1)
+            .write_bits(0, 3) // Transform skip, MTS and LFNST
+            .write_bits(0b01, 2) // Same chroma QP table
+            .write_se(0)
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0)
+            .write_bits(0, 7) // SAO to IDR RPL
+            .write_bits(1, 1) // RPL1 same as RPL0
+            .write_ue(0)
+            .write_bits(0, 7) // Wraparound to MMVD
+            .write_ue(5) // 1 merge candidate
+            .write_bits(0, 4) // SBT to CIIP
+            .write_ue(0)
+            .write_bits(0, 8) // ISP to palette
+            .write_bits(0, 5) // LADF to virtual boundaries
+            .write_bits(1, 1) // Timing and HRD parameters present
+            .write_bits(1, 32)
+            .write_bits(50, 32)
+            .write_bits(0, 2) // No NAL or VCL HRD parameters
+            .write_bits(0, 2) // Picture rate not fixed
+            .write_bits(1, 1) // Field sequence
+            .write_bits(0, 2);
+        let rbsp = writer.finish();
+
+        let mut reader = RbspReader::new(&rbsp);
+        let sps = Sps::parse_rbsp(&mut reader).unwrap();
+        assert!(!reader.more_rbsp_data());
+        let hrd = sps.hrd.unwrap();
+        assert_eq!((hrd.num_units_in_tick, hrd.time_scale), (1, 50));
+        assert_eq!(sps.elemental_duration_in_tc, None);
+        assert!(!hrd.sublayers[0].is_fixed_pic_rate_within_cvs);
+        assert!(!hrd.sublayers[0].is_low_delay);
+        assert!(sps.field_seq);
+        assert!(!sps.has_vui);
+    }
+
+    // Timing and HRD parameters of 2 sublayers with NAL HRD parameters for decoding units.
+    #[test]
+    fn test_parse_ols_timing_hrd_parameters() {
+        let hrd = Hrd {
+            num_units_in_tick: 1,
+            time_scale: 50,
+            has_nal_hrd_params: true,
+            has_vcl_hrd_params: false,
+            is_same_pic_timing_in_all_ols: true,
+            has_decoding_unit_hrd_params: true,
+            tick_divisor: Some(2),
+            bit_rate_scale: 1,
+            cpb_size_scale: 3,
+            cpb_size_du_scale: Some(2),
+            cpb_count: 1,
+            sublayers: Vec::new(),
+        };
+        let mut writer = BitWriter::default();
+        writer
+            .write_bits(0, 2) // Picture rate not fixed
+            .write_bits(1, 1) // Low delay
+            .write_ue(9)
+            .write_ue(99)
+            .write_ue(4)
+            .write_ue(1)
+            .write_bits(1, 1) // CBR
+            .write_bits(1, 1) // Picture rate fixed
+            .write_ue(1)
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0)
+            .write_ue(0)
+            .write_bits(0, 1);
+        let rbsp = writer.finish();
+
+        let mut reader = RbspReader::new(&rbsp);
+        let sublayers = parse_ols_timing_hrd_parameters(&mut reader, &hrd, 0, 1).unwrap();
+        assert!(!reader.more_rbsp_data());
+        assert_eq!(
+            sublayers,
+            [
+                SublayerHrd {
+                    temporal_id: 0,
+                    is_fixed_pic_rate_general: false,
+                    is_fixed_pic_rate_within_cvs: false,
+                    elemental_duration_in_tc: None,
+                    is_low_delay: true,
+                    nal_cpb_params: vec![CpbParams {
+                        bit_rate: 1280,
+                        cpb_size: 12800,
+                        cpb_size_du: Some(320),
+                        bit_rate_du: Some(256),
+                        is_cbr: true,
+                    }],
+                    vcl_cpb_params: vec![],
+                },
+                SublayerHrd {
+                    temporal_id: 1,
+                    is_fixed_pic_rate_general: true,
+                    is_fixed_pic_rate_within_cvs: true,
+                    elemental_duration_in_tc: Some(2),
+                    is_low_delay: false,
+                    nal_cpb_params: vec![CpbParams {
+                        bit_rate: 128,
+                        cpb_size: 128,
+                        cpb_size_du: Some(64),
+                        bit_rate_du: Some(128),
+                        is_cbr: false,
+                    }],
+                    vcl_cpb_params: vec![],
+                },
+            ]
+        );
+        assert_eq!(hrd.frame_rate(2), Some((50, 2)));
+    }
+
+    #[test]
+    fn test_parse_wrong_nal_type() {
+        let pps = find_nal_unit(include_bytes!("../../tests/short.vvc"), NalType::Pps);
+        assert_eq!(Sps::parse(pps), Err(ParseError::InvalidHeader));
+        assert_eq!(Vps::parse(pps), Err(ParseError::InvalidHeader));
+        assert_eq!(Pps::parse(&pps[..6]), Err(ParseError::EndOfData));
+    }
+
+    #[test]
+    fn test_cropped_size() {
+        let window = ConformanceWindow {
+            left_offset: 0,
+            right_offset: 4,
+            top_offset: 1,
+            bottom_offset: 3,
+        };
+        assert_eq!(
+            cropped_size(1928, 1088, Some(&window), &ColorFormat::Yuv420Planar),
+            (1920, 1080)
+        );
+        assert_eq!(
+            cropped_size(1928, 1088, Some(&window), &ColorFormat::Yuv444Planar),
+            (1924, 1084)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use super::{strip_start_code, NalUnitHeader, ParseError, Pps, RbspReader, Sps};
+use crate::NalType;
+
+/// Picture header (PH).
+///
+/// Only the syntax elements up to the picture order count LSBs are parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PictureHeader {
+    /// Is the picture an IRAP or GDR picture?
+    pub gdr_or_irap: bool,
+    /// Is the picture never used as a reference picture?
+    pub non_ref: bool,
+    /// Is the picture a GDR picture?
+    pub gdr: bool,
+    /// ID of the PPS referred to.
+    pub pps_id: u8,
+    /// Picture order count modulo `1 << Sps::log2_max_pic_order_cnt_lsb`.
+    pub pic_order_cnt_lsb: u32,
+}
+
+impl PictureHeader {
+    /// Parse the picture header of a PH NAL unit, or of a slice NAL unit carrying it in its slice header, with or
+    /// without its start code.
+    ///
+    /// The PPS and SPS referred to are looked up by ID in `pps` and `sps`.
+    pub fn parse(nal_unit: &[u8], pps: &[Pps], sps: &[Sps]) -> Result<Self, ParseError> {
+        let nal_unit = strip_start_code(nal_unit);
+        let header = NalUnitHeader::parse(nal_unit).ok_or(ParseError::InvalidHeader)?;
+        let mut reader = RbspReader::new(&nal_unit[NalUnitHeader::SIZE..]);
+        if header.nal_type.is_vcl() {
+            if !reader.read_flag()? {
+                return Err(ParseError::InvalidValue(
+                    "sh_picture_header_in_slice_header_flag",
+                ));
+            }
+        } else if header.nal_type != NalType::Ph {
+            return Err(ParseError::InvalidHeader);
+        }
+
+        let gdr_or_irap = reader.read_flag()?;
+        let non_ref = reader.read_flag()?;
+        let gdr = gdr_or_irap && reader.read_flag()?;
+        let inter_slice_allowed = reader.read_flag()?;
+        if inter_slice_allowed {
+            let _intra_slice_allowed = reader.read_flag()?;
+        }
+        let pps_id = reader.read_ue()?;
+        let sps = pps
+            .iter()
+            .find(|pps| u32::from(pps.id) == pps_id)
+            .and_then(|pps| sps.iter().find(|sps| sps.id == pps.sps_id))
+            .ok_or(ParseError::InvalidValue("ph_pic_parameter_set_id"))?;
+        let pic_order_cnt_lsb = reader.read_bits(sps.log2_max_pic_order_cnt_lsb)?;
+
+        Ok(Self {
+            gdr_or_irap,
+            non_ref,
+            gdr,
+            pps_id: pps_id as u8,
+            pic_order_cnt_lsb,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nal::AnnexBSplitter;
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+
+    #[test]
+    fn test_parse_picture_header() {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).collect();
+        let sps = [Sps::parse(nal_units[0]).unwrap()];
+        let pps = [Pps::parse(nal_units[1]).unwrap()];
+
+        let headers: Vec<_> = nal_units[3..]
+            .iter()
+            .map(|nal_unit| PictureHeader::parse(nal_unit, &pps, &sps).unwrap())
+            .collect();
+        assert!(headers[0].gdr_or_irap);
+        assert!(!headers[0].gdr);
+        assert!(!headers[1].gdr_or_irap);
+        let pic_order_cnt_lsbs: Vec<_> = headers
+            .iter()
+            .map(|header| header.pic_order_cnt_lsb)
+            .collect();
+        assert_eq!(pic_order_cnt_lsbs, [0, 2, 1]);
+
+        assert_eq!(
+            PictureHeader::parse(nal_units[3], &pps, &[]),
+            Err(ParseError::InvalidValue("ph_pic_parameter_set_id"))
+        );
+        assert_eq!(
+            PictureHeader::parse(nal_units[0], &pps, &sps),
+            Err(ParseError::InvalidHeader)
+        );
+    }
+}
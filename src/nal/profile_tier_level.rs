@@ -0,0 +1,625 @@
+use std::cmp::Ordering;
+
+use super::{AnnexBSplitter, NalUnitHeader, ParseError, RbspReader, Sps, Vps};
+use crate::NalType;
+
+/// Profile, tier and level information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileTierLevel {
+    /// Profile.
+    pub profile: Profile,
+    /// Tier.
+    pub tier: Tier,
+    /// Level.
+    pub level: Level,
+    /// Does the stream only contain frames, rather than fields?
+    pub frame_only_constraint: bool,
+    /// Can the stream contain multiple layers?
+    pub multilayer_enabled: bool,
+    /// General constraints information, if present.
+    pub general_constraints_info: Option<GeneralConstraintsInfo>,
+    /// Levels of each temporal sublayer, from the lowest to the highest.
+    pub sublayer_levels: Vec<Level>,
+    /// Sub-profile indicators.
+    pub sub_profile_idcs: Vec<u32>,
+}
+
+impl ProfileTierLevel {
+    /// Probe the profile, tier and level of an Annex-B byte stream, such as the beginning of a file.
+    ///
+    /// The information is taken from the first SPS carrying it, or from the first VPS otherwise. Returns `None`
+    /// if no parameter set in `data` carries it.
+    ///
+    /// ```
+    /// use vvdec::nal::{Level, Profile, ProfileTierLevel};
+    ///
+    /// let data = include_bytes!("../../tests/short.vvc");
+    /// let profile_tier_level = ProfileTierLevel::probe(data).unwrap();
+    /// assert_eq!(profile_tier_level.profile, Profile::Main10);
+    /// assert!(profile_tier_level.level <= Level::Level5_1);
+    /// ```
+    pub fn probe(data: &[u8]) -> Option<Self> {
+        let mut from_vps = None;
+        for nal_unit in AnnexBSplitter::new(data) {
+            match NalUnitHeader::parse(nal_unit).map(|header| header.nal_type) {
+                Some(NalType::Sps) => {
+                    if let Some(profile_tier_level) = Sps::parse(nal_unit)
+                        .ok()
+                        .and_then(|sps| sps.profile_tier_level)
+                    {
+                        return Some(profile_tier_level);
+                    }
+                }
+                Some(NalType::Vps) if from_vps.is_none() => {
+                    from_vps = Vps::parse(nal_unit)
+                        .ok()
+                        .and_then(|vps| vps.profile_tier_levels.into_iter().next());
+                }
+                _ => {}
+            }
+        }
+        from_vps
+    }
+
+    pub(super) fn parse(
+        reader: &mut RbspReader,
+        profile_tier_present: bool,
+        max_sublayers_minus1: u8,
+        previous: Option<&ProfileTierLevel>,
+    ) -> Result<Self, ParseError> {
+        let (profile, tier) = if profile_tier_present {
+            let profile = Profile::new(reader.read_bits(7)? as u8);
+            let tier = if reader.read_flag()? {
+                Tier::High
+            } else {
+                Tier::Main
+            };
+            (profile, tier)
+        } else {
+            let previous = previous.ok_or(ParseError::InvalidValue("vps_pt_present_flag"))?;
+            (previous.profile, previous.tier)
+        };
+        let level = Level::new(reader.read_bits(8)? as u8);
+        let frame_only_constraint = reader.read_flag()?;
+        let multilayer_enabled = reader.read_flag()?;
+        let general_constraints_info = if profile_tier_present {
+            GeneralConstraintsInfo::parse(reader)?
+        } else {
+            previous.and_then(|previous| previous.general_constraints_info.clone())
+        };
+
+        let max_sublayers_minus1 = max_sublayers_minus1 as usize;
+        let mut sublayer_level_present = vec![false; max_sublayers_minus1];
+        for present in sublayer_level_present.iter_mut().rev() {
+            *present = reader.read_flag()?;
+        }
+        reader.byte_align()?;
+
+        // Levels of sublayers which are not present are inferred from the next higher sublayer.
+        let mut sublayer_levels = vec![level; max_sublayers_minus1 + 1];
+        for i in (0..max_sublayers_minus1).rev() {
+            sublayer_levels[i] = if sublayer_level_present[i] {
+                Level::new(reader.read_bits(8)? as u8)
+            } else {
+                sublayer_levels[i + 1]
+            };
+        }
+
+        let mut sub_profile_idcs = Vec::new();
+        if profile_tier_present {
+            let num_sub_profiles = reader.read_bits(8)?;
+            for _ in 0..num_sub_profiles {
+                sub_profile_idcs.push(reader.read_bits(32)?);
+            }
+        } else if let Some(previous) = previous {
+            sub_profile_idcs.clone_from(&previous.sub_profile_idcs);
+        }
+
+        Ok(Self {
+            profile,
+            tier,
+            level,
+            frame_only_constraint,
+            multilayer_enabled,
+            general_constraints_info,
+            sublayer_levels,
+            sub_profile_idcs,
+        })
+    }
+}
+
+/// Profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Main 10.
+    Main10,
+    /// Main 10 Still Picture.
+    Main10StillPicture,
+    /// Multilayer Main 10.
+    MultilayerMain10,
+    /// Main 10 4:4:4.
+    Main10_444,
+    /// Main 10 4:4:4 Still Picture.
+    Main10_444StillPicture,
+    /// Multilayer Main 10 4:4:4.
+    MultilayerMain10_444,
+    /// Main 12.
+    Main12,
+    /// Main 12 4:4:4.
+    Main12_444,
+    /// Main 16 4:4:4.
+    Main16_444,
+    /// Main 12 Intra.
+    Main12Intra,
+    /// Main 12 4:4:4 Intra.
+    Main12_444Intra,
+    /// Main 16 4:4:4 Intra.
+    Main16_444Intra,
+    /// Main 12 Still Picture.
+    Main12StillPicture,
+    /// Main 12 4:4:4 Still Picture.
+    Main12_444StillPicture,
+    /// Main 16 4:4:4 Still Picture.
+    Main16_444StillPicture,
+    /// Unknown profile.
+    Unknown(u8),
+}
+
+impl Profile {
+    pub(super) fn new(idc: u8) -> Self {
+        use Profile::*;
+        match idc {
+            1 => Main10,
+            65 => Main10StillPicture,
+            17 => MultilayerMain10,
+            33 => Main10_444,
+            97 => Main10_444StillPicture,
+            49 => MultilayerMain10_444,
+            2 => Main12,
+            34 => Main12_444,
+            36 => Main16_444,
+            10 => Main12Intra,
+            42 => Main12_444Intra,
+            44 => Main16_444Intra,
+            66 => Main12StillPicture,
+            98 => Main12_444StillPicture,
+            100 => Main16_444StillPicture,
+            _ => Unknown(idc),
+        }
+    }
+
+    /// Get the profile indicator (`general_profile_idc`).
+    pub fn idc(self) -> u8 {
+        use Profile::*;
+        match self {
+            Main10 => 1,
+            Main10StillPicture => 65,
+            MultilayerMain10 => 17,
+            Main10_444 => 33,
+            Main10_444StillPicture => 97,
+            MultilayerMain10_444 => 49,
+            Main12 => 2,
+            Main12_444 => 34,
+            Main16_444 => 36,
+            Main12Intra => 10,
+            Main12_444Intra => 42,
+            Main16_444Intra => 44,
+            Main12StillPicture => 66,
+            Main12_444StillPicture => 98,
+            Main16_444StillPicture => 100,
+            Unknown(idc) => idc,
+        }
+    }
+}
+
+/// Tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    /// Main tier.
+    Main,
+    /// High tier.
+    High,
+}
+
+/// Level.
+///
+/// Levels are ordered by their indicator, so that a level can be compared against a maximum supported level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Level 1.
+    Level1,
+    /// Level 2.
+    Level2,
+    /// Level 2.1.
+    Level2_1,
+    /// Level 3.
+    Level3,
+    /// Level 3.1.
+    Level3_1,
+    /// Level 4.
+    Level4,
+    /// Level 4.1.
+    Level4_1,
+    /// Level 5.
+    Level5,
+    /// Level 5.1.
+    Level5_1,
+    /// Level 5.2.
+    Level5_2,
+    /// Level 6.
+    Level6,
+    /// Level 6.1.
+    Level6_1,
+    /// Level 6.2.
+    Level6_2,
+    /// Level 6.3.
+    Level6_3,
+    /// Level 15.5, for streams which do not conform to any other level.
+    Level15_5,
+    /// Unknown level.
+    Unknown(u8),
+}
+
+impl Level {
+    pub(super) fn new(idc: u8) -> Self {
+        use Level::*;
+        match idc {
+            16 => Level1,
+            32 => Level2,
+            35 => Level2_1,
+            48 => Level3,
+            51 => Level3_1,
+            64 => Level4,
+            67 => Level4_1,
+            80 => Level5,
+            83 => Level5_1,
+            86 => Level5_2,
+            96 => Level6,
+            99 => Level6_1,
+            102 => Level6_2,
+            105 => Level6_3,
+            255 => Level15_5,
+            _ => Unknown(idc),
+        }
+    }
+
+    /// Get the level indicator (`general_level_idc`), which is 16 times the level number.
+    pub fn idc(self) -> u8 {
+        use Level::*;
+        match self {
+            Level1 => 16,
+            Level2 => 32,
+            Level2_1 => 35,
+            Level3 => 48,
+            Level3_1 => 51,
+            Level4 => 64,
+            Level4_1 => 67,
+            Level5 => 80,
+            Level5_1 => 83,
+            Level5_2 => 86,
+            Level6 => 96,
+            Level6_1 => 99,
+            Level6_2 => 102,
+            Level6_3 => 105,
+            Level15_5 => 255,
+            Unknown(idc) => idc,
+        }
+    }
+}
+
+impl PartialOrd for Level {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Level {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.idc().cmp(&other.idc())
+    }
+}
+
+/// General constraints information.
+///
+/// Each flag, when set, indicates that the corresponding tool or feature is not used in the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneralConstraintsInfo {
+    /// All slices are intra slices.
+    pub intra_only: bool,
+    /// All layers are coded independently.
+    pub all_layers_independent: bool,
+    /// There is only one access unit.
+    pub one_au_only: bool,
+    /// Maximum bit depth.
+    pub max_bit_depth: u32,
+    /// Maximum chroma format indicator, from 0 for 4:0:0 to 3 for 4:4:4.
+    pub max_chroma_format_idc: u32,
+    /// No pictures with mixed NAL unit types.
+    pub no_mixed_nalu_types_in_pic: bool,
+    /// No trailing pictures.
+    pub no_trail: bool,
+    /// No STSA pictures.
+    pub no_stsa: bool,
+    /// No RASL pictures.
+    pub no_rasl: bool,
+    /// No RADL pictures.
+    pub no_radl: bool,
+    /// No IDR pictures.
+    pub no_idr: bool,
+    /// No CRA pictures.
+    pub no_cra: bool,
+    /// No GDR pictures.
+    pub no_gdr: bool,
+    /// No APS.
+    pub no_aps: bool,
+    /// No reference picture lists in the slice headers of IDR pictures.
+    pub no_idr_rpl: bool,
+    /// One tile per picture.
+    pub one_tile_per_pic: bool,
+    /// Picture headers are in slice headers.
+    pub pic_header_in_slice_header: bool,
+    /// One slice per picture.
+    pub one_slice_per_pic: bool,
+    /// No rectangular slices.
+    pub no_rectangular_slice: bool,
+    /// One slice per subpicture.
+    pub one_slice_per_subpic: bool,
+    /// No subpicture information.
+    pub no_subpic_info: bool,
+    /// Maximum CTU size in luma samples.
+    pub max_ctu_size: u32,
+    /// No partition constraints override.
+    pub no_partition_constraints_override: bool,
+    /// No multi-type tree partitioning.
+    pub no_mtt: bool,
+    /// No dual tree for intra slices.
+    pub no_qtbtt_dual_tree_intra: bool,
+    /// No palette mode.
+    pub no_palette: bool,
+    /// No intra block copy.
+    pub no_ibc: bool,
+    /// No intra sub-partitions.
+    pub no_isp: bool,
+    /// No multiple reference lines intra prediction.
+    pub no_mrl: bool,
+    /// No matrix-based intra prediction.
+    pub no_mip: bool,
+    /// No cross-component linear model intra prediction.
+    pub no_cclm: bool,
+    /// No reference picture resampling.
+    pub no_ref_pic_resampling: bool,
+    /// No resolution change within coded layer video sequences.
+    pub no_res_change_in_clvs: bool,
+    /// No weighted prediction.
+    pub no_weighted_prediction: bool,
+    /// No reference picture wraparound.
+    pub no_ref_wraparound: bool,
+    /// No temporal motion vector prediction.
+    pub no_temporal_mvp: bool,
+    /// No subblock-based temporal motion vector prediction.
+    pub no_sbtmvp: bool,
+    /// No adaptive motion vector resolution.
+    pub no_amvr: bool,
+    /// No bi-directional optical flow.
+    pub no_bdof: bool,
+    /// No symmetric motion vector difference.
+    pub no_smvd: bool,
+    /// No decoder-side motion vector refinement.
+    pub no_dmvr: bool,
+    /// No merge with motion vector difference.
+    pub no_mmvd: bool,
+    /// No affine motion compensation.
+    pub no_affine_motion: bool,
+    /// No prediction refinement with optical flow.
+    pub no_prof: bool,
+    /// No bi-prediction with CU-level weights.
+    pub no_bcw: bool,
+    /// No combined inter and intra prediction.
+    pub no_ciip: bool,
+    /// No geometric partitioning mode.
+    pub no_gpm: bool,
+    /// No 64x64 luma transforms.
+    pub no_luma_transform_size_64: bool,
+    /// No transform skip.
+    pub no_transform_skip: bool,
+    /// No block-based delta pulse code modulation.
+    pub no_bdpcm: bool,
+    /// No multiple transform selection.
+    pub no_mts: bool,
+    /// No low-frequency non-separable transform.
+    pub no_lfnst: bool,
+    /// No joint coding of chroma residuals.
+    pub no_joint_cbcr: bool,
+    /// No subblock transform.
+    pub no_sbt: bool,
+    /// No adaptive colour transform.
+    pub no_act: bool,
+    /// No explicit scaling lists.
+    pub no_explicit_scaling_list: bool,
+    /// No dependent quantization.
+    pub no_dep_quant: bool,
+    /// No sign data hiding.
+    pub no_sign_data_hiding: bool,
+    /// No CU-level QP deltas.
+    pub no_cu_qp_delta: bool,
+    /// No chroma QP offsets.
+    pub no_chroma_qp_offset: bool,
+    /// No sample adaptive offset.
+    pub no_sao: bool,
+    /// No adaptive loop filter.
+    pub no_alf: bool,
+    /// No cross-component adaptive loop filter.
+    pub no_ccalf: bool,
+    /// No luma mapping with chroma scaling.
+    pub no_lmcs: bool,
+    /// No luma-adaptive deblocking filter.
+    pub no_ladf: bool,
+    /// No virtual boundaries.
+    pub no_virtual_boundaries: bool,
+    /// All pictures are IRAP or GDR pictures, if signalled.
+    pub all_rap_pictures: Option<bool>,
+    /// No extended precision processing, if signalled.
+    pub no_extended_precision_processing: Option<bool>,
+    /// No Rice parameter derivation for transform skip residual coding, if signalled.
+    pub no_ts_residual_coding_rice: Option<bool>,
+    /// No Rice parameter extension for regular residual coding, if signalled.
+    pub no_rrc_rice_extension: Option<bool>,
+    /// No persistent Rice adaptation, if signalled.
+    pub no_persistent_rice_adaptation: Option<bool>,
+    /// No reverse last significant coefficient position coding, if signalled.
+    pub no_reverse_last_sig_coeff: Option<bool>,
+}
+
+impl GeneralConstraintsInfo {
+    // Number of additional bits carrying the range extension constraint flags.
+    const NUM_ADDITIONAL_BITS_USED: u32 = 6;
+
+    fn parse(reader: &mut RbspReader) -> Result<Option<Self>, ParseError> {
+        let gci_present = reader.read_flag()?;
+        if !gci_present {
+            reader.byte_align()?;
+            return Ok(None);
+        }
+
+        let intra_only = reader.read_flag()?;
+        let all_layers_independent = reader.read_flag()?;
+        let one_au_only = reader.read_flag()?;
+        let max_bit_depth = 16 - reader.read_bits(4)?;
+        let max_chroma_format_idc =
+            3u32.checked_sub(reader.read_bits(2)?)
+                .ok_or(ParseError::InvalidValue(
+                    "gci_three_minus_max_chroma_format_constraint_idc",
+                ))?;
+
+        let mut gci = Self {
+            intra_only,
+            all_layers_independent,
+            one_au_only,
+            max_bit_depth,
+            max_chroma_format_idc,
+            no_mixed_nalu_types_in_pic: reader.read_flag()?,
+            no_trail: reader.read_flag()?,
+            no_stsa: reader.read_flag()?,
+            no_rasl: reader.read_flag()?,
+            no_radl: reader.read_flag()?,
+            no_idr: reader.read_flag()?,
+            no_cra: reader.read_flag()?,
+            no_gdr: reader.read_flag()?,
+            no_aps: reader.read_flag()?,
+            no_idr_rpl: reader.read_flag()?,
+            one_tile_per_pic: reader.read_flag()?,
+            pic_header_in_slice_header: reader.read_flag()?,
+            one_slice_per_pic: reader.read_flag()?,
+            no_rectangular_slice: reader.read_flag()?,
+            one_slice_per_subpic: reader.read_flag()?,
+            no_subpic_info: reader.read_flag()?,
+            max_ctu_size: 1 << (8 - reader.read_bits(2)?).min(7),
+            no_partition_constraints_override: reader.read_flag()?,
+            no_mtt: reader.read_flag()?,
+            no_qtbtt_dual_tree_intra: reader.read_flag()?,
+            no_palette: reader.read_flag()?,
+            no_ibc: reader.read_flag()?,
+            no_isp: reader.read_flag()?,
+            no_mrl: reader.read_flag()?,
+            no_mip: reader.read_flag()?,
+            no_cclm: reader.read_flag()?,
+            no_ref_pic_resampling: reader.read_flag()?,
+            no_res_change_in_clvs: reader.read_flag()?,
+            no_weighted_prediction: reader.read_flag()?,
+            no_ref_wraparound: reader.read_flag()?,
+            no_temporal_mvp: reader.read_flag()?,
+            no_sbtmvp: reader.read_flag()?,
+            no_amvr: reader.read_flag()?,
+            no_bdof: reader.read_flag()?,
+            no_smvd: reader.read_flag()?,
+            no_dmvr: reader.read_flag()?,
+            no_mmvd: reader.read_flag()?,
+            no_affine_motion: reader.read_flag()?,
+            no_prof: reader.read_flag()?,
+            no_bcw: reader.read_flag()?,
+            no_ciip: reader.read_flag()?,
+            no_gpm: reader.read_flag()?,
+            no_luma_transform_size_64: reader.read_flag()?,
+            no_transform_skip: reader.read_flag()?,
+            no_bdpcm: reader.read_flag()?,
+            no_mts: reader.read_flag()?,
+            no_lfnst: reader.read_flag()?,
+            no_joint_cbcr: reader.read_flag()?,
+            no_sbt: reader.read_flag()?,
+            no_act: reader.read_flag()?,
+            no_explicit_scaling_list: reader.read_flag()?,
+            no_dep_quant: reader.read_flag()?,
+            no_sign_data_hiding: reader.read_flag()?,
+            no_cu_qp_delta: reader.read_flag()?,
+            no_chroma_qp_offset: reader.read_flag()?,
+            no_sao: reader.read_flag()?,
+            no_alf: reader.read_flag()?,
+            no_ccalf: reader.read_flag()?,
+            no_lmcs: reader.read_flag()?,
+            no_ladf: reader.read_flag()?,
+            no_virtual_boundaries: reader.read_flag()?,
+            all_rap_pictures: None,
+            no_extended_precision_processing: None,
+            no_ts_residual_coding_rice: None,
+            no_rrc_rice_extension: None,
+            no_persistent_rice_adaptation: None,
+            no_reverse_last_sig_coeff: None,
+        };
+
+        let mut num_additional_bits = reader.read_bits(8)?;
+        if num_additional_bits >= Self::NUM_ADDITIONAL_BITS_USED {
+            gci.all_rap_pictures = Some(reader.read_flag()?);
+            gci.no_extended_precision_processing = Some(reader.read_flag()?);
+            gci.no_ts_residual_coding_rice = Some(reader.read_flag()?);
+            gci.no_rrc_rice_extension = Some(reader.read_flag()?);
+            gci.no_persistent_rice_adaptation = Some(reader.read_flag()?);
+            gci.no_reverse_last_sig_coeff = Some(reader.read_flag()?);
+            num_additional_bits -= Self::NUM_ADDITIONAL_BITS_USED;
+        }
+        reader.skip_bits(num_additional_bits as usize)?;
+        reader.byte_align()?;
+
+        Ok(Some(gci))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_ordering() {
+        assert!(Level::Level5_1 > Level::Level5);
+        assert!(Level::Level6 > Level::Level5_2);
+        assert!(Level::Level15_5 > Level::Level6_3);
+        assert!(Level::Unknown(90) > Level::Level5_2);
+        assert_eq!(Level::new(Level::Level4_1.idc()), Level::Level4_1);
+        assert_eq!(Level::new(7), Level::Unknown(7));
+    }
+
+    #[test]
+    fn test_profile_idc_roundtrip() {
+        for idc in 0..=127 {
+            assert_eq!(Profile::new(idc).idc(), idc);
+        }
+        assert_eq!(Profile::new(1), Profile::Main10);
+        assert_eq!(Profile::new(33), Profile::Main10_444);
+    }
+
+    #[test]
+    fn test_probe() {
+        let profile_tier_level =
+            ProfileTierLevel::probe(include_bytes!("../../tests/short.vvc")).unwrap();
+        assert_eq!(profile_tier_level.profile, Profile::Main10);
+        assert_eq!(profile_tier_level.tier, Tier::Main);
+        assert!(profile_tier_level.frame_only_constraint);
+        assert!(!profile_tier_level.multilayer_enabled);
+        assert_eq!(profile_tier_level.sublayer_levels.len(), 6);
+        assert!(profile_tier_level
+            .sublayer_levels
+            .iter()
+            .all(|&level| level == profile_tier_level.level));
+
+        assert_eq!(ProfileTierLevel::probe(&[0, 0, 1, 0, 0x81, 0xbb]), None);
+    }
+}
@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+
+/// Error parsing a NAL unit, a decoder configuration record or an RTP packet.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The data ended before the syntax structure was complete.
+    #[error("unexpected end of data")]
+    EndOfData,
+    /// The NAL unit header is invalid or of an unexpected type.
+    #[error("invalid NAL unit header")]
+    InvalidHeader,
+    /// A syntax element has a value out of range.
+    #[error("invalid value for {0}")]
+    InvalidValue(&'static str),
+}
+
+/// Bit reader over a raw byte sequence payload (RBSP).
+///
+/// Emulation prevention bytes are removed from the data it is created from, so it can be created directly from the
+/// payload of a NAL unit.
+#[derive(Debug, Clone)]
+pub struct RbspReader<'a> {
+    data: Cow<'a, [u8]>,
+    // Position in bits.
+    pos: usize,
+}
+
+impl<'a> RbspReader<'a> {
+    /// Create a reader over the payload of a NAL unit, following its header.
+    pub fn new(payload: &'a [u8]) -> Self {
+        Self {
+            data: remove_emulation_prevention(payload),
+            pos: 0,
+        }
+    }
+
+    // Create a reader over data from which emulation prevention bytes were already removed.
+    pub(crate) fn from_rbsp(data: &'a [u8]) -> Self {
+        Self {
+            data: Cow::Borrowed(data),
+            pos: 0,
+        }
+    }
+
+    /// Read a single bit, as a flag.
+    pub fn read_flag(&mut self) -> Result<bool, ParseError> {
+        let byte = self.data.get(self.pos / 8).ok_or(ParseError::EndOfData)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit == 1)
+    }
+
+    /// Read an unsigned integer of up to 32 bits, as `u(n)`.
+    pub fn read_bits(&mut self, num_bits: u32) -> Result<u32, ParseError> {
+        if num_bits > 32 {
+            return Err(ParseError::InvalidValue("num_bits"));
+        }
+        if self.pos + num_bits as usize > self.data.len() * 8 {
+            return Err(ParseError::EndOfData);
+        }
+
+        let mut value = 0u64;
+        for _ in 0..num_bits {
+            value = (value << 1) | self.read_flag()? as u64;
+        }
+        Ok(value as u32)
+    }
+
+    /// Read an unsigned exp-Golomb-coded integer, as `ue(v)`.
+    pub fn read_ue(&mut self) -> Result<u32, ParseError> {
+        let mut leading_zero_bits = 0;
+        while !self.read_flag()? {
+            leading_zero_bits += 1;
+            if leading_zero_bits > 31 {
+                return Err(ParseError::InvalidValue("exp-Golomb code"));
+            }
+        }
+        let suffix = self.read_bits(leading_zero_bits)?;
+        Ok(((1u64 << leading_zero_bits) - 1 + suffix as u64) as u32)
+    }
+
+    /// Read a signed exp-Golomb-coded integer, as `se(v)`.
+    pub fn read_se(&mut self) -> Result<i32, ParseError> {
+        let code = self.read_ue()? as i64;
+        let value = if code % 2 == 1 {
+            (code + 1) / 2
+        } else {
+            -(code / 2)
+        };
+        Ok(value as i32)
+    }
+
+    /// Skip a number of bits.
+    pub fn skip_bits(&mut self, num_bits: usize) -> Result<(), ParseError> {
+        if self.pos + num_bits > self.data.len() * 8 {
+            return Err(ParseError::EndOfData);
+        }
+        self.pos += num_bits;
+        Ok(())
+    }
+
+    /// Is the reader at a byte boundary?
+    pub fn is_byte_aligned(&self) -> bool {
+        self.pos % 8 == 0
+    }
+
+    /// Skip the bits up to the next byte boundary.
+    pub fn byte_align(&mut self) -> Result<(), ParseError> {
+        self.skip_bits((8 - self.pos % 8) % 8)
+    }
+
+    /// Is there more data before the RBSP trailing bits, as `more_rbsp_data()`?
+    pub fn more_rbsp_data(&self) -> bool {
+        let Some(last_byte) = self.data.iter().rposition(|&byte| byte != 0) else {
+            return false;
+        };
+        let stop_bit = last_byte * 8 + 7 - self.data[last_byte].trailing_zeros() as usize;
+        self.pos < stop_bit
+    }
+}
+
+fn remove_emulation_prevention(payload: &[u8]) -> Cow<'_, [u8]> {
+    let has_emulation_prevention = payload.windows(3).any(|window| window == [0, 0, 3]);
+    if !has_emulation_prevention {
+        return Cow::Borrowed(payload);
+    }
+
+    let mut rbsp = Vec::with_capacity(payload.len());
+    let mut num_zeros = 0;
+    for &byte in payload {
+        if num_zeros >= 2 && byte == 3 {
+            num_zeros = 0;
+            continue;
+        }
+        num_zeros = if byte == 0 { num_zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    Cow::Owned(rbsp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_bits() {
+        let mut reader = RbspReader::new(&[0b1010_0000, 0xff]);
+        assert_eq!(reader.read_flag(), Ok(true));
+        assert_eq!(reader.read_bits(3), Ok(0b010));
+        assert!(!reader.is_byte_aligned());
+        assert_eq!(reader.read_bits(8), Ok(0b0000_1111));
+        assert_eq!(reader.read_bits(5), Err(ParseError::EndOfData));
+        assert_eq!(reader.read_bits(4), Ok(0b1111));
+        assert!(reader.is_byte_aligned());
+        assert_eq!(
+            reader.read_bits(33),
+            Err(ParseError::InvalidValue("num_bits"))
+        );
+    }
+
+    #[test]
+    fn test_read_exp_golomb() {
+        // 1, 010, 011, 00100, 00101 and 0001000
+        let mut reader = RbspReader::new(&[0b1010_0110, 0b0100_0010, 0b1000_1000]);
+        assert_eq!(reader.read_ue(), Ok(0));
+        assert_eq!(reader.read_ue(), Ok(1));
+        assert_eq!(reader.read_ue(), Ok(2));
+        assert_eq!(reader.read_se(), Ok(2));
+        assert_eq!(reader.read_se(), Ok(-2));
+        assert_eq!(reader.read_ue(), Ok(7));
+    }
+
+    #[test]
+    fn test_emulation_prevention() {
+        let mut reader = RbspReader::new(&[0, 0, 3, 1, 0, 0, 3, 0, 3]);
+        assert_eq!(reader.read_bits(32), Ok(0x0000_0100));
+        assert_eq!(reader.read_bits(24), Ok(0x00_0003));
+        assert_eq!(reader.read_flag(), Err(ParseError::EndOfData));
+    }
+
+    #[test]
+    fn test_more_rbsp_data() {
+        let mut reader = RbspReader::new(&[0b1101_0000, 0]);
+        assert!(reader.more_rbsp_data());
+        reader.skip_bits(2).unwrap();
+        assert!(reader.more_rbsp_data());
+        reader.skip_bits(1).unwrap();
+        assert!(!reader.more_rbsp_data());
+    }
+}
@@ -0,0 +1,513 @@
+use std::mem;
+
+use super::{NalUnitHeader, ParseError};
+use crate::{AccessUnit, NalType};
+
+const AGGREGATION_PACKET: u8 = 28;
+const FRAGMENTATION_UNIT: u8 = 29;
+
+// Extended value of RTP timestamp 0 before the first wraparound, leaving room for timestamps before the first one.
+const TIMESTAMP_EXTENSION_START: u64 = 1 << 32;
+
+/// RTP packet, as specified by RFC 3550.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpPacket<'a> {
+    /// Marker bit, set on the last packet of an access unit.
+    pub marker: bool,
+    /// Payload type.
+    pub payload_type: u8,
+    /// Sequence number.
+    pub sequence_number: u16,
+    /// Timestamp, in the 90 kHz clock for video.
+    pub timestamp: u32,
+    /// Synchronization source identifier.
+    pub ssrc: u32,
+    /// Payload, without the CSRC list, header extension and padding.
+    pub payload: &'a [u8],
+}
+
+impl<'a> RtpPacket<'a> {
+    /// Parse an RTP packet.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ParseError> {
+        if data.len() < 12 {
+            return Err(ParseError::EndOfData);
+        }
+        if data[0] >> 6 != 2 {
+            return Err(ParseError::InvalidValue("version"));
+        }
+        let has_padding = data[0] & 0x20 != 0;
+        let has_extension = data[0] & 0x10 != 0;
+        let csrc_count = (data[0] & 0xf) as usize;
+
+        let mut start = 12 + 4 * csrc_count;
+        if has_extension {
+            let extension = data.get(start..start + 4).ok_or(ParseError::EndOfData)?;
+            start += 4 + 4 * u16::from_be_bytes([extension[2], extension[3]]) as usize;
+        }
+        let mut end = data.len();
+        if has_padding {
+            end = end
+                .checked_sub(data[end - 1] as usize)
+                .ok_or(ParseError::EndOfData)?;
+        }
+
+        Ok(Self {
+            marker: data[1] & 0x80 != 0,
+            payload_type: data[1] & 0x7f,
+            sequence_number: u16::from_be_bytes([data[2], data[3]]),
+            timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            payload: data.get(start..end).ok_or(ParseError::EndOfData)?,
+        })
+    }
+}
+
+/// Depacketizer of VVC RTP payloads into Annex-B access units, following RFC 9328.
+///
+/// Single NAL unit packets, aggregation packets and fragmentation units are supported. Packets must be pushed in
+/// transmission order, which must also be the decoding order, so DONL fields are not supported (sprop-max-don-diff
+/// must be 0).
+///
+/// Access units end with a packet with the marker bit set, or when a packet with another timestamp is received.
+/// Their cts is the RTP timestamp extended to 64 bits across wraparounds, starting from 2^32 plus the first
+/// timestamp so that pictures reordered before the first one keep earlier timestamps. Gaps in sequence numbers flag
+/// the access units that may have lost packets as damaged, which are still returned, and late or duplicate packets
+/// are dropped.
+///
+/// ```
+/// use vvdec::nal::RtpDepacketizer;
+/// # let packets: Vec<&[u8]> = vec![];
+///
+/// let mut depacketizer = RtpDepacketizer::new();
+/// for packet in packets {
+///     if let Some(access_unit) = depacketizer.push(packet).unwrap() {
+///         // Decode the access unit.
+///     }
+/// }
+/// if let Some(access_unit) = depacketizer.flush() {
+///     // Decode the last access unit.
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct RtpDepacketizer {
+    payload: Vec<u8>,
+    timestamp: Option<u32>,
+    // RTP timestamp of the current access unit, extended to 64 bits.
+    extended_timestamp: Option<u64>,
+    is_random_access_point: bool,
+    is_damaged: bool,
+    // NAL unit being reassembled from fragmentation units.
+    fragment: Option<Vec<u8>>,
+    last_sequence_number: Option<u16>,
+    // Whether the last packet had the marker bit set, completing its access unit.
+    last_marker: bool,
+}
+
+impl RtpDepacketizer {
+    /// Create a new depacketizer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Push an RTP packet.
+    ///
+    /// Returns an access unit once it is complete. Invalid packets are treated as lost.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<AccessUnit<Vec<u8>>>, ParseError> {
+        let packet = RtpPacket::parse(packet)?;
+        let payload = Payload::parse(packet.payload)?;
+
+        if let Some(last_sequence_number) = self.last_sequence_number {
+            if packet.sequence_number.wrapping_sub(last_sequence_number) as i16 <= 0 {
+                return Ok(None);
+            }
+        }
+        let is_lost = self
+            .last_sequence_number
+            .is_some_and(|last| packet.sequence_number != last.wrapping_add(1));
+        self.last_sequence_number = Some(packet.sequence_number);
+
+        // Lost packets may belong to the current access unit if it was not complete, and to the next one.
+        if is_lost && !self.last_marker {
+            self.is_damaged = true;
+            self.fragment = None;
+        }
+        let mut access_unit = None;
+        if self.timestamp != Some(packet.timestamp) {
+            access_unit = self.flush();
+            self.extended_timestamp = Some(self.extend_timestamp(packet.timestamp));
+            self.timestamp = Some(packet.timestamp);
+        }
+        self.is_damaged |= is_lost;
+
+        match payload {
+            Payload::NalUnits(nal_units) => {
+                if self.fragment.take().is_some() {
+                    self.is_damaged = true;
+                }
+                for nal_unit in nal_units {
+                    self.push_nal_unit(nal_unit);
+                }
+            }
+            Payload::Fragment {
+                header,
+                start,
+                end,
+                data,
+            } => {
+                if start && self.fragment.replace(header.to_vec()).is_some() {
+                    self.is_damaged = true;
+                }
+                match &mut self.fragment {
+                    Some(fragment) => fragment.extend_from_slice(data),
+                    // The first fragment was lost.
+                    None => self.is_damaged = true,
+                }
+                if end {
+                    if let Some(nal_unit) = self.fragment.take() {
+                        self.push_nal_unit(&nal_unit);
+                    }
+                }
+            }
+        }
+
+        self.last_marker = packet.marker;
+        if packet.marker && access_unit.is_none() {
+            access_unit = self.flush();
+        }
+        Ok(access_unit)
+    }
+
+    /// Take the current access unit, if any NAL unit was received since the last one.
+    ///
+    /// An incomplete fragmented NAL unit is dropped and the access unit is flagged as damaged.
+    pub fn flush(&mut self) -> Option<AccessUnit<Vec<u8>>> {
+        if self.fragment.take().is_some() {
+            self.is_damaged = true;
+        }
+        // A damaged access unit with no NAL unit left carries its damage over to the next one.
+        if self.payload.is_empty() {
+            return None;
+        }
+
+        let access_unit = AccessUnit {
+            payload: mem::take(&mut self.payload),
+            cts: self.extended_timestamp,
+            dts: None,
+            is_random_access_point: self.is_random_access_point,
+            is_damaged: self.is_damaged,
+        };
+        self.is_random_access_point = false;
+        self.is_damaged = false;
+        Some(access_unit)
+    }
+
+    // Extends an RTP timestamp to 64 bits, taking the nearest value to the last timestamp, which may be earlier when
+    // pictures are reordered.
+    fn extend_timestamp(&self, timestamp: u32) -> u64 {
+        match (self.timestamp, self.extended_timestamp) {
+            (Some(last), Some(extended)) => {
+                extended.saturating_add_signed(timestamp.wrapping_sub(last) as i32 as i64)
+            }
+            _ => TIMESTAMP_EXTENSION_START + u64::from(timestamp),
+        }
+    }
+
+    fn push_nal_unit(&mut self, nal_unit: &[u8]) {
+        if let Some(header) = NalUnitHeader::parse(nal_unit) {
+            self.is_random_access_point |=
+                header.nal_type.is_irap() || header.nal_type == NalType::CodedSliceGdr;
+        }
+        self.payload.extend_from_slice(&[0, 0, 0, 1]);
+        self.payload.extend_from_slice(nal_unit);
+    }
+}
+
+enum Payload<'a> {
+    NalUnits(Vec<&'a [u8]>),
+    Fragment {
+        // Header of the fragmented NAL unit.
+        header: [u8; 2],
+        start: bool,
+        end: bool,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> Payload<'a> {
+    fn parse(payload: &'a [u8]) -> Result<Self, ParseError> {
+        if payload.len() < NalUnitHeader::SIZE {
+            return Err(ParseError::EndOfData);
+        }
+        match payload[1] >> 3 {
+            AGGREGATION_PACKET => {
+                let mut nal_units = Vec::new();
+                let mut data = &payload[NalUnitHeader::SIZE..];
+                while !data.is_empty() {
+                    let [size_high, size_low, rest @ ..] = data else {
+                        return Err(ParseError::EndOfData);
+                    };
+                    let size = u16::from_be_bytes([*size_high, *size_low]) as usize;
+                    if rest.len() < size {
+                        return Err(ParseError::EndOfData);
+                    }
+                    let (nal_unit, rest) = rest.split_at(size);
+                    nal_units.push(nal_unit);
+                    data = rest;
+                }
+                Ok(Self::NalUnits(nal_units))
+            }
+            FRAGMENTATION_UNIT => {
+                let fu_header = *payload.get(2).ok_or(ParseError::EndOfData)?;
+                let start = fu_header & 0x80 != 0;
+                let end = fu_header & 0x40 != 0;
+                let fu_type = fu_header & 0x1f;
+                if (start && end) || fu_type == AGGREGATION_PACKET || fu_type == FRAGMENTATION_UNIT
+                {
+                    return Err(ParseError::InvalidValue("fu_header"));
+                }
+                Ok(Self::Fragment {
+                    header: [payload[0], fu_type << 3 | payload[1] & 0x7],
+                    start,
+                    end,
+                    data: &payload[3..],
+                })
+            }
+            _ => Ok(Self::NalUnits(vec![payload])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nal::{strip_start_code, AnnexBSplitter};
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+    const TIMESTAMPS: [u32; 3] = [3600, 10800, 7200];
+
+    fn rtp_packet(sequence_number: u16, timestamp: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x80, (marker as u8) << 7 | 96];
+        packet.extend_from_slice(&sequence_number.to_be_bytes());
+        packet.extend_from_slice(&timestamp.to_be_bytes());
+        packet.extend_from_slice(&0x1234_5678u32.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn nal_units() -> Vec<&'static [u8]> {
+        AnnexBSplitter::new(DATA).map(strip_start_code).collect()
+    }
+
+    // Packets of short.vvc: the parameter sets in an aggregation packet, the IDR slice in fragmentation units,
+    // and the other slices in single NAL unit packets.
+    fn rtp_packets() -> Vec<Vec<u8>> {
+        let nal_units = nal_units();
+        let mut payloads = Vec::new();
+
+        let mut aggregation_packet = vec![0, AGGREGATION_PACKET << 3 | 1];
+        for nal_unit in &nal_units[..3] {
+            aggregation_packet.extend_from_slice(&(nal_unit.len() as u16).to_be_bytes());
+            aggregation_packet.extend_from_slice(nal_unit);
+        }
+        payloads.push((TIMESTAMPS[0], aggregation_packet));
+
+        let idr = nal_units[3];
+        let fragments: Vec<_> = idr[NalUnitHeader::SIZE..].chunks(100).collect();
+        for (index, fragment) in fragments.iter().enumerate() {
+            let start = (index == 0) as u8;
+            let end = (index == fragments.len() - 1) as u8;
+            let mut payload = vec![idr[0], FRAGMENTATION_UNIT << 3 | idr[1] & 0x7];
+            payload.push(start << 7 | end << 6 | idr[1] >> 3);
+            payload.extend_from_slice(fragment);
+            payloads.push((TIMESTAMPS[0], payload));
+        }
+
+        payloads.push((TIMESTAMPS[1], nal_units[4].to_vec()));
+        payloads.push((TIMESTAMPS[2], nal_units[5].to_vec()));
+
+        let mut packets = Vec::new();
+        for (index, (timestamp, payload)) in payloads.iter().enumerate() {
+            let marker = payloads
+                .get(index + 1)
+                .map_or(true, |(next_timestamp, _)| next_timestamp != timestamp);
+            // Sequence numbers wrap around.
+            let sequence_number = 0xfffe_u16.wrapping_add(index as u16);
+            packets.push(rtp_packet(sequence_number, *timestamp, marker, payload));
+        }
+        packets
+    }
+
+    fn depacketize(packets: &[Vec<u8>]) -> Vec<AccessUnit<Vec<u8>>> {
+        let mut depacketizer = RtpDepacketizer::new();
+        let mut access_units = Vec::new();
+        for packet in packets {
+            access_units.extend(depacketizer.push(packet).unwrap());
+        }
+        access_units.extend(depacketizer.flush());
+        access_units
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        // Packet with a CSRC, a header extension of one word and 3 bytes of padding.
+        let mut data = vec![0xb1, 0xe0, 0, 5, 0, 0, 0, 9, 0, 0, 0, 1, 0, 0, 0, 2];
+        data.extend_from_slice(&[0xbe, 0xde, 0, 1, 0xff, 0xff, 0xff, 0xff]);
+        data.extend_from_slice(&[0xaa, 0xbb, 0, 0, 3]);
+
+        let packet = RtpPacket::parse(&data).unwrap();
+        assert_eq!(
+            packet,
+            RtpPacket {
+                marker: true,
+                payload_type: 96,
+                sequence_number: 5,
+                timestamp: 9,
+                ssrc: 1,
+                payload: &[0xaa, 0xbb],
+            }
+        );
+
+        assert_eq!(RtpPacket::parse(&data[..11]), Err(ParseError::EndOfData));
+        data[0] = 0x40;
+        assert_eq!(
+            RtpPacket::parse(&data),
+            Err(ParseError::InvalidValue("version"))
+        );
+    }
+
+    #[test]
+    fn test_depacketize() {
+        let packets = rtp_packets();
+        assert!(packets.len() > 4);
+
+        let access_units = depacketize(&packets);
+        assert_eq!(access_units.len(), 3);
+        for (access_unit, timestamp) in access_units.iter().zip(TIMESTAMPS) {
+            assert_eq!(
+                access_unit.cts,
+                Some(TIMESTAMP_EXTENSION_START + timestamp as u64)
+            );
+            assert!(!access_unit.is_damaged);
+        }
+        assert!(access_units[0].is_random_access_point);
+        assert!(!access_units[1].is_random_access_point);
+
+        let depacketized: Vec<_> = access_units
+            .iter()
+            .flat_map(|access_unit| AnnexBSplitter::new(&access_unit.payload))
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(depacketized, nal_units());
+    }
+
+    #[test]
+    fn test_duplicate_packets() {
+        let mut packets = rtp_packets();
+        packets.insert(2, packets[1].clone());
+        packets.insert(1, packets[0].clone());
+
+        let access_units = depacketize(&packets);
+        assert_eq!(access_units.len(), 3);
+        assert!(access_units
+            .iter()
+            .all(|access_unit| !access_unit.is_damaged));
+    }
+
+    #[test]
+    fn test_lost_fragment() {
+        let mut packets = rtp_packets();
+        packets.remove(2);
+
+        let access_units = depacketize(&packets);
+        assert_eq!(access_units.len(), 3);
+        // The IDR slice is dropped from the first access unit.
+        assert_eq!(AnnexBSplitter::new(&access_units[0].payload).count(), 3);
+        let damaged: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.is_damaged)
+            .collect();
+        assert_eq!(damaged, [true, false, false]);
+    }
+
+    #[test]
+    fn test_lost_access_unit() {
+        let mut packets = rtp_packets();
+        let len = packets.len();
+        packets.remove(len - 2);
+
+        let access_units = depacketize(&packets);
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.cts)
+            .collect();
+        assert_eq!(
+            timestamps,
+            [3600, 7200].map(|timestamp| Some(TIMESTAMP_EXTENSION_START + timestamp))
+        );
+        let damaged: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.is_damaged)
+            .collect();
+        assert_eq!(damaged, [false, true]);
+    }
+
+    #[test]
+    fn test_timestamp_wraparound() {
+        const OFFSET: u32 = u32::MAX - 5000;
+        let mut packets = rtp_packets();
+        for packet in &mut packets {
+            let timestamp = u32::from_be_bytes(packet[4..8].try_into().unwrap());
+            packet[4..8].copy_from_slice(&timestamp.wrapping_add(OFFSET).to_be_bytes());
+        }
+
+        let access_units = depacketize(&packets);
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.cts)
+            .collect();
+        let expected: Vec<_> = TIMESTAMPS
+            .iter()
+            .map(|&timestamp| {
+                Some(TIMESTAMP_EXTENSION_START + u64::from(timestamp) + u64::from(OFFSET))
+            })
+            .collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[test]
+    fn test_timestamp_before_first() {
+        // Reversed timestamps, so that the second and third access units are earlier than the first one.
+        const END: u32 = 20000;
+        let mut packets = rtp_packets();
+        for packet in &mut packets {
+            let timestamp = u32::from_be_bytes(packet[4..8].try_into().unwrap());
+            packet[4..8].copy_from_slice(&(END - timestamp).to_be_bytes());
+        }
+
+        let access_units = depacketize(&packets);
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.cts)
+            .collect();
+        let expected: Vec<_> = TIMESTAMPS
+            .iter()
+            .map(|&timestamp| Some(TIMESTAMP_EXTENSION_START + u64::from(END - timestamp)))
+            .collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        let mut depacketizer = RtpDepacketizer::new();
+        let packet = rtp_packet(0, 0, true, &[0, AGGREGATION_PACKET << 3 | 1, 0, 10, 0]);
+        assert_eq!(
+            depacketizer.push(&packet).err(),
+            Some(ParseError::EndOfData)
+        );
+        let packet = rtp_packet(0, 0, true, &[0, FRAGMENTATION_UNIT << 3 | 1, 0xc0 | 8]);
+        assert_eq!(
+            depacketizer.push(&packet).err(),
+            Some(ParseError::InvalidValue("fu_header"))
+        );
+    }
+}
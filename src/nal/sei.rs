@@ -0,0 +1,109 @@
+use super::{strip_start_code, NalUnitHeader, ParseError, RbspReader};
+use crate::NalType;
+
+/// SEI message of an SEI NAL unit, with its payload unparsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeiPayload {
+    /// SEI payload type.
+    pub payload_type: u32,
+    /// Payload bytes, without emulation prevention bytes.
+    pub payload: Vec<u8>,
+}
+
+impl SeiPayload {
+    /// Parse the SEI messages of a prefix or suffix SEI NAL unit, with or without its start code.
+    ///
+    /// ```
+    /// use vvdec::nal::SeiPayload;
+    ///
+    /// // Prefix SEI NAL unit with a content light level information SEI message.
+    /// let data = [0, 0, 1, 0, 0xb9, 144, 4, 0x03, 0xe8, 0x01, 0x90, 0x80];
+    /// let messages = SeiPayload::parse_nal_unit(&data).unwrap();
+    /// assert_eq!(messages, [SeiPayload { payload_type: 144, payload: vec![0x03, 0xe8, 0x01, 0x90] }]);
+    /// ```
+    pub fn parse_nal_unit(nal_unit: &[u8]) -> Result<Vec<Self>, ParseError> {
+        let nal_unit = strip_start_code(nal_unit);
+        match NalUnitHeader::parse(nal_unit) {
+            Some(header) if matches!(header.nal_type, NalType::PrefixSei | NalType::SuffixSei) => {}
+            _ => return Err(ParseError::InvalidHeader),
+        }
+
+        let mut reader = RbspReader::new(&nal_unit[NalUnitHeader::SIZE..]);
+        let mut messages = Vec::new();
+        loop {
+            let payload_type = read_sei_value(&mut reader)?;
+            let payload_size = read_sei_value(&mut reader)?;
+            let payload = (0..payload_size)
+                .map(|_| reader.read_bits(8).map(|byte| byte as u8))
+                .collect::<Result<_, _>>()?;
+            messages.push(Self {
+                payload_type,
+                payload,
+            });
+            if !reader.more_rbsp_data() {
+                return Ok(messages);
+            }
+        }
+    }
+}
+
+// Reads a payload type or size, coded as a sequence of 0xff bytes followed by a last byte.
+fn read_sei_value(reader: &mut RbspReader) -> Result<u32, ParseError> {
+    let mut value = 0u32;
+    loop {
+        let byte = reader.read_bits(8)?;
+        value = value
+            .checked_add(byte)
+            .ok_or(ParseError::InvalidValue("sei_message"))?;
+        if byte != 0xff {
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nal_unit() {
+        // Suffix SEI NAL unit with a decoded picture hash and a user data unregistered SEI message of 300 bytes,
+        // whose payload contains an emulation prevention byte.
+        let mut data = vec![0, 0, 1, 0, 0xc1, 132, 5, 1, 0, 0x12, 0x34, 0, 5, 0xff, 45];
+        let mut user_data = vec![0xaa; 300];
+        user_data[10..13].copy_from_slice(&[0, 0, 1]);
+        data.extend_from_slice(&user_data[..12]);
+        data.push(3);
+        data.extend_from_slice(&user_data[12..]);
+        data.push(0x80);
+
+        let messages = SeiPayload::parse_nal_unit(&data).unwrap();
+        assert_eq!(
+            messages,
+            [
+                SeiPayload {
+                    payload_type: 132,
+                    payload: vec![1, 0, 0x12, 0x34, 0],
+                },
+                SeiPayload {
+                    payload_type: 5,
+                    payload: user_data,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_nal_unit() {
+        // SPS NAL unit.
+        assert_eq!(
+            SeiPayload::parse_nal_unit(&[0, 0x79, 0]),
+            Err(ParseError::InvalidHeader)
+        );
+        // The payload size exceeds the NAL unit.
+        assert_eq!(
+            SeiPayload::parse_nal_unit(&[0, 0xb9, 4, 8, 0, 0x80]),
+            Err(ParseError::EndOfData)
+        );
+    }
+}
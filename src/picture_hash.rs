@@ -0,0 +1,320 @@
+//! Decoded picture hash computation, as specified for the decoded picture hash SEI message in H.266 Annex D.
+
+use crate::PictureHashMethod;
+
+/// Samples of a single picture component.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlaneSamples<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    pub bytes_per_sample: usize,
+    pub bit_depth: u32,
+}
+
+impl PlaneSamples<'_> {
+    fn row(&self, y: usize) -> impl Iterator<Item = u32> + '_ {
+        let start = y * self.stride;
+        let row = &self.data[start..start + self.width * self.bytes_per_sample];
+        row.chunks_exact(self.bytes_per_sample)
+            .map(|sample| match sample {
+                [lo] => *lo as u32,
+                [lo, hi] => u16::from_le_bytes([*lo, *hi]) as u32,
+                _ => unreachable!("unsupported number of bytes per sample"),
+            })
+    }
+}
+
+/// Compute the digest of a picture component with the given method.
+pub(crate) fn compute(method: PictureHashMethod, plane: &PlaneSamples) -> Vec<u8> {
+    match method {
+        PictureHashMethod::Md5 => md5_plane(plane).to_vec(),
+        PictureHashMethod::Crc => crc_plane(plane).to_be_bytes().to_vec(),
+        PictureHashMethod::Checksum => checksum_plane(plane).to_be_bytes().to_vec(),
+    }
+}
+
+fn md5_plane(plane: &PlaneSamples) -> [u8; 16] {
+    let mut md5 = Md5::new();
+    let mut row_bytes = Vec::with_capacity(plane.width * 2);
+    for y in 0..plane.height {
+        row_bytes.clear();
+        for sample in plane.row(y) {
+            row_bytes.push(sample as u8);
+            if plane.bit_depth > 8 {
+                row_bytes.push((sample >> 8) as u8);
+            }
+        }
+        md5.update(&row_bytes);
+    }
+    md5.finalize()
+}
+
+fn crc_plane(plane: &PlaneSamples) -> u16 {
+    fn push_bit(crc: u32, bit: u32) -> u32 {
+        let msb = (crc >> 15) & 1;
+        (((crc << 1) + bit) & 0xffff) ^ (msb * 0x1021)
+    }
+
+    let num_bits = if plane.bit_depth > 8 { 16 } else { 8 };
+    let mut crc = 0xffff;
+    for y in 0..plane.height {
+        for sample in plane.row(y) {
+            // The low byte is processed first, then the high byte, each from its most significant bit.
+            for bit_idx in 0..8 {
+                crc = push_bit(crc, (sample >> (7 - bit_idx)) & 1);
+            }
+            for bit_idx in 8..num_bits {
+                crc = push_bit(crc, (sample >> (23 - bit_idx)) & 1);
+            }
+        }
+    }
+    for _ in 0..16 {
+        crc = push_bit(crc, 0);
+    }
+    crc as u16
+}
+
+fn checksum_plane(plane: &PlaneSamples) -> u32 {
+    let mut checksum = 0u32;
+    for y in 0..plane.height {
+        for (x, sample) in plane.row(y).enumerate() {
+            let xor_mask = ((x & 0xff) ^ (y & 0xff) ^ (x >> 8) ^ (y >> 8)) as u32;
+            checksum = checksum.wrapping_add((sample & 0xff) ^ xor_mask);
+            if plane.bit_depth > 8 {
+                checksum = checksum.wrapping_add((sample >> 8) ^ xor_mask);
+            }
+        }
+    }
+    checksum
+}
+
+/// Minimal MD5 implementation (RFC 1321).
+struct Md5 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+impl Md5 {
+    fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; 64],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(data.len() as u64);
+
+        if self.buffer_len > 0 {
+            let n = data.len().min(64 - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + n].copy_from_slice(&data[..n]);
+            self.buffer_len += n;
+            data = &data[n..];
+            if self.buffer_len < 64 {
+                return;
+            }
+            let block = self.buffer;
+            self.process_block(&block);
+            self.buffer_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.process_block(block.try_into().unwrap());
+        }
+        let remainder = blocks.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffer_len = remainder.len();
+    }
+
+    fn finalize(mut self) -> [u8; 16] {
+        let bit_len = self.total_len.wrapping_mul(8);
+        let padding_len = if self.buffer_len < 56 {
+            56 - self.buffer_len
+        } else {
+            120 - self.buffer_len
+        };
+        let mut padding = [0u8; 64];
+        padding[0] = 0x80;
+        self.update(&padding[..padding_len]);
+        self.update(&bit_len.to_le_bytes());
+
+        let mut digest = [0; 16];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn process_block(&mut self, block: &[u8; 64]) {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(MD5_CONSTANTS[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_SHIFTS[i]));
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md5(data: &[u8]) -> [u8; 16] {
+        let mut md5 = Md5::new();
+        md5.update(data);
+        md5.finalize()
+    }
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex(&md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn test_md5_split_updates() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut md5 = Md5::new();
+        for chunk in data.chunks(37) {
+            md5.update(chunk);
+        }
+        assert_eq!(md5.finalize(), self::md5(&data));
+    }
+
+    #[test]
+    fn test_md5_plane_skips_padding() {
+        // 2x2 plane with a stride of 3 bytes, where the padding must not be hashed.
+        let plane = PlaneSamples {
+            data: &[b'a', b'b', 0xff, b'c', b'd', 0xff],
+            width: 2,
+            height: 2,
+            stride: 3,
+            bytes_per_sample: 1,
+            bit_depth: 8,
+        };
+        assert_eq!(compute(PictureHashMethod::Md5, &plane), md5(b"abcd"));
+    }
+
+    #[test]
+    fn test_md5_plane_high_bit_depth() {
+        let plane = PlaneSamples {
+            data: &[0x01, 0x02, 0x03, 0x00],
+            width: 2,
+            height: 1,
+            stride: 4,
+            bytes_per_sample: 2,
+            bit_depth: 10,
+        };
+        assert_eq!(
+            compute(PictureHashMethod::Md5, &plane),
+            md5(&[0x01, 0x02, 0x03, 0x00])
+        );
+    }
+
+    #[test]
+    fn test_checksum_plane() {
+        let plane = PlaneSamples {
+            data: &[1, 2, 3, 4],
+            width: 2,
+            height: 2,
+            stride: 2,
+            bytes_per_sample: 1,
+            bit_depth: 8,
+        };
+        // (1 ^ 0) + (2 ^ 1) + (3 ^ 1) + (4 ^ 1 ^ 1)
+        assert_eq!(
+            compute(PictureHashMethod::Checksum, &plane),
+            10u32.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn test_crc_plane_check_value() {
+        // The H.266 CRC is CRC-16/SPI-FUJITSU, whose check value over "123456789" is 0xe5cc.
+        let plane = PlaneSamples {
+            data: b"123456789",
+            width: 9,
+            height: 1,
+            stride: 9,
+            bytes_per_sample: 1,
+            bit_depth: 8,
+        };
+        assert_eq!(compute(PictureHashMethod::Crc, &plane), [0xe5, 0xcc]);
+    }
+
+    #[test]
+    fn test_crc_plane_of_zeros() {
+        // Feeding zero bits only shifts the initial value through the generator polynomial.
+        let plane = PlaneSamples {
+            data: &[0; 4],
+            width: 4,
+            height: 1,
+            stride: 4,
+            bytes_per_sample: 1,
+            bit_depth: 8,
+        };
+        let mut crc = 0xffffu32;
+        for _ in 0..(4 * 8 + 16) {
+            let msb = (crc >> 15) & 1;
+            crc = ((crc << 1) & 0xffff) ^ (msb * 0x1021);
+        }
+        assert_eq!(
+            compute(PictureHashMethod::Crc, &plane),
+            (crc as u16).to_be_bytes()
+        );
+    }
+}
@@ -0,0 +1,865 @@
+use std::slice;
+
+use vvdec_sys::*;
+
+use crate::{
+    nal::{ParseError, RbspReader, SeiPayload},
+    ColourPrimaries, MatrixCoefficients, TransferCharacteristics,
+};
+
+/// SEI message attached to a decoded frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeiMessage {
+    /// Mastering display colour volume.
+    MasteringDisplayColourVolume(MasteringDisplayColourVolume),
+    /// Content light level information.
+    ContentLightLevelInfo(ContentLightLevelInfo),
+    /// Alternative transfer characteristics.
+    AlternativeTransferCharacteristics(AlternativeTransferCharacteristics),
+    /// Film grain characteristics.
+    FilmGrainCharacteristics(FilmGrainCharacteristics),
+    /// Decoded picture hash.
+    DecodedPictureHash(DecodedPictureHash),
+    /// User data registered by Rec. ITU-T T.35.
+    UserDataRegistered(UserDataRegistered),
+    /// User data unregistered.
+    UserDataUnregistered(UserDataUnregistered),
+    /// Frame packing arrangement.
+    FramePacking(FramePacking),
+    /// Equirectangular projection.
+    EquirectangularProjection(EquirectangularProjection),
+    /// SEI message without a typed representation, or which failed to parse.
+    Other {
+        /// SEI payload type.
+        payload_type: u32,
+        /// Payload bytes, as carried in the SEI NAL unit without emulation prevention bytes.
+        payload: Vec<u8>,
+    },
+}
+
+const USER_DATA_REGISTERED_ITU_T_T35: u32 = 4;
+const USER_DATA_UNREGISTERED: u32 = 5;
+const FILM_GRAIN_CHARACTERISTICS: u32 = 19;
+const FRAME_PACKING: u32 = 45;
+const DECODED_PICTURE_HASH: u32 = 132;
+const MASTERING_DISPLAY_COLOUR_VOLUME: u32 = 137;
+const CONTENT_LIGHT_LEVEL_INFO: u32 = 144;
+const ALTERNATIVE_TRANSFER_CHARACTERISTICS: u32 = 147;
+const EQUIRECTANGULAR_PROJECTION: u32 = 150;
+
+/// Payload types of the typed messages, as looked up in the SEI messages that VVdeC attaches to a frame.
+pub(crate) const VVDEC_PAYLOAD_TYPES: &[vvdecSEIPayloadType] = &[
+    vvdecSEIPayloadType_VVDEC_MASTERING_DISPLAY_COLOUR_VOLUME,
+    vvdecSEIPayloadType_VVDEC_CONTENT_LIGHT_LEVEL_INFO,
+    vvdecSEIPayloadType_VVDEC_ALTERNATIVE_TRANSFER_CHARACTERISTICS,
+    vvdecSEIPayloadType_VVDEC_FILM_GRAIN_CHARACTERISTICS,
+    vvdecSEIPayloadType_VVDEC_DECODED_PICTURE_HASH,
+    vvdecSEIPayloadType_VVDEC_USER_DATA_REGISTERED_ITU_T_T35,
+    vvdecSEIPayloadType_VVDEC_USER_DATA_UNREGISTERED,
+    vvdecSEIPayloadType_VVDEC_FRAME_PACKING,
+    vvdecSEIPayloadType_VVDEC_EQUIRECTANGULAR_PROJECTION,
+];
+
+impl SeiMessage {
+    pub(crate) fn new(sei: SeiPayload) -> Self {
+        Self::parse(sei.payload_type, &sei.payload).unwrap_or(SeiMessage::Other {
+            payload_type: sei.payload_type,
+            payload: sei.payload,
+        })
+    }
+
+    fn parse(payload_type: u32, payload: &[u8]) -> Result<Self, ParseError> {
+        Ok(match payload_type {
+            MASTERING_DISPLAY_COLOUR_VOLUME => SeiMessage::MasteringDisplayColourVolume(
+                MasteringDisplayColourVolume::parse(payload)?,
+            ),
+            CONTENT_LIGHT_LEVEL_INFO => {
+                SeiMessage::ContentLightLevelInfo(ContentLightLevelInfo::parse(payload)?)
+            }
+            ALTERNATIVE_TRANSFER_CHARACTERISTICS => SeiMessage::AlternativeTransferCharacteristics(
+                AlternativeTransferCharacteristics::parse(payload)?,
+            ),
+            FILM_GRAIN_CHARACTERISTICS => {
+                SeiMessage::FilmGrainCharacteristics(FilmGrainCharacteristics::parse(payload)?)
+            }
+            DECODED_PICTURE_HASH => {
+                SeiMessage::DecodedPictureHash(DecodedPictureHash::parse(payload)?)
+            }
+            USER_DATA_REGISTERED_ITU_T_T35 => {
+                SeiMessage::UserDataRegistered(UserDataRegistered::parse(payload)?)
+            }
+            USER_DATA_UNREGISTERED => {
+                SeiMessage::UserDataUnregistered(UserDataUnregistered::parse(payload)?)
+            }
+            FRAME_PACKING => SeiMessage::FramePacking(FramePacking::parse(payload)?),
+            EQUIRECTANGULAR_PROJECTION => {
+                SeiMessage::EquirectangularProjection(EquirectangularProjection::parse(payload)?)
+            }
+            _ => return Err(ParseError::InvalidValue("sei_payload_type")),
+        })
+    }
+
+    /// Convert an SEI message as parsed by VVdeC, if it has a typed representation.
+    pub(crate) fn from_vvdec(sei: &vvdecSEI) -> Option<Self> {
+        /// Safety: the payload must point to a valid instance of T.
+        unsafe fn payload<T>(sei: &vvdecSEI) -> Option<&T> {
+            (sei.payload as *const T).as_ref()
+        }
+
+        #[allow(non_upper_case_globals)]
+        unsafe {
+            Some(match sei.payloadType {
+                vvdecSEIPayloadType_VVDEC_MASTERING_DISPLAY_COLOUR_VOLUME => {
+                    SeiMessage::MasteringDisplayColourVolume(
+                        MasteringDisplayColourVolume::from_vvdec(payload(sei)?),
+                    )
+                }
+                vvdecSEIPayloadType_VVDEC_CONTENT_LIGHT_LEVEL_INFO => {
+                    SeiMessage::ContentLightLevelInfo(ContentLightLevelInfo::from_vvdec(payload(
+                        sei,
+                    )?))
+                }
+                vvdecSEIPayloadType_VVDEC_ALTERNATIVE_TRANSFER_CHARACTERISTICS => {
+                    SeiMessage::AlternativeTransferCharacteristics(
+                        AlternativeTransferCharacteristics::from_vvdec(payload(sei)?),
+                    )
+                }
+                vvdecSEIPayloadType_VVDEC_FILM_GRAIN_CHARACTERISTICS => {
+                    SeiMessage::FilmGrainCharacteristics(FilmGrainCharacteristics::from_vvdec(
+                        payload(sei)?,
+                    ))
+                }
+                vvdecSEIPayloadType_VVDEC_DECODED_PICTURE_HASH => {
+                    SeiMessage::DecodedPictureHash(DecodedPictureHash::from_vvdec(payload(sei)?))
+                }
+                vvdecSEIPayloadType_VVDEC_USER_DATA_REGISTERED_ITU_T_T35 => {
+                    SeiMessage::UserDataRegistered(UserDataRegistered::from_vvdec(payload(sei)?))
+                }
+                vvdecSEIPayloadType_VVDEC_USER_DATA_UNREGISTERED => {
+                    SeiMessage::UserDataUnregistered(UserDataUnregistered::from_vvdec(payload(
+                        sei,
+                    )?))
+                }
+                vvdecSEIPayloadType_VVDEC_FRAME_PACKING => {
+                    SeiMessage::FramePacking(FramePacking::from_vvdec(payload(sei)?))
+                }
+                vvdecSEIPayloadType_VVDEC_EQUIRECTANGULAR_PROJECTION => {
+                    SeiMessage::EquirectangularProjection(EquirectangularProjection::from_vvdec(
+                        payload(sei)?,
+                    ))
+                }
+                _ => return None,
+            })
+        }
+    }
+}
+
+/// Safety: data must be valid for len bytes, unless it is null.
+unsafe fn raw_bytes(data: *const u8, len: usize) -> Vec<u8> {
+    if data.is_null() {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(data, len).to_vec()
+    }
+}
+
+fn read_u8(reader: &mut RbspReader) -> Result<u8, ParseError> {
+    reader.read_bits(8).map(|value| value as u8)
+}
+
+fn read_u16(reader: &mut RbspReader) -> Result<u16, ParseError> {
+    reader.read_bits(16).map(|value| value as u16)
+}
+
+/// Mastering display colour volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasteringDisplayColourVolume {
+    /// Chromaticity coordinates (x, y) of the display primaries, in increments of 0.00002.
+    pub display_primaries: [[u16; 2]; 3],
+    /// Chromaticity coordinates (x, y) of the white point, in increments of 0.00002.
+    pub white_point: [u16; 2],
+    /// Maximum display luminance, in units of 0.0001 candelas per square metre.
+    pub max_luminance: u32,
+    /// Minimum display luminance, in units of 0.0001 candelas per square metre.
+    pub min_luminance: u32,
+}
+
+impl MasteringDisplayColourVolume {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = RbspReader::from_rbsp(payload);
+        let mut display_primaries = [[0; 2]; 3];
+        for primary in &mut display_primaries {
+            *primary = [read_u16(&mut reader)?, read_u16(&mut reader)?];
+        }
+        Ok(Self {
+            display_primaries,
+            white_point: [read_u16(&mut reader)?, read_u16(&mut reader)?],
+            max_luminance: reader.read_bits(32)?,
+            min_luminance: reader.read_bits(32)?,
+        })
+    }
+}
+
+impl MasteringDisplayColourVolume {
+    fn from_vvdec(sei: &vvdecSEIMasteringDisplayColourVolume) -> Self {
+        Self {
+            display_primaries: sei.primaries,
+            white_point: sei.whitePoint,
+            max_luminance: sei.maxLuminance,
+            min_luminance: sei.minLuminance,
+        }
+    }
+}
+
+/// Content light level information.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentLightLevelInfo {
+    /// Maximum content light level, in candelas per square metre.
+    pub max_content_light_level: u16,
+    /// Maximum picture average light level, in candelas per square metre.
+    pub max_pic_average_light_level: u16,
+}
+
+impl ContentLightLevelInfo {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = RbspReader::from_rbsp(payload);
+        Ok(Self {
+            max_content_light_level: read_u16(&mut reader)?,
+            max_pic_average_light_level: read_u16(&mut reader)?,
+        })
+    }
+}
+
+impl ContentLightLevelInfo {
+    fn from_vvdec(sei: &vvdecSEIContentLightLevelInfo) -> Self {
+        Self {
+            max_content_light_level: sei.maxContentLightLevel,
+            max_pic_average_light_level: sei.maxPicAverageLightLevel,
+        }
+    }
+}
+
+/// Alternative transfer characteristics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlternativeTransferCharacteristics {
+    /// Preferred transfer characteristics.
+    pub preferred_transfer_characteristics: TransferCharacteristics,
+}
+
+impl AlternativeTransferCharacteristics {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let &[preferred_transfer_characteristics, ..] = payload else {
+            return Err(ParseError::EndOfData);
+        };
+        Ok(Self {
+            preferred_transfer_characteristics: TransferCharacteristics::new(
+                preferred_transfer_characteristics.into(),
+            ),
+        })
+    }
+}
+
+impl AlternativeTransferCharacteristics {
+    fn from_vvdec(sei: &vvdecSEIAlternativeTransferCharacteristics) -> Self {
+        Self {
+            preferred_transfer_characteristics: TransferCharacteristics::new(
+                sei.preferred_transfer_characteristics as i32,
+            ),
+        }
+    }
+}
+
+/// Film grain characteristics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmGrainCharacteristics {
+    /// Cancels the persistence of previous film grain characteristics.
+    pub cancel: bool,
+    /// Film grain simulation model.
+    pub model_id: u8,
+    /// Colour description of the film grain model, if it differs from the one of the stream.
+    pub colour_description: Option<FilmGrainColourDescription>,
+    /// Blending mode.
+    pub blending_mode_id: u8,
+    /// Scale factor for the model values, as a base-2 logarithm.
+    pub log2_scale_factor: u8,
+    /// Model for each colour component, if present.
+    pub component_models: [Option<FilmGrainComponentModel>; 3],
+    /// Does the film grain characteristics persist for subsequent pictures?
+    pub persistence: bool,
+}
+
+impl FilmGrainCharacteristics {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = RbspReader::from_rbsp(payload);
+        let cancel = reader.read_flag()?;
+        if cancel {
+            return Ok(Self {
+                cancel,
+                model_id: 0,
+                colour_description: None,
+                blending_mode_id: 0,
+                log2_scale_factor: 0,
+                component_models: [None, None, None],
+                persistence: false,
+            });
+        }
+
+        let model_id = reader.read_bits(2)? as u8;
+        let colour_description = if reader.read_flag()? {
+            Some(FilmGrainColourDescription {
+                bit_depth_luma: reader.read_bits(3)? as u8 + 8,
+                bit_depth_chroma: reader.read_bits(3)? as u8 + 8,
+                full_range: reader.read_flag()?,
+                colour_primaries: ColourPrimaries::new(reader.read_bits(8)? as i32),
+                transfer_characteristics: TransferCharacteristics::new(reader.read_bits(8)? as i32),
+                matrix_coefficients: MatrixCoefficients::new(reader.read_bits(8)? as i32),
+            })
+        } else {
+            None
+        };
+        let blending_mode_id = reader.read_bits(2)? as u8;
+        let log2_scale_factor = reader.read_bits(4)? as u8;
+        let mut present = [false; 3];
+        for present in &mut present {
+            *present = reader.read_flag()?;
+        }
+        let mut component_models = [None, None, None];
+        for (model, present) in component_models.iter_mut().zip(present) {
+            if present {
+                *model = Some(FilmGrainComponentModel::parse(&mut reader)?);
+            }
+        }
+
+        Ok(Self {
+            cancel,
+            model_id,
+            colour_description,
+            blending_mode_id,
+            log2_scale_factor,
+            component_models,
+            persistence: reader.read_flag()?,
+        })
+    }
+}
+
+impl FilmGrainCharacteristics {
+    fn from_vvdec(sei: &vvdecSEIFilmGrainCharacteristics) -> Self {
+        Self {
+            cancel: sei.filmGrainCharacteristicsCancelFlag,
+            model_id: sei.filmGrainModelId,
+            colour_description: sei.separateColourDescriptionPresentFlag.then(|| {
+                FilmGrainColourDescription {
+                    bit_depth_luma: sei.filmGrainBitDepthLuma,
+                    bit_depth_chroma: sei.filmGrainBitDepthChroma,
+                    full_range: sei.filmGrainFullRangeFlag,
+                    colour_primaries: ColourPrimaries::new(sei.filmGrainColourPrimaries as i32),
+                    transfer_characteristics: TransferCharacteristics::new(
+                        sei.filmGrainTransferCharacteristics as i32,
+                    ),
+                    matrix_coefficients: MatrixCoefficients::new(sei.filmGrainMatrixCoeffs as i32),
+                }
+            }),
+            blending_mode_id: sei.blendingModeId,
+            log2_scale_factor: sei.log2ScaleFactor,
+            component_models: sei
+                .compModel
+                .each_ref()
+                .map(FilmGrainComponentModel::from_vvdec),
+            persistence: sei.filmGrainCharacteristicsPersistenceFlag,
+        }
+    }
+}
+
+/// Colour description of a film grain model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmGrainColourDescription {
+    /// Luma bit depth.
+    pub bit_depth_luma: u8,
+    /// Chroma bit depth.
+    pub bit_depth_chroma: u8,
+    /// Is it full range?
+    pub full_range: bool,
+    /// Colour primaries.
+    pub colour_primaries: ColourPrimaries,
+    /// Transfer characteristics.
+    pub transfer_characteristics: TransferCharacteristics,
+    /// Matrix coefficients.
+    pub matrix_coefficients: MatrixCoefficients,
+}
+
+/// Film grain model of a colour component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmGrainComponentModel {
+    /// Number of model values per intensity interval.
+    pub num_model_values: u8,
+    /// Intensity intervals with their model values.
+    pub intensity_intervals: Vec<FilmGrainIntensityInterval>,
+}
+
+impl FilmGrainComponentModel {
+    fn parse(reader: &mut RbspReader) -> Result<Self, ParseError> {
+        let num_intensity_intervals = reader.read_bits(8)? + 1;
+        let num_model_values = reader.read_bits(3)? as u8 + 1;
+        if num_model_values > 6 {
+            return Err(ParseError::InvalidValue("fg_num_model_values_minus1"));
+        }
+        let intensity_intervals = (0..num_intensity_intervals)
+            .map(|_| {
+                Ok(FilmGrainIntensityInterval {
+                    lower_bound: read_u8(reader)?,
+                    upper_bound: read_u8(reader)?,
+                    model_values: (0..num_model_values)
+                        .map(|_| reader.read_se())
+                        .collect::<Result<_, _>>()?,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        Ok(Self {
+            num_model_values,
+            intensity_intervals,
+        })
+    }
+}
+
+impl FilmGrainComponentModel {
+    fn from_vvdec(model: &vvdecCompModel) -> Option<Self> {
+        let num_model_values = model.numModelValues.min(6);
+        let num_intensity_intervals =
+            (model.numIntensityIntervals as usize).min(model.intensityValues.len());
+        model.presentFlag.then(|| Self {
+            num_model_values,
+            intensity_intervals: model.intensityValues[..num_intensity_intervals]
+                .iter()
+                .map(|interval| FilmGrainIntensityInterval {
+                    lower_bound: interval.intensityIntervalLowerBound,
+                    upper_bound: interval.intensityIntervalUpperBound,
+                    model_values: interval.compModelValue[..num_model_values as usize].to_vec(),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Intensity interval of a film grain model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmGrainIntensityInterval {
+    /// Lower bound of the interval.
+    pub lower_bound: u8,
+    /// Upper bound of the interval.
+    pub upper_bound: u8,
+    /// Model values.
+    pub model_values: Vec<i32>,
+}
+
+/// Method used to compute a decoded picture hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictureHashMethod {
+    /// MD5.
+    Md5,
+    /// CRC.
+    Crc,
+    /// Checksum.
+    Checksum,
+}
+
+impl PictureHashMethod {
+    fn new(hash_type: u8) -> Option<Self> {
+        use PictureHashMethod::*;
+        match hash_type {
+            0 => Some(Md5),
+            1 => Some(Crc),
+            2 => Some(Checksum),
+            _ => None,
+        }
+    }
+
+    /// Get the length of a digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            PictureHashMethod::Md5 => 16,
+            PictureHashMethod::Crc => 2,
+            PictureHashMethod::Checksum => 4,
+        }
+    }
+}
+
+/// Decoded picture hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedPictureHash {
+    /// Hash method, if known.
+    pub method: Option<PictureHashMethod>,
+    /// Is the picture hashed as a single component?
+    pub single_component: bool,
+    /// Digest of each colour component.
+    pub digests: Vec<Vec<u8>>,
+}
+
+impl DecodedPictureHash {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let [hash_type, flags, digests @ ..] = payload else {
+            return Err(ParseError::EndOfData);
+        };
+        let method = PictureHashMethod::new(*hash_type);
+        let single_component = flags >> 7 == 1;
+        let digests = match method {
+            Some(method) => {
+                let num_components = if single_component { 1 } else { 3 };
+                let digests = digests
+                    .get(..num_components * method.digest_len())
+                    .ok_or(ParseError::EndOfData)?;
+                digests
+                    .chunks_exact(method.digest_len())
+                    .map(<[u8]>::to_vec)
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        Ok(Self {
+            method,
+            single_component,
+            digests,
+        })
+    }
+}
+
+impl DecodedPictureHash {
+    fn from_vvdec(sei: &vvdecSEIDecodedPictureHash) -> Self {
+        #[allow(non_upper_case_globals)]
+        let method = match sei.method {
+            vvdecHashType_VVDEC_HASHTYPE_MD5 => Some(PictureHashMethod::Md5),
+            vvdecHashType_VVDEC_HASHTYPE_CRC => Some(PictureHashMethod::Crc),
+            vvdecHashType_VVDEC_HASHTYPE_CHECKSUM => Some(PictureHashMethod::Checksum),
+            _ => None,
+        };
+        let digest_len = method.map_or(0, PictureHashMethod::digest_len);
+        let num_planes = (sei.numPlanes.max(0) as usize).min(sei.digest.len());
+        Self {
+            method,
+            single_component: sei.singleCompFlag,
+            digests: sei.digest[..num_planes]
+                .iter()
+                .map(|digest| digest[..digest_len].to_vec())
+                .collect(),
+        }
+    }
+}
+
+/// User data registered by Rec. ITU-T T.35.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserDataRegistered {
+    /// Country code, as specified in Rec. ITU-T T.35. A code followed by an extension byte is 255 plus the
+    /// extension byte.
+    pub country_code: u16,
+    /// User data, following the country code.
+    pub payload: Vec<u8>,
+}
+
+impl UserDataRegistered {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let (country_code, payload) = match payload {
+            [0xff, extension, payload @ ..] => (0xff + *extension as u16, payload),
+            [country_code, payload @ ..] => (*country_code as u16, payload),
+            [] => return Err(ParseError::EndOfData),
+        };
+        Ok(Self {
+            country_code,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+impl UserDataRegistered {
+    fn from_vvdec(sei: &vvdecSEIUserDataRegistered) -> Self {
+        Self {
+            country_code: sei.ituCountryCode,
+            payload: unsafe { raw_bytes(sei.userData, sei.userDataLength as usize) },
+        }
+    }
+}
+
+/// User data unregistered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserDataUnregistered {
+    /// UUID identifying the user data, as specified in ISO/IEC 11578.
+    pub uuid: [u8; 16],
+    /// User data, following the UUID.
+    pub payload: Vec<u8>,
+}
+
+impl UserDataUnregistered {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let (uuid, payload) = payload.split_first_chunk().ok_or(ParseError::EndOfData)?;
+        Ok(Self {
+            uuid: *uuid,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+impl UserDataUnregistered {
+    fn from_vvdec(sei: &vvdecSEIUserDataUnregistered) -> Self {
+        Self {
+            uuid: sei.uuid_iso_iec_11578,
+            payload: unsafe { raw_bytes(sei.userData, sei.userDataLength as usize) },
+        }
+    }
+}
+
+/// Frame packing arrangement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FramePacking {
+    /// Arrangement identifier.
+    pub arrangement_id: i32,
+    /// Cancels the persistence of previous frame packing arrangements.
+    pub cancel: bool,
+    /// Arrangement type (e.g. 3 for side-by-side, 4 for top-bottom).
+    pub arrangement_type: i32,
+    /// Is each frame sampled in a quincunx pattern?
+    pub quincunx_sampling: bool,
+    /// Content interpretation type.
+    pub content_interpretation_type: i32,
+    /// Is one of the constituent frames spatially flipped?
+    pub spatial_flipping: bool,
+    /// Is the constituent frame 0 the flipped one?
+    pub frame0_flipped: bool,
+    /// Are the constituent frames fields of a video?
+    pub field_views: bool,
+    /// Is the current frame the constituent frame 0?
+    pub current_frame_is_frame0: bool,
+    /// Is the constituent frame 0 self-contained?
+    pub frame0_self_contained: bool,
+    /// Is the constituent frame 1 self-contained?
+    pub frame1_self_contained: bool,
+    /// Grid position (x, y) of the constituent frame 0.
+    pub frame0_grid_position: (i32, i32),
+    /// Grid position (x, y) of the constituent frame 1.
+    pub frame1_grid_position: (i32, i32),
+    /// Does the arrangement persist for subsequent pictures?
+    pub persistence: bool,
+    /// Is the aspect ratio of the upsampled frames signalled?
+    pub upsampled_aspect_ratio: bool,
+}
+
+impl FramePacking {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = RbspReader::from_rbsp(payload);
+        let arrangement_id = reader.read_ue()? as i32;
+        let cancel = reader.read_flag()?;
+        let mut frame_packing = Self {
+            arrangement_id,
+            cancel,
+            arrangement_type: 0,
+            quincunx_sampling: false,
+            content_interpretation_type: 0,
+            spatial_flipping: false,
+            frame0_flipped: false,
+            field_views: false,
+            current_frame_is_frame0: false,
+            frame0_self_contained: false,
+            frame1_self_contained: false,
+            frame0_grid_position: (0, 0),
+            frame1_grid_position: (0, 0),
+            persistence: false,
+            upsampled_aspect_ratio: false,
+        };
+        if !cancel {
+            frame_packing.arrangement_type = reader.read_bits(7)? as i32;
+            frame_packing.quincunx_sampling = reader.read_flag()?;
+            frame_packing.content_interpretation_type = reader.read_bits(6)? as i32;
+            frame_packing.spatial_flipping = reader.read_flag()?;
+            frame_packing.frame0_flipped = reader.read_flag()?;
+            frame_packing.field_views = reader.read_flag()?;
+            frame_packing.current_frame_is_frame0 = reader.read_flag()?;
+            frame_packing.frame0_self_contained = reader.read_flag()?;
+            frame_packing.frame1_self_contained = reader.read_flag()?;
+            if !frame_packing.quincunx_sampling && frame_packing.arrangement_type != 5 {
+                frame_packing.frame0_grid_position =
+                    (reader.read_bits(4)? as i32, reader.read_bits(4)? as i32);
+                frame_packing.frame1_grid_position =
+                    (reader.read_bits(4)? as i32, reader.read_bits(4)? as i32);
+            }
+            let _reserved_byte = reader.read_bits(8)?;
+            frame_packing.persistence = reader.read_flag()?;
+        }
+        frame_packing.upsampled_aspect_ratio = reader.read_flag()?;
+        Ok(frame_packing)
+    }
+}
+
+impl FramePacking {
+    fn from_vvdec(sei: &vvdecSEIFramePacking) -> Self {
+        Self {
+            arrangement_id: sei.arrangementId,
+            cancel: sei.arrangementCancelFlag,
+            arrangement_type: sei.arrangementType,
+            quincunx_sampling: sei.quincunxSamplingFlag,
+            content_interpretation_type: sei.contentInterpretationType,
+            spatial_flipping: sei.spatialFlippingFlag,
+            frame0_flipped: sei.frame0FlippedFlag,
+            field_views: sei.fieldViewsFlag,
+            current_frame_is_frame0: sei.currentFrameIsFrame0Flag,
+            frame0_self_contained: sei.frame0SelfContainedFlag,
+            frame1_self_contained: sei.frame1SelfContainedFlag,
+            frame0_grid_position: (sei.frame0GridPositionX, sei.frame0GridPositionY),
+            frame1_grid_position: (sei.frame1GridPositionX, sei.frame1GridPositionY),
+            persistence: sei.arrangementPersistenceFlag,
+            upsampled_aspect_ratio: sei.upsampledAspectRatio,
+        }
+    }
+}
+
+/// Equirectangular projection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EquirectangularProjection {
+    /// Cancels the persistence of previous equirectangular projections.
+    pub cancel: bool,
+    /// Does the projection persist for subsequent pictures?
+    pub persistence: bool,
+    /// Guard band, if present.
+    pub guard_band: Option<ErpGuardBand>,
+}
+
+impl EquirectangularProjection {
+    fn parse(payload: &[u8]) -> Result<Self, ParseError> {
+        let mut reader = RbspReader::from_rbsp(payload);
+        let cancel = reader.read_flag()?;
+        if cancel {
+            return Ok(Self {
+                cancel,
+                persistence: false,
+                guard_band: None,
+            });
+        }
+
+        let persistence = reader.read_flag()?;
+        let has_guard_band = reader.read_flag()?;
+        let _reserved_zero_2bits = reader.read_bits(2)?;
+        let guard_band = if has_guard_band {
+            Some(ErpGuardBand {
+                guard_band_type: reader.read_bits(3)? as u8,
+                left_width: read_u8(&mut reader)?,
+                right_width: read_u8(&mut reader)?,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            cancel,
+            persistence,
+            guard_band,
+        })
+    }
+}
+
+impl EquirectangularProjection {
+    fn from_vvdec(sei: &vvdecSEIEquirectangularProjection) -> Self {
+        Self {
+            cancel: sei.erpCancelFlag,
+            persistence: sei.erpPersistenceFlag,
+            guard_band: sei.erpGuardBandFlag.then_some(ErpGuardBand {
+                guard_band_type: sei.erpGuardBandType,
+                left_width: sei.erpLeftGuardBandWidth,
+                right_width: sei.erpRightGuardBandWidth,
+            }),
+        }
+    }
+}
+
+/// Guard band of an equirectangular projection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErpGuardBand {
+    /// Guard band type.
+    pub guard_band_type: u8,
+    /// Width of the left guard band, in units of luma samples.
+    pub left_width: u8,
+    /// Width of the right guard band, in units of luma samples.
+    pub right_width: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(payload_type: u32, payload: &[u8]) -> SeiMessage {
+        SeiMessage::new(SeiPayload {
+            payload_type,
+            payload: payload.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_decoded_picture_hash() {
+        let hash = DecodedPictureHash::parse(&[1, 0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc]).unwrap();
+        assert_eq!(hash.method, Some(PictureHashMethod::Crc));
+        assert!(!hash.single_component);
+        assert_eq!(hash.digests, [[0x12, 0x34], [0x56, 0x78], [0x9a, 0xbc]]);
+
+        let hash = DecodedPictureHash::parse(&[2, 0x80, 1, 2, 3, 4]).unwrap();
+        assert_eq!(hash.method, Some(PictureHashMethod::Checksum));
+        assert_eq!(hash.digests, [[1, 2, 3, 4]]);
+
+        assert_eq!(
+            DecodedPictureHash::parse(&[0, 0, 1, 2, 3]),
+            Err(ParseError::EndOfData)
+        );
+    }
+
+    #[test]
+    fn test_user_data_unregistered() {
+        let mut payload = vec![7; 16];
+        payload.extend_from_slice(b"payload");
+
+        assert_eq!(
+            message(USER_DATA_UNREGISTERED, &payload),
+            SeiMessage::UserDataUnregistered(UserDataUnregistered {
+                uuid: [7; 16],
+                payload: b"payload".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_film_grain_characteristics() {
+        // Model 0 with a luma model of one interval of two values, 3 and -1, and persistence.
+        let payload = [0x00, 0xe0, 0x01, 0x20, 0xff, 0x33, 0x80];
+        let film_grain = FilmGrainCharacteristics::parse(&payload).unwrap();
+        assert!(!film_grain.cancel);
+        assert_eq!(film_grain.log2_scale_factor, 3);
+        assert_eq!(
+            film_grain.component_models,
+            [
+                Some(FilmGrainComponentModel {
+                    num_model_values: 2,
+                    intensity_intervals: vec![FilmGrainIntensityInterval {
+                        lower_bound: 32,
+                        upper_bound: 255,
+                        model_values: vec![3, -1],
+                    }],
+                }),
+                None,
+                None,
+            ]
+        );
+        assert!(film_grain.persistence);
+    }
+
+    #[test]
+    fn test_other() {
+        // Unknown payload type.
+        assert_eq!(
+            message(200, &[1, 2, 3]),
+            SeiMessage::Other {
+                payload_type: 200,
+                payload: vec![1, 2, 3],
+            }
+        );
+        // Truncated content light level information.
+        assert_eq!(
+            message(CONTENT_LIGHT_LEVEL_INFO, &[1, 2, 3]),
+            SeiMessage::Other {
+                payload_type: CONTENT_LIGHT_LEVEL_INFO,
+                payload: vec![1, 2, 3],
+            }
+        );
+    }
+}
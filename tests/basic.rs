@@ -12,7 +12,7 @@ const DATA: &[u8] = include_bytes!("../tests/short.vvc");
 fn basic() -> Result<(), Error> {
     let mut decoder = Decoder::builder().build()?;
 
-    assert_matches!(decoder.decode(DATA), Err(Error::TryAgain));
+    assert_matches!(decoder.decode(DATA), Err(Error::TryAgain(_)));
 
     let frame1 = decoder.flush()?.unwrap();
     let _plane = frame1.plane(PlaneComponent::Y).unwrap();
@@ -23,27 +23,13 @@ fn basic() -> Result<(), Error> {
     let _frame3 = decoder.flush()?.unwrap();
 
     assert_matches!(decoder.flush(), Ok(None));
-    assert_matches!(decoder.flush(), Err(Error::RestartRequired));
+    assert_matches!(decoder.flush(), Err(Error::RestartRequired(_)));
 
     Ok(())
 }
 
 fn split_data(data: &[u8]) -> Vec<&[u8]> {
-    const ANNEX_B_START_CODE: &[u8] = &[0, 0, 0, 1];
-    let mut indices: Vec<_> = DATA
-        .windows(4)
-        .enumerate()
-        .filter(|(_, window)| *window == ANNEX_B_START_CODE)
-        .map(|(i, _)| i)
-        .collect();
-    indices.push(DATA.len());
-
-    let mut chunks: Vec<&[u8]> = indices
-        .windows(2)
-        .map(|pair| &data[pair[0]..pair[1]])
-        .collect();
-    chunks.push(&data[indices[indices.len() - 1]..]);
-    chunks
+    nal::AnnexBSplitter::new(data).collect()
 }
 
 #[test]
@@ -63,7 +49,7 @@ fn test_split_data() -> Result<(), Error> {
     let _frame3 = decoder.flush()?.unwrap();
 
     assert_matches!(decoder.flush(), Ok(None));
-    assert_matches!(decoder.flush(), Err(Error::RestartRequired));
+    assert_matches!(decoder.flush(), Err(Error::RestartRequired(_)));
 
     Ok(())
 }
@@ -118,3 +104,507 @@ fn test_change_resolution() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_logger() -> Result<(), Error> {
+    use std::sync::{Arc, Mutex};
+
+    let decode_with_logger = |log_level| -> Result<Vec<(LogLevel, String)>, Error> {
+        let mut decoder = Decoder::builder().log_level(log_level).build()?;
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let logged = messages.clone();
+        decoder.set_logger(move |level, message: &str| {
+            logged.lock().unwrap().push((level, message.to_owned()));
+        })?;
+
+        let _ = decoder.decode(DATA);
+        while decoder.flush()?.is_some() {}
+
+        let messages = messages.lock().unwrap().clone();
+        Ok(messages)
+    };
+
+    let messages = decode_with_logger(LogLevel::Details)?;
+    assert!(!messages.is_empty());
+    for (level, message) in &messages {
+        assert_ne!(*level, LogLevel::Silent);
+        assert!(!message.ends_with('\n'));
+    }
+
+    assert!(decode_with_logger(LogLevel::Silent)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_picture_hash() -> Result<(), Error> {
+    let mut decoder = Decoder::builder().verify_picture_hash(true).build()?;
+
+    // short.vvc has no decoded picture hash SEI messages.
+    let _ = decoder.decode(DATA);
+    while let Some(frame) = decoder.flush()? {
+        assert_eq!(frame.picture_hash_status(), PictureHashStatus::Absent);
+    }
+    assert_eq!(decoder.picture_hash_error_count(), 0);
+
+    let mut decoder = Decoder::new()?;
+    let _ = decoder.decode(DATA);
+    let frame = decoder.flush()?.unwrap();
+    assert_eq!(frame.picture_hash_status(), PictureHashStatus::NotVerified);
+
+    Ok(())
+}
+
+const PREFIX_SEI: u8 = 23;
+const SUFFIX_SEI: u8 = 24;
+
+// Builds an SEI NAL unit with the layer and temporal IDs of a slice.
+fn sei_nal_unit(nal_type: u8, slice: &[u8], messages: &[(u8, &[u8])]) -> Vec<u8> {
+    let slice = nal::strip_start_code(slice);
+    let mut nal_unit = vec![0, 0, 0, 1, slice[0], nal_type << 3 | slice[1] & 0x7];
+    for (payload_type, payload) in messages {
+        nal_unit.push(*payload_type);
+        nal_unit.push(payload.len() as u8);
+        nal_unit.extend_from_slice(payload);
+    }
+    nal_unit.push(0x80);
+    nal_unit
+}
+
+#[test]
+fn test_sei_messages() -> Result<(), Error> {
+    let user_data_unregistered = |uuid: u8, data: &[u8]| [&[uuid; 16], data].concat();
+    let nal_units = split_data(DATA);
+    let data = [
+        &nal_units[..3].concat(),
+        &sei_nal_unit(
+            PREFIX_SEI,
+            nal_units[3],
+            &[
+                (5, &user_data_unregistered(1, b"first")),
+                (5, &user_data_unregistered(1, b"second")),
+                (144, &[0x03, 0xe8, 0x01, 0x90]),
+            ],
+        ),
+        nal_units[3],
+        &sei_nal_unit(
+            SUFFIX_SEI,
+            nal_units[3],
+            &[(5, &user_data_unregistered(2, b"suffix"))],
+        ),
+        &sei_nal_unit(PREFIX_SEI, nal_units[4], &[(200, &[1, 2, 3])]),
+        nal_units[4],
+        nal_units[5],
+    ]
+    .concat();
+
+    let mut decoder = Decoder::new()?;
+    let _ = decoder.decode(&data);
+    let mut messages = Vec::new();
+    while let Some(frame) = decoder.flush()? {
+        messages.push(frame.sei_messages().collect::<Vec<_>>());
+    }
+
+    // Frames are output in the order of their picture order counts, 0, 1 and 2.
+    assert_eq!(
+        messages,
+        [
+            vec![
+                SeiMessage::UserDataUnregistered(UserDataUnregistered {
+                    uuid: [1; 16],
+                    payload: b"first".to_vec(),
+                }),
+                SeiMessage::UserDataUnregistered(UserDataUnregistered {
+                    uuid: [1; 16],
+                    payload: b"second".to_vec(),
+                }),
+                SeiMessage::ContentLightLevelInfo(ContentLightLevelInfo {
+                    max_content_light_level: 1000,
+                    max_pic_average_light_level: 400,
+                }),
+                SeiMessage::UserDataUnregistered(UserDataUnregistered {
+                    uuid: [2; 16],
+                    payload: b"suffix".to_vec(),
+                }),
+            ],
+            vec![],
+            vec![SeiMessage::Other {
+                payload_type: 200,
+                payload: vec![1, 2, 3],
+            }],
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_frame_allocator() -> Result<(), Error> {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    struct AlignedBuffer {
+        data: Vec<u8>,
+        offset: usize,
+    }
+
+    impl AsMut<[u8]> for AlignedBuffer {
+        fn as_mut(&mut self) -> &mut [u8] {
+            &mut self.data[self.offset..]
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingAllocator {
+        allocated: Arc<AtomicUsize>,
+        released: Arc<AtomicUsize>,
+    }
+
+    impl FrameAllocator for CountingAllocator {
+        type Buffer = AlignedBuffer;
+
+        fn allocate(
+            &self,
+            _component: PlaneComponent,
+            size: usize,
+            alignment: usize,
+        ) -> Option<AlignedBuffer> {
+            self.allocated.fetch_add(1, Ordering::SeqCst);
+            let data = vec![0; size + alignment];
+            let offset = data.as_ptr().align_offset(alignment.max(1));
+            Some(AlignedBuffer { data, offset })
+        }
+
+        fn release(&self, _buffer: AlignedBuffer) {
+            self.released.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let allocator = CountingAllocator::default();
+    let allocated = allocator.allocated.clone();
+    let released = allocator.released.clone();
+
+    let mut decoder = Decoder::builder().frame_allocator(allocator).build()?;
+    let _ = decoder.decode(DATA);
+    while let Some(frame) = decoder.flush()? {
+        let plane = frame.plane(PlaneComponent::Y).unwrap();
+        assert!(plane.buffer::<AlignedBuffer>().is_some());
+        assert!(plane.buffer::<Vec<u8>>().is_none());
+    }
+    drop(decoder);
+
+    assert!(allocated.load(Ordering::SeqCst) > 0);
+    assert_eq!(
+        allocated.load(Ordering::SeqCst),
+        released.load(Ordering::SeqCst)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_remove_padding() -> Result<(), Error> {
+    let mut decoder = Decoder::builder().remove_padding(true).build()?;
+
+    let _ = decoder.decode(DATA);
+    let frame = decoder.flush()?.unwrap();
+    for component in [PlaneComponent::Y, PlaneComponent::U, PlaneComponent::V] {
+        let plane = frame.plane(component).unwrap();
+        let row_len = (plane.width() * plane.bytes_per_sample()) as usize;
+        assert_eq!(plane.stride() as usize, row_len);
+        assert_eq!(plane.rows().count(), plane.height() as usize);
+        assert!(plane.rows().all(|row| row.len() == row_len));
+        assert_eq!(plane.to_contiguous(), plane.as_ref());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_typed_samples() -> Result<(), Error> {
+    let mut decoder = Decoder::new()?;
+
+    let _ = decoder.decode(DATA);
+    let frame = decoder.flush()?.unwrap();
+    let plane = frame.plane(PlaneComponent::Y).unwrap();
+    match plane.bytes_per_sample() {
+        1 => {
+            assert_eq!(plane.samples::<u8>().unwrap(), plane.as_ref());
+            assert!(plane.as_u16().is_none());
+        }
+        2 => {
+            let samples = plane.as_u16().unwrap();
+            assert_eq!(samples.len() * 2, plane.len());
+            assert!(samples.iter().all(|&s| s < 1 << frame.bit_depth()));
+            assert!(plane.samples::<u8>().is_none());
+        }
+        _ => unreachable!(),
+    }
+
+    let rows: Vec<&[u16]> = match plane.sample_rows::<u16>() {
+        Some(rows) => rows.collect(),
+        None => return Ok(()),
+    };
+    assert_eq!(rows.len(), plane.height() as usize);
+    assert!(rows.iter().all(|row| row.len() == plane.width() as usize));
+
+    Ok(())
+}
+
+#[test]
+fn test_upscale_output() -> Result<(), Error> {
+    // short2.vvc with reference picture resampling enabled and a maximum size of 320x240 in its SPS, while its
+    // pictures are coded at 160x120.
+    const RPR_DATA: &[u8] = include_bytes!("../tests/rpr.vvc");
+
+    for (upscale_output, size) in [
+        (UpscaleOutput::Off, (160, 120)),
+        (UpscaleOutput::CopyWithoutRescale, (320, 240)),
+        (UpscaleOutput::Rescale, (320, 240)),
+    ] {
+        let mut decoder = Decoder::builder().upscale_output(upscale_output).build()?;
+
+        let _ = decoder.decode(RPR_DATA);
+        let mut num_frames = 0;
+        while let Some(frame) = decoder.flush()? {
+            assert_eq!((frame.width(), frame.height()), size);
+            assert_eq!(frame.coded_width(), Some(160));
+            assert_eq!(frame.coded_height(), Some(120));
+            num_frames += 1;
+        }
+        assert_eq!(num_frames, 3);
+    }
+
+    let mut decoder = Decoder::new()?;
+    let _ = decoder.decode(DATA);
+    let frame = decoder.flush()?.unwrap();
+    assert_eq!(frame.coded_width(), Some(frame.width()));
+    assert_eq!(frame.coded_height(), Some(frame.height()));
+
+    Ok(())
+}
+
+#[test]
+fn test_film_grain_synthesis() -> Result<(), Error> {
+    let mut decoder = Decoder::builder().film_grain_synthesis(false).build()?;
+
+    let _ = decoder.decode(DATA);
+    while let Some(frame) = decoder.flush()? {
+        assert_eq!(frame.film_grain_characteristics(), None);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_error_handling() -> Result<(), Error> {
+    let mut decoder = Decoder::builder()
+        .error_handling(ErrorHandling::TryContinue)
+        .build()?;
+
+    // Truncate the slice of the last picture in decoding order, then restart from the IDR picture.
+    let nal_units = split_data(DATA);
+    let mut data = Vec::new();
+    for nal_unit in &nal_units[..5] {
+        data.extend_from_slice(&[0, 0, 1]);
+        data.extend_from_slice(nal::strip_start_code(nal_unit));
+    }
+    data.extend_from_slice(&[0, 0, 1]);
+    data.extend_from_slice(&nal::strip_start_code(nal_units[5])[..4]);
+    data.extend_from_slice(DATA);
+
+    let mut frames = Vec::new();
+    let mut has_error = false;
+    for nal_unit in split_data(&data) {
+        match decoder.decode(nal_unit) {
+            Ok(frame) => frames.extend(frame),
+            Err(Error::TryAgain(_)) => {}
+            Err(Error::DecInput(_)) => has_error = true,
+            Err(error) => return Err(error),
+        }
+    }
+    while let Some(frame) = decoder.flush()? {
+        frames.push(frame);
+    }
+    assert!(has_error);
+
+    // Decoding went on, flagging frames output after the error until the IDR picture.
+    assert!(frames.len() > 3);
+    let (first_frames, last_frames) = frames.split_at(frames.len() - 3);
+    assert!(first_frames.iter().any(Frame::is_corrupted));
+    let pocs: Vec<_> = last_frames
+        .iter()
+        .map(|frame| frame.picture_attributes().unwrap().poc)
+        .collect();
+    assert_eq!(pocs, [0, 1, 2]);
+    assert!(last_frames.iter().all(|frame| !frame.is_corrupted()));
+
+    Ok(())
+}
+
+#[test]
+fn test_version() -> Result<(), Error> {
+    assert!(!vvdec::version().is_empty());
+
+    let decoder = Decoder::new()?;
+    assert!(!decoder.info().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_access_unit_assembler() -> Result<(), Error> {
+    let mut decoder = Decoder::new()?;
+    let mut assembler = nal::AccessUnitAssembler::new();
+
+    let mut access_units: Vec<_> = nal::AnnexBSplitter::new(DATA)
+        .filter_map(|nal_unit| assembler.push(nal_unit))
+        .collect();
+    access_units.extend(assembler.flush());
+    assert_eq!(access_units.len(), 3);
+    assert!(access_units[0].is_random_access_point);
+
+    let mut num_frames = 0;
+    for access_unit in access_units {
+        if let Ok(Some(_)) = decoder.decode(access_unit) {
+            num_frames += 1;
+        }
+    }
+    while decoder.flush()?.is_some() {
+        num_frames += 1;
+    }
+    assert_eq!(num_frames, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_profile_tier_level() -> Result<(), Error> {
+    let mut decoder = Decoder::new()?;
+
+    let _ = decoder.decode(DATA);
+    let mut num_frames = 0;
+    while let Some(frame) = decoder.flush()? {
+        let profile_tier_level = frame.profile_tier_level().unwrap();
+        assert_eq!(
+            profile_tier_level,
+            nal::ProfileTierLevel::probe(DATA).unwrap()
+        );
+        assert_eq!(profile_tier_level.profile, nal::Profile::Main10);
+        assert_eq!(profile_tier_level.tier, nal::Tier::Main);
+        assert_eq!(
+            frame.picture_attributes().unwrap().profile_tier_level,
+            Some(profile_tier_level)
+        );
+        num_frames += 1;
+    }
+    assert_eq!(num_frames, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_hrd_sublayers() -> Result<(), Error> {
+    let sps = nal::Sps::parse(split_data(DATA)[0]).unwrap();
+    let mut decoder = Decoder::new()?;
+
+    let _ = decoder.decode(DATA);
+    let mut num_frames = 0;
+    while let Some(frame) = decoder.flush()? {
+        let hrd = frame.picture_attributes().unwrap().hrd.unwrap();
+        assert_eq!(hrd.sublayers, sps.hrd.as_ref().unwrap().sublayers);
+        let elemental_duration_in_tc = hrd.sublayers.last().unwrap().elemental_duration_in_tc;
+        assert_eq!(
+            hrd.frame_rate(elemental_duration_in_tc.unwrap()),
+            Some((25, 1))
+        );
+        num_frames += 1;
+    }
+    assert_eq!(num_frames, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_length_prefixed_converter() -> Result<(), Error> {
+    let nal_units: Vec<&[u8]> = nal::AnnexBSplitter::new(DATA)
+        .map(nal::strip_start_code)
+        .collect();
+    let (parameter_sets, slices) = nal_units.split_at(3);
+
+    let record = nal::DecoderConfigurationRecord {
+        length_size: 4,
+        stream_info: None,
+        nal_units: parameter_sets
+            .iter()
+            .map(|nal_unit| nal_unit.to_vec())
+            .collect(),
+    };
+    let converter = nal::LengthPrefixedConverter::from_configuration_record(&record).unwrap();
+
+    let mut decoder = Decoder::new()?;
+    let mut num_frames = 0;
+    for (index, slice) in slices.iter().enumerate() {
+        let mut sample = (slice.len() as u32).to_be_bytes().to_vec();
+        sample.extend_from_slice(slice);
+
+        let mut access_unit = converter.convert(&sample).unwrap();
+        assert_eq!(access_unit.is_random_access_point, index == 0);
+        access_unit.cts = Some(index as u64);
+        match decoder.decode(access_unit) {
+            Ok(frame) => num_frames += frame.is_some() as usize,
+            Err(Error::TryAgain(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    while decoder.flush()?.is_some() {
+        num_frames += 1;
+    }
+    assert_eq!(num_frames, 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_rtp_depacketizer() -> Result<(), Error> {
+    let nal_units: Vec<&[u8]> = nal::AnnexBSplitter::new(DATA)
+        .map(nal::strip_start_code)
+        .collect();
+    // Single NAL unit packets, with the parameter sets and the IDR slice in the first access unit.
+    let timestamps = [3000, 3000, 3000, 3000, 6000, 9000];
+
+    let mut decoder = Decoder::new()?;
+    let mut depacketizer = nal::RtpDepacketizer::new();
+    let mut ctss = Vec::new();
+    for (index, (nal_unit, timestamp)) in nal_units.iter().zip(timestamps).enumerate() {
+        let marker = timestamps.get(index + 1) != Some(&timestamp);
+        let mut packet = vec![0x80, (marker as u8) << 7 | 96];
+        packet.extend_from_slice(&(index as u16).to_be_bytes());
+        packet.extend_from_slice(&(timestamp as u32).to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(nal_unit);
+
+        if let Some(access_unit) = depacketizer.push(&packet).unwrap() {
+            assert!(!access_unit.is_damaged);
+            if let Ok(Some(frame)) = decoder.decode(access_unit) {
+                ctss.push(frame.cts());
+            }
+        }
+    }
+    assert!(depacketizer.flush().is_none());
+    while let Some(frame) = decoder.flush()? {
+        assert!(!frame.is_corrupted());
+        ctss.push(frame.cts());
+    }
+    ctss.sort();
+    // RTP timestamps are extended from 2^32.
+    assert_eq!(
+        ctss,
+        [3000, 6000, 9000].map(|timestamp| Some((1 << 32) + timestamp))
+    );
+
+    Ok(())
+}
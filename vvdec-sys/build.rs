@@ -1,9 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use bindgen::{RustEdition, RustTarget};
 
 const VVDEC_VERSION: &str = "3.0.0";
 
+static VENDORED: AtomicBool = AtomicBool::new(false);
+
 mod vendored {
     use super::*;
     use std::str::FromStr;
@@ -13,6 +18,7 @@ mod vendored {
         _version: &str,
     ) -> Result<system_deps::Library, system_deps::BuildInternalClosureError> {
         println!("cargo:rerun-if-changed=vvdec");
+        VENDORED.store(true, Ordering::Relaxed);
         let source = PathBuf::from_str("vvdec").expect("submodule is initialized");
         let mut config = cmake::Config::new(source);
         config.define("VVDEC_TOPLEVEL_OUTPUT_DIRS", "OFF");
@@ -28,6 +34,7 @@ mod vendored {
 
 fn main() {
     println!("cargo:rerun-if-changed=wrapper.h");
+    println!("cargo:rerun-if-changed=logging.c");
 
     #[cfg(feature = "docsrs")]
     std::env::set_var("SYSTEM_DEPS_LIBVVDEC_BUILD_INTERNAL", "always");
@@ -39,6 +46,23 @@ fn main() {
 
     let library = dependencies.get_by_name("libvvdec").unwrap();
 
+    println!("cargo:rustc-check-cfg=cfg(vvdec_vendored)");
+    if VENDORED.load(Ordering::Relaxed) {
+        println!("cargo:rustc-cfg=vvdec_vendored");
+    }
+
+    // VVdeC logs through a printf-style callback taking a va_list, which cannot be consumed portably from Rust.
+    let mut build = cc::Build::new();
+    build
+        .file("logging.c")
+        .include(".")
+        .includes(&library.include_paths);
+    // The logging callback is stored in a C11 atomic, which MSVC only supports experimentally.
+    if build.get_compiler().is_like_msvc() {
+        build.flag("/std:c11").flag("/experimental:c11atomics");
+    }
+    build.compile("vvdec-rs-logging");
+
     let bindings = bindgen::Builder::default()
         // FIXME: InvalidRustTarget doesn't implement Debug?!
         .rust_target(RustTarget::stable(80, 1).map_err(|_| ()).unwrap())
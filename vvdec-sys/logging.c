@@ -0,0 +1,28 @@
+#include <stdarg.h>
+#include <stdatomic.h>
+#include <stdio.h>
+
+#include "wrapper.h"
+
+#define VVDEC_RS_LOG_BUFFER_SIZE 4096
+
+// Shared by all decoders, which may log from their worker threads while another decoder sets it.
+static _Atomic vvdecRsLoggingCallback vvdec_rs_logging_callback = NULL;
+
+static void vvdec_rs_logging_trampoline(void *opaque, int level, const char *fmt, va_list args)
+{
+  char message[VVDEC_RS_LOG_BUFFER_SIZE];
+  vvdecRsLoggingCallback callback = atomic_load(&vvdec_rs_logging_callback);
+
+  if (callback == NULL || fmt == NULL)
+    return;
+
+  vsnprintf(message, sizeof(message), fmt, args);
+  callback(opaque, level, message);
+}
+
+int vvdec_rs_set_logging_callback(vvdecDecoder *decoder, vvdecRsLoggingCallback callback)
+{
+  atomic_store(&vvdec_rs_logging_callback, callback);
+  return vvdec_set_logging_callback(decoder, vvdec_rs_logging_trampoline);
+}
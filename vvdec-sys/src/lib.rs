@@ -3,3 +3,6 @@
 #![allow(non_snake_case)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Whether VVdeC was built from the vendored sources, rather than found in the system.
+pub const VVDEC_VENDORED: bool = cfg!(vvdec_vendored);
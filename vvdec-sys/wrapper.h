@@ -1 +1,7 @@
 #include <vvdec/vvdec.h>
+
+// Logging callback receiving an already formatted message.
+typedef void (*vvdecRsLoggingCallback)(void *opaque, int level, const char *message);
+
+// Set a logging callback on the decoder, formatting VVdeC's printf-style messages before forwarding them.
+int vvdec_rs_set_logging_callback(vvdecDecoder *decoder, vvdecRsLoggingCallback callback);
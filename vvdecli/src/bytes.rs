@@ -0,0 +1,46 @@
+use anyhow::bail;
+
+/// Reader of big-endian values from a byte slice.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("unexpected end of data");
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub fn skip(&mut self, len: usize) -> anyhow::Result<()> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    pub fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_be_bytes(self.read_bytes(2)?.try_into()?))
+    }
+
+    pub fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.read_bytes(4)?.try_into()?))
+    }
+
+    pub fn read_u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_be_bytes(self.read_bytes(8)?.try_into()?))
+    }
+}
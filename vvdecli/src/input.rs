@@ -0,0 +1,195 @@
+use std::{
+    fs::File,
+    io::{self, Cursor, Read, Seek},
+    path::Path,
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use vvdec::{
+    nal::{AccessUnitAssembler, AnnexBReader},
+    AccessUnit,
+};
+
+use crate::{
+    mkv::MatroskaDemuxer,
+    mp4::Mp4Demuxer,
+    rtp::{PcapReader, RtpDemuxer, RtpdumpReader},
+    ts::TsDemuxer,
+};
+
+// Number of bytes read from the start of the input to probe its format.
+const PROBE_SIZE: usize = 4096;
+
+/// Input container format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Detect the format from the file extension, or from the data otherwise.
+    Auto,
+    /// Raw Annex-B byte stream.
+    AnnexB,
+    /// MP4 file with a 'vvc1' or 'vvi1' track.
+    Mp4,
+    /// Matroska or WebM file with a 'V_MPEGI/ISO/VVC' track.
+    Matroska,
+    /// MPEG-2 transport stream with a VVC elementary stream (stream type 0x33).
+    Ts,
+    /// Packet capture of a VVC RTP stream over UDP, in the pcap format.
+    Pcap,
+    /// Recording of a VVC RTP stream in the rtpdump format of rtptools.
+    Rtpdump,
+}
+
+impl InputFormat {
+    fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "mp4" | "m4v" | "mov" => Some(Self::Mp4),
+            "mkv" | "webm" => Some(Self::Matroska),
+            "ts" | "m2t" | "trp" => Some(Self::Ts),
+            "pcap" | "cap" => Some(Self::Pcap),
+            "rtpdump" | "rtp" => Some(Self::Rtpdump),
+            "vvc" | "266" | "h266" | "bit" | "bin" => Some(Self::AnnexB),
+            _ => None,
+        }
+    }
+
+    fn probe(data: &[u8]) -> Self {
+        if crate::mp4::probe(data) {
+            Self::Mp4
+        } else if crate::mkv::probe(data) {
+            Self::Matroska
+        } else if crate::ts::probe(data) {
+            Self::Ts
+        } else if crate::rtp::probe_pcap(data) {
+            Self::Pcap
+        } else if crate::rtp::probe_rtpdump(data) {
+            Self::Rtpdump
+        } else {
+            Self::AnnexB
+        }
+    }
+}
+
+/// Selection of the VVC stream in inputs that can carry several streams.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamSelector {
+    /// Program number in a transport stream. The first program with a VVC stream is selected if unset.
+    pub program: Option<u16>,
+    /// PID of the elementary stream in a transport stream, taking precedence over the program.
+    pub pid: Option<u16>,
+    /// UDP destination port of the RTP stream in a packet capture.
+    pub port: Option<u16>,
+    /// Payload type of the RTP stream. The stream of the first RTP packet is selected if unset.
+    pub payload_type: Option<u8>,
+}
+
+pub type AccessUnits = Box<dyn Iterator<Item = anyhow::Result<AccessUnit<Vec<u8>>>>>;
+
+/// Open the input file, or stdin if no path is given, as an iterator of access units.
+///
+/// The selector picks the VVC stream of transport streams and RTP captures.
+pub fn open(
+    path: Option<&Path>,
+    format: InputFormat,
+    selector: StreamSelector,
+) -> anyhow::Result<AccessUnits> {
+    let (source, head) = match path {
+        Some(path) => {
+            let mut file = File::open(path).context("could not open input file")?;
+            let head = read_head(&mut file)?;
+            file.rewind()?;
+            (Source::File(file), head)
+        }
+        None => {
+            let mut stdin = io::stdin();
+            let head = read_head(&mut stdin)?;
+            let stream = Cursor::new(head.clone()).chain(stdin);
+            (Source::Stream(Box::new(stream)), head)
+        }
+    };
+
+    let format = match format {
+        InputFormat::Auto => path
+            .and_then(InputFormat::from_extension)
+            .unwrap_or_else(|| InputFormat::probe(&head)),
+        format => format,
+    };
+
+    Ok(match format {
+        InputFormat::Auto | InputFormat::AnnexB => Box::new(AnnexBInput {
+            reader: AnnexBReader::new(source.into_read()),
+            assembler: AccessUnitAssembler::new(),
+        }),
+        InputFormat::Mp4 => Box::new(Mp4Demuxer::new(source.into_seekable()?)?),
+        InputFormat::Matroska => Box::new(MatroskaDemuxer::new(source.into_read())?),
+        InputFormat::Ts => Box::new(TsDemuxer::new(source.into_read(), selector)),
+        InputFormat::Pcap => Box::new(RtpDemuxer::new(
+            PcapReader::new(source.into_read(), selector.port)?,
+            selector,
+        )),
+        InputFormat::Rtpdump => Box::new(RtpDemuxer::new(
+            RtpdumpReader::new(source.into_read())?,
+            selector,
+        )),
+    })
+}
+
+fn read_head(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(PROBE_SIZE);
+    reader.take(PROBE_SIZE as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+enum Source {
+    File(File),
+    Stream(Box<dyn Read>),
+}
+
+impl Source {
+    fn into_read(self) -> Box<dyn Read> {
+        match self {
+            Self::File(file) => Box::new(file),
+            Self::Stream(stream) => stream,
+        }
+    }
+
+    // Streams are read entirely into memory to be seekable.
+    fn into_seekable(self) -> io::Result<Box<dyn ReadSeek>> {
+        match self {
+            Self::File(file) => Ok(Box::new(file)),
+            Self::Stream(mut stream) => {
+                let mut data = Vec::new();
+                stream.read_to_end(&mut data)?;
+                Ok(Box::new(Cursor::new(data)))
+            }
+        }
+    }
+}
+
+struct AnnexBInput {
+    reader: AnnexBReader<Box<dyn Read>>,
+    assembler: AccessUnitAssembler,
+}
+
+impl Iterator for AnnexBInput {
+    type Item = anyhow::Result<AccessUnit<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.reader.next_nal_unit() {
+                Ok(Some(nal_unit)) => {
+                    if let Some(access_unit) = self.assembler.push(nal_unit) {
+                        return Some(Ok(access_unit));
+                    }
+                }
+                Ok(None) => return self.assembler.flush().map(Ok),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
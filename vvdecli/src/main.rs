@@ -1,125 +1,111 @@
-use std::{fs::File, io::Read, io::Write, path::PathBuf};
+use std::{fs::File, io::Write, path::PathBuf};
 
 use clap::Parser;
-use vvdec::{ColorFormat, Decoder, Error, Frame, PlaneComponent};
-use y4m::{Colorspace, Encoder};
+use vvdec::{Decoder, Error, ErrorHandling, Frame};
 
-mod chunked_reader;
-use chunked_reader::ChunkedReader;
+use input::{InputFormat, StreamSelector};
+use output::{FrameWriter, OutputFormat};
+
+mod bytes;
+mod input;
+mod mkv;
+mod mp4;
+mod output;
+mod rtp;
+mod ts;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Input VVC file. If empty, input is read from stdin.
+    /// Input VVC file, either an Annex-B bitstream or a container. If empty, input is read from stdin.
     #[arg(short, long)]
     input: Option<PathBuf>,
 
-    /// Output Y4M file. If empty, output is written to stdout.
+    /// Input container format.
+    #[arg(long, value_enum, default_value_t = InputFormat::Auto)]
+    input_format: InputFormat,
+
+    /// Program number of the VVC stream in a transport stream. Defaults to the first program
+    /// carrying VVC.
+    #[arg(long)]
+    program: Option<u16>,
+
+    /// PID of the VVC stream in a transport stream, taking precedence over the program.
+    #[arg(long)]
+    pid: Option<u16>,
+
+    /// UDP destination port of the VVC RTP stream in a pcap file.
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Payload type of the VVC RTP stream in pcap and rtpdump files. Defaults to the stream of the
+    /// first RTP packet.
+    #[arg(long)]
+    payload_type: Option<u8>,
+
+    /// Output file. If empty, output is written to stdout.
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    /// Output file format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Y4m)]
+    format: OutputFormat,
+
+    /// Bit depth of the output samples. Defaults to the bit depth of the stream.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(8..=16))]
+    output_bitdepth: Option<u32>,
+
+    /// Stop at the first error in the input bitstream, instead of concealing damaged pictures and
+    /// going on.
+    #[arg(long)]
+    stop_on_error: bool,
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let reader: Box<dyn Read> = cli.input.map_or(Box::new(std::io::stdin()), |i| {
-        Box::new(File::open(i).expect("could not open input file"))
-    });
+    let selector = StreamSelector {
+        program: cli.program,
+        pid: cli.pid,
+        port: cli.port,
+        payload_type: cli.payload_type,
+    };
+    let access_units = input::open(cli.input.as_deref(), cli.input_format, selector)?;
 
-    let mut writer: Box<dyn Write> = cli.output.map_or(Box::new(std::io::stdout()), |o| {
+    let writer: Box<dyn Write> = cli.output.map_or(Box::new(std::io::stdout()), |o| {
         Box::new(File::create(o).expect("could not open output file"))
     });
+    let mut frame_writer = FrameWriter::new(writer, cli.format, cli.output_bitdepth)?;
 
-    let mut chunked_reader = ChunkedReader::new(reader);
-    let mut decoder = Decoder::builder().build()?;
-
-    let mut y4m_encoder = None;
-    while let Some(chunk) = chunked_reader.next_chunk()? {
-        match decoder.decode(chunk) {
-            Ok(Some(frame)) => {
-                let y4m_encoder = y4m_encoder.get_or_insert_with(|| {
-                    let writer = std::mem::replace(&mut writer, Box::new(std::io::sink()));
-                    create_y4m_encoder(&frame, writer).expect("could not create y4m encoder")
-                });
-                write_frame(y4m_encoder, frame)?;
-            }
-            Ok(None) | Err(Error::TryAgain) => {}
+    let error_handling = if cli.stop_on_error {
+        ErrorHandling::Strict
+    } else {
+        ErrorHandling::TryContinue
+    };
+    let mut decoder = Decoder::builder().error_handling(error_handling).build()?;
+
+    let mut num_corrupted_frames = 0;
+    let mut write_frame = |frame: Frame| {
+        num_corrupted_frames += frame.is_corrupted() as usize;
+        frame_writer.write_frame(frame)
+    };
+
+    for access_unit in access_units {
+        match decoder.decode(access_unit?) {
+            Ok(Some(frame)) => write_frame(frame)?,
+            Ok(None) | Err(Error::TryAgain(_)) => {}
+            Err(err @ Error::DecInput(_)) if !cli.stop_on_error => eprintln!("warning: {err}"),
             Err(err) => return Err(err.into()),
         }
     }
 
     while let Some(frame) = decoder.flush()? {
-        let y4m_encoder = y4m_encoder.get_or_insert_with(|| {
-            let writer = std::mem::replace(&mut writer, Box::new(std::io::sink()));
-            create_y4m_encoder(&frame, writer).expect("could not create y4m encoder")
-        });
-        write_frame(y4m_encoder, frame)?;
+        write_frame(frame)?;
     }
 
-    Ok(())
-}
-
-fn create_y4m_encoder<W: Write>(frame: &Frame, writer: W) -> Result<Encoder<W>, y4m::Error> {
-    let hrd = frame.picture_attributes().unwrap().hrd.unwrap();
-    y4m::encode(
-        frame.width() as usize,
-        frame.height() as usize,
-        y4m::Ratio {
-            num: hrd.time_scale as usize,
-            den: hrd.num_units_in_tick as usize,
-        },
-    )
-    .with_colorspace(convert_colorspace(frame.color_format(), frame.bit_depth()))
-    .write_header(writer)
-}
-
-fn convert_colorspace(color_format: ColorFormat, bit_depth: u32) -> Colorspace {
-    if bit_depth > 8 {
-        match color_format {
-            ColorFormat::Yuv420Planar => Colorspace::C420p10,
-            ColorFormat::Yuv422Planar => Colorspace::C422p10,
-            ColorFormat::Yuv444Planar => Colorspace::C444p10,
-            _ => unimplemented!(),
-        }
-    } else {
-        match color_format {
-            ColorFormat::Yuv420Planar => Colorspace::C420,
-            ColorFormat::Yuv422Planar => Colorspace::C422,
-            ColorFormat::Yuv444Planar => Colorspace::C444,
-            _ => unimplemented!(),
-        }
+    if num_corrupted_frames > 0 {
+        eprintln!("warning: {num_corrupted_frames} frames may be corrupted by errors in the input");
     }
-}
-
-fn remove_padding(frame: Frame) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
-    let y_plane = frame.plane(PlaneComponent::Y).unwrap();
-    let u_plane = frame.plane(PlaneComponent::U).unwrap();
-    let v_plane = frame.plane(PlaneComponent::V).unwrap();
-
-    let y_plane_data = extract_plane_data(&y_plane);
-    let u_plane_data = extract_plane_data(&u_plane);
-    let v_plane_data = extract_plane_data(&v_plane);
-
-    (y_plane_data, u_plane_data, v_plane_data)
-}
-
-fn extract_plane_data(plane: &vvdec::Plane) -> Vec<u8> {
-    let mut plane_data = Vec::with_capacity((plane.width() * plane.height() * plane.bytes_per_sample()) as usize);
-    for row in 0..plane.height() {
-        let start = (row * plane.stride()) as usize;
-        let end = start + (plane.width() * plane.bytes_per_sample()) as usize;
-        plane_data.extend_from_slice(&plane.as_ref()[start..end]);
-    }
-    plane_data
-}
 
-fn write_frame(encoder: &mut y4m::Encoder<impl Write>, frame: Frame) -> anyhow::Result<()> {
-    let (y_plane, u_plane, v_plane) = remove_padding(frame);
-    encoder.write_frame(&y4m::Frame::new(
-        [
-            y_plane.as_slice(), u_plane.as_slice(), v_plane.as_slice(),
-        ],
-        None,
-    ))?;
     Ok(())
 }
@@ -0,0 +1,432 @@
+use std::io::{self, Read};
+
+use anyhow::{bail, Context};
+use vvdec::{
+    nal::{DecoderConfigurationRecord, LengthPrefixedConverter},
+    AccessUnit,
+};
+
+const EBML: u32 = 0x1A45DFA3;
+const SEGMENT: u32 = 0x18538067;
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const CLUSTER: u32 = 0x1F43B675;
+const CLUSTER_TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const REFERENCE_BLOCK: u32 = 0xFB;
+
+const VVC_CODEC_ID: &[u8] = b"V_MPEGI/ISO/VVC";
+
+/// Check whether data looks like the start of a Matroska or WebM file.
+pub fn probe(data: &[u8]) -> bool {
+    data.starts_with(&EBML.to_be_bytes())
+}
+
+/// Demuxer of the first VVC track of a Matroska or WebM file.
+///
+/// Frames are converted to Annex-B access units carrying their composition timestamps, in units of the segment's
+/// timestamp scale, which is 1 ms by default. The file is read sequentially, so it can be streamed.
+pub struct MatroskaDemuxer<R> {
+    reader: R,
+    track_number: u64,
+    converter: LengthPrefixedConverter,
+    cluster_timestamp: u64,
+}
+
+impl<R: Read> MatroskaDemuxer<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        // Elements preceding the first cluster are read until the VVC track is found.
+        let (track_number, converter) = loop {
+            let Some((id, size)) = read_element_header(&mut reader)? else {
+                bail!("no VVC track found in the Matroska file");
+            };
+            match id {
+                SEGMENT => {}
+                TRACKS => {
+                    if let Some(track) = find_vvc_track(&read_element(&mut reader, size)?)? {
+                        break track;
+                    }
+                }
+                CLUSTER => bail!("no VVC track found before the first cluster"),
+                _ => skip_element(&mut reader, size)?,
+            }
+        };
+
+        Ok(Self {
+            reader,
+            track_number,
+            converter,
+            cluster_timestamp: 0,
+        })
+    }
+
+    fn next_access_unit(&mut self) -> anyhow::Result<Option<AccessUnit<Vec<u8>>>> {
+        loop {
+            let Some((id, size)) = read_element_header(&mut self.reader)? else {
+                return Ok(None);
+            };
+            let block = match id {
+                // Clusters are read element by element, as their size may be unknown when streaming.
+                SEGMENT | CLUSTER => continue,
+                CLUSTER_TIMESTAMP => {
+                    self.cluster_timestamp = read_uint(&read_element(&mut self.reader, size)?);
+                    continue;
+                }
+                SIMPLE_BLOCK => {
+                    let data = read_element(&mut self.reader, size)?;
+                    let block = Block::parse(&data)?;
+                    let is_keyframe = block.flags & 0x80 != 0;
+                    self.access_unit(block, is_keyframe)?
+                }
+                BLOCK_GROUP => {
+                    let data = read_element(&mut self.reader, size)?;
+                    let mut block = None;
+                    let mut is_keyframe = true;
+                    for child in Elements(&data) {
+                        match child? {
+                            (BLOCK, data) => block = Some(Block::parse(data)?),
+                            (REFERENCE_BLOCK, _) => is_keyframe = false,
+                            _ => {}
+                        }
+                    }
+                    let block = block.context("block group without block")?;
+                    self.access_unit(block, is_keyframe)?
+                }
+                _ => {
+                    skip_element(&mut self.reader, size)?;
+                    continue;
+                }
+            };
+            if block.is_some() {
+                return Ok(block);
+            }
+        }
+    }
+
+    // Returns None for blocks of other tracks.
+    fn access_unit(
+        &self,
+        block: Block,
+        is_keyframe: bool,
+    ) -> anyhow::Result<Option<AccessUnit<Vec<u8>>>> {
+        if block.track_number != self.track_number {
+            return Ok(None);
+        }
+        // Lacing is not used for video in practice.
+        if block.flags & 0x06 != 0 {
+            bail!("laced Matroska blocks are not supported");
+        }
+
+        let mut access_unit = self.converter.convert(block.frame)?;
+        let cts = self.cluster_timestamp as i64 + block.relative_timestamp as i64;
+        access_unit.cts = Some(cts.max(0) as u64);
+        access_unit.is_random_access_point |= is_keyframe;
+        Ok(Some(access_unit))
+    }
+}
+
+impl<R: Read> Iterator for MatroskaDemuxer<R> {
+    type Item = anyhow::Result<AccessUnit<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_access_unit().transpose()
+    }
+}
+
+fn find_vvc_track(tracks: &[u8]) -> anyhow::Result<Option<(u64, LengthPrefixedConverter)>> {
+    for entry in Elements(tracks) {
+        let (id, entry) = entry?;
+        if id != TRACK_ENTRY {
+            continue;
+        }
+
+        let mut track_number = None;
+        let mut codec_id = None;
+        let mut codec_private = None;
+        for child in Elements(entry) {
+            match child? {
+                (TRACK_NUMBER, data) => track_number = Some(read_uint(data)),
+                (CODEC_ID, data) => codec_id = Some(data),
+                (CODEC_PRIVATE, data) => codec_private = Some(data),
+                _ => {}
+            }
+        }
+        if codec_id.map(|codec_id| codec_id.strip_suffix(&[0]).unwrap_or(codec_id))
+            != Some(VVC_CODEC_ID)
+        {
+            continue;
+        }
+
+        let track_number = track_number.context("VVC track without track number")?;
+        let mut codec_private = codec_private.context("VVC track without codec private data")?;
+        // Some muxers keep the version and flags of the vvcC box, while the record starts with reserved bits set.
+        if codec_private.len() > 4 && codec_private[..4] == [0; 4] {
+            codec_private = &codec_private[4..];
+        }
+        let record = DecoderConfigurationRecord::parse(codec_private)
+            .context("invalid VVC codec private data")?;
+        return Ok(Some((
+            track_number,
+            LengthPrefixedConverter::from_configuration_record(&record)?,
+        )));
+    }
+    Ok(None)
+}
+
+struct Block<'a> {
+    track_number: u64,
+    relative_timestamp: i16,
+    flags: u8,
+    frame: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    fn parse(mut data: &'a [u8]) -> anyhow::Result<Self> {
+        let track_number = read_vint(&mut data, false)?.context("truncated block")?;
+        let [t0, t1, flags, ..] = *data else {
+            bail!("truncated block");
+        };
+        Ok(Self {
+            track_number,
+            relative_timestamp: i16::from_be_bytes([t0, t1]),
+            flags,
+            frame: &data[3..],
+        })
+    }
+}
+
+// Reads an element ID and size, or returns None at the end of the data. Unknown sizes are returned as None.
+fn read_element_header(reader: &mut impl Read) -> anyhow::Result<Option<(u32, Option<u64>)>> {
+    let Some(id) = read_vint(reader, true)? else {
+        return Ok(None);
+    };
+    let size = read_vint(reader, false)?.context("truncated element header")?;
+    Ok(Some((id as u32, (size != u64::MAX).then_some(size))))
+}
+
+fn read_element(reader: &mut impl Read, size: Option<u64>) -> anyhow::Result<Vec<u8>> {
+    let size = size.context("unexpected element of unknown size")?;
+    let mut data = Vec::new();
+    reader.take(size).read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        bail!("truncated element");
+    }
+    Ok(data)
+}
+
+fn skip_element(reader: &mut impl Read, size: Option<u64>) -> anyhow::Result<()> {
+    let size = size.context("unexpected element of unknown size")?;
+    if io::copy(&mut reader.take(size), &mut io::sink())? != size {
+        bail!("truncated element");
+    }
+    Ok(())
+}
+
+// Reads an EBML variable-length integer, keeping its length marker for element IDs. A size with all value bits set
+// is returned as u64::MAX, meaning unknown.
+fn read_vint(reader: &mut impl Read, keep_marker: bool) -> anyhow::Result<Option<u64>> {
+    let mut first = [0];
+    if reader.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    let len = first[0].leading_zeros() as usize + 1;
+    if len > 8 {
+        bail!("invalid EBML variable-length integer");
+    }
+    let mut bytes = [0; 8];
+    reader
+        .read_exact(&mut bytes[..len - 1])
+        .context("truncated EBML variable-length integer")?;
+
+    let marker = if keep_marker { 0 } else { 0x80 >> (len - 1) };
+    let mut value = (first[0] & !marker) as u64;
+    let mut all_ones = value == (0xff >> len) as u64;
+    for &byte in &bytes[..len - 1] {
+        value = value << 8 | byte as u64;
+        all_ones &= byte == 0xff;
+    }
+    Ok(Some(if !keep_marker && all_ones {
+        u64::MAX
+    } else {
+        value
+    }))
+}
+
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter().fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+// Iterator over the child elements of a master element, yielding their ID and data.
+struct Elements<'a>(&'a [u8]);
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = anyhow::Result<(u32, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let result = (|| {
+            let (id, size) = read_element_header(&mut self.0)?.context("truncated element")?;
+            let size = size.context("unexpected element of unknown size")?;
+            if size > self.0.len() as u64 {
+                bail!("truncated element");
+            }
+            let (data, rest) = self.0.split_at(size as usize);
+            self.0 = rest;
+            Ok((id, data))
+        })();
+        if result.is_err() {
+            self.0 = &[];
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vvdec::nal::{strip_start_code, AnnexBSplitter};
+
+    use super::*;
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+
+    // Elements are written with 8-byte sizes, or an unknown size if no data is given.
+    fn element(id: u32, data: Option<&[u8]>) -> Vec<u8> {
+        let mut element: Vec<u8> = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&byte| byte == 0)
+            .collect();
+        match data {
+            Some(data) => {
+                element.push(1);
+                element.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+                element.extend_from_slice(data);
+            }
+            None => element.push(0xff),
+        }
+        element
+    }
+
+    fn block(track_number: u8, relative_timestamp: i16, flags: u8, nal_unit: &[u8]) -> Vec<u8> {
+        let mut block = vec![0x80 | track_number];
+        block.extend_from_slice(&relative_timestamp.to_be_bytes());
+        block.push(flags);
+        block.extend_from_slice(&(nal_unit.len() as u32).to_be_bytes());
+        block.extend_from_slice(nal_unit);
+        block
+    }
+
+    // Matroska file with the IDR picture and the two STSA pictures of short.vvc in a cluster of unknown size,
+    // interleaved with a block of another track.
+    fn mkv_file() -> Vec<u8> {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).map(strip_start_code).collect();
+        let (parameter_sets, pictures) = nal_units.split_at(3);
+
+        let vvcc = DecoderConfigurationRecord {
+            length_size: 4,
+            stream_info: None,
+            nal_units: parameter_sets
+                .iter()
+                .map(|nal_unit| nal_unit.to_vec())
+                .collect(),
+        }
+        .to_bytes();
+        let audio_track = [
+            element(TRACK_NUMBER, Some(&[1])),
+            element(CODEC_ID, Some(b"A_OPUS")),
+        ]
+        .concat();
+        let video_track = [
+            element(TRACK_NUMBER, Some(&[2])),
+            element(CODEC_ID, Some(VVC_CODEC_ID)),
+            element(CODEC_PRIVATE, Some(&vvcc)),
+        ]
+        .concat();
+        let tracks = [
+            element(TRACK_ENTRY, Some(&audio_track)),
+            element(TRACK_ENTRY, Some(&video_track)),
+        ]
+        .concat();
+
+        let block_group = [
+            element(BLOCK, Some(&block(2, 80, 0, pictures[1]))),
+            element(REFERENCE_BLOCK, Some(&[0xd8])),
+        ]
+        .concat();
+        [
+            element(EBML, Some(&element(0x4282, Some(b"matroska")))),
+            element(SEGMENT, None),
+            element(TRACKS, Some(&tracks)),
+            element(CLUSTER, None),
+            element(CLUSTER_TIMESTAMP, Some(&[100])),
+            element(SIMPLE_BLOCK, Some(&block(2, 0, 0x80, pictures[0]))),
+            element(SIMPLE_BLOCK, Some(&block(1, 0, 0x80, &[0xaa]))),
+            element(BLOCK_GROUP, Some(&block_group)),
+            element(SIMPLE_BLOCK, Some(&block(2, 40, 0, pictures[2]))),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_probe() {
+        assert!(probe(&mkv_file()));
+        assert!(!probe(DATA));
+    }
+
+    #[test]
+    fn test_demux() {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).map(strip_start_code).collect();
+        let access_units = MatroskaDemuxer::new(mkv_file().as_slice())
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(access_units.len(), 3);
+
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| (access_unit.cts, access_unit.is_random_access_point))
+            .collect();
+        assert_eq!(
+            timestamps,
+            [(Some(100), true), (Some(180), false), (Some(140), false)]
+        );
+
+        // The parameter sets of the codec private data are inserted before the IDR picture.
+        let first: Vec<_> = AnnexBSplitter::new(&access_units[0].payload)
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(first, nal_units[..4]);
+    }
+
+    #[test]
+    fn test_read_vint() {
+        assert_eq!(read_vint(&mut &[0x81][..], false).unwrap(), Some(1));
+        assert_eq!(read_vint(&mut &[0x40, 0x02][..], false).unwrap(), Some(2));
+        assert_eq!(
+            read_vint(&mut &[0x1a, 0x45, 0xdf, 0xa3][..], true).unwrap(),
+            Some(EBML as u64)
+        );
+        assert_eq!(read_vint(&mut &[0xff][..], false).unwrap(), Some(u64::MAX));
+        assert_eq!(read_vint(&mut &[][..], false).unwrap(), None);
+        assert!(read_vint(&mut &[0x40][..], false).is_err());
+        assert!(read_vint(&mut &[0][..], false).is_err());
+    }
+
+    #[test]
+    fn test_no_vvc_track() {
+        let data = [
+            element(EBML, Some(&[])),
+            element(SEGMENT, None),
+            element(CLUSTER, None),
+        ]
+        .concat();
+        assert!(MatroskaDemuxer::new(data.as_slice()).is_err());
+    }
+}
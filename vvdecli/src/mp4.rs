@@ -0,0 +1,539 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read, Seek, SeekFrom},
+    vec,
+};
+
+use anyhow::{bail, Context};
+use vvdec::{
+    nal::{DecoderConfigurationRecord, LengthPrefixedConverter},
+    AccessUnit,
+};
+
+use crate::bytes::ByteReader;
+
+type FourCc = [u8; 4];
+
+/// Check whether data looks like the start of an MP4 file.
+pub fn probe(data: &[u8]) -> bool {
+    const BOX_TYPES: [&FourCc; 7] = [
+        b"ftyp", b"styp", b"moov", b"mdat", b"free", b"skip", b"wide",
+    ];
+    data.get(4..8)
+        .is_some_and(|box_type| BOX_TYPES.iter().any(|&expected| box_type == expected))
+}
+
+/// Demuxer of the first VVC track of an MP4 file.
+///
+/// Samples are converted to Annex-B access units carrying their composition and decoding timestamps, in the
+/// timescale of the track. Fragmented files are not supported.
+pub struct Mp4Demuxer<R> {
+    reader: R,
+    converter: LengthPrefixedConverter,
+    samples: vec::IntoIter<Sample>,
+    file_size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Sample {
+    offset: u64,
+    size: u32,
+    dts: u64,
+    cts: u64,
+    is_sync: bool,
+}
+
+impl<R: Read + Seek> Mp4Demuxer<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let start = reader.stream_position()?;
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        let moov = read_moov(&mut reader)?;
+
+        let mut tracks = BoxIter::new(&moov).filter_map(|child| match child {
+            Ok((box_type, trak)) if &box_type == b"trak" => Some(parse_vvc_track(trak, file_size)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        });
+        let Some((record, samples)) = tracks.find_map(|track| track.transpose()).transpose()?
+        else {
+            bail!("no VVC track found in the MP4 file");
+        };
+        if samples.is_empty() && find_box(&moov, &[b"mvex"]).is_some() {
+            bail!("fragmented MP4 files are not supported");
+        }
+
+        Ok(Self {
+            reader,
+            converter: LengthPrefixedConverter::from_configuration_record(&record)?,
+            samples: samples.into_iter(),
+            file_size,
+        })
+    }
+
+    fn read_sample(&mut self, sample: &Sample) -> anyhow::Result<AccessUnit<Vec<u8>>> {
+        // Sizes are checked before allocating, as they are only bounded by the sample table.
+        if sample.offset.saturating_add(sample.size as u64) > self.file_size {
+            bail!("MP4 sample exceeds the file size");
+        }
+        let mut data = vec![0; sample.size as usize];
+        self.reader.seek(SeekFrom::Start(sample.offset))?;
+        self.reader
+            .read_exact(&mut data)
+            .context("could not read MP4 sample")?;
+
+        let mut access_unit = self.converter.convert(&data)?;
+        access_unit.cts = Some(sample.cts);
+        access_unit.dts = Some(sample.dts);
+        access_unit.is_random_access_point |= sample.is_sync;
+        Ok(access_unit)
+    }
+}
+
+impl<R: Read + Seek> Iterator for Mp4Demuxer<R> {
+    type Item = anyhow::Result<AccessUnit<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.samples.next()?;
+        Some(self.read_sample(&sample))
+    }
+}
+
+// Reads the top-level boxes until the movie box, and returns its payload.
+fn read_moov(reader: &mut (impl Read + Seek)) -> anyhow::Result<Vec<u8>> {
+    loop {
+        let mut header = [0; 8];
+        match reader.read_exact(&mut header) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                bail!("no movie box found in the MP4 file")
+            }
+            result => result?,
+        }
+        let mut size = u32::from_be_bytes(header[..4].try_into()?) as u64;
+        let mut header_size = 8;
+        if size == 1 {
+            let mut large_size = [0; 8];
+            reader.read_exact(&mut large_size)?;
+            size = u64::from_be_bytes(large_size);
+            header_size += 8;
+        }
+
+        if &header[4..] == b"moov" {
+            let mut moov = Vec::new();
+            let reader = reader.by_ref();
+            if size == 0 {
+                reader.read_to_end(&mut moov)?;
+            } else {
+                let len = size.checked_sub(header_size).context("invalid box size")?;
+                reader.take(len).read_to_end(&mut moov)?;
+            }
+            return Ok(moov);
+        }
+        if size == 0 {
+            bail!("no movie box found in the MP4 file");
+        }
+        let len = size.checked_sub(header_size).context("invalid box size")?;
+        reader.seek(SeekFrom::Current(len.try_into()?))?;
+    }
+}
+
+// Returns the decoder configuration record and the samples of a track, if it is a VVC track.
+fn parse_vvc_track(
+    trak: &[u8],
+    file_size: u64,
+) -> anyhow::Result<Option<(DecoderConfigurationRecord, Vec<Sample>)>> {
+    let Some(stbl) = find_box(trak, &[b"mdia", b"minf", b"stbl"]) else {
+        return Ok(None);
+    };
+    let Some(stsd) = find_box(stbl, &[b"stsd"]) else {
+        return Ok(None);
+    };
+
+    // Full box header and entry count, followed by the first sample entry.
+    let Some(Ok((entry_type, entry))) = stsd
+        .get(8..)
+        .map(BoxIter::new)
+        .and_then(|mut entries| entries.next())
+    else {
+        return Ok(None);
+    };
+    if &entry_type != b"vvc1" && &entry_type != b"vvi1" {
+        return Ok(None);
+    }
+    // Child boxes follow the fields of the visual sample entry.
+    let vvcc = entry
+        .get(78..)
+        .and_then(|children| find_box(children, &[b"vvcC"]))
+        .context("VVC sample entry without vvcC box")?;
+    let record = DecoderConfigurationRecord::parse(vvcc.get(4..).unwrap_or_default())
+        .context("invalid vvcC box")?;
+
+    Ok(Some((record, parse_sample_table(stbl, file_size)?)))
+}
+
+fn parse_sample_table(stbl: &[u8], file_size: u64) -> anyhow::Result<Vec<Sample>> {
+    let sizes = parse_sample_sizes(stbl, file_size)?;
+    let chunk_offsets = parse_chunk_offsets(stbl)?;
+
+    // Samples are laid out contiguously in chunks, whose number of samples is given by runs of chunks.
+    let mut stsc = full_box(stbl, b"stsc")?;
+    let num_entries = stsc.read_u32()?;
+    let mut runs = Vec::new();
+    for _ in 0..num_entries {
+        let first_chunk = stsc.read_u32()?;
+        let samples_per_chunk = stsc.read_u32()?;
+        stsc.skip(4)?;
+        if first_chunk == 0 {
+            bail!("invalid stsc box");
+        }
+        runs.push((first_chunk as usize - 1, samples_per_chunk));
+    }
+
+    let mut samples = Vec::with_capacity(sizes.len());
+    let mut sizes = sizes.into_iter();
+    for (index, &(first_chunk, samples_per_chunk)) in runs.iter().enumerate() {
+        let end_chunk = runs
+            .get(index + 1)
+            .map_or(chunk_offsets.len(), |&(next_first_chunk, _)| {
+                next_first_chunk
+            });
+        for &chunk_offset in chunk_offsets
+            .get(first_chunk..end_chunk)
+            .unwrap_or_default()
+        {
+            let mut offset = chunk_offset;
+            for size in sizes.by_ref().take(samples_per_chunk as usize) {
+                samples.push(Sample {
+                    offset,
+                    size,
+                    dts: 0,
+                    cts: 0,
+                    is_sync: true,
+                });
+                offset = offset
+                    .checked_add(size as u64)
+                    .context("invalid chunk offset")?;
+            }
+        }
+    }
+
+    let mut stts = full_box(stbl, b"stts")?;
+    let num_entries = stts.read_u32()?;
+    let mut dts = 0u64;
+    let mut timestamps = samples.iter_mut();
+    for _ in 0..num_entries {
+        let count = stts.read_u32()?;
+        let delta = stts.read_u32()?;
+        for sample in timestamps.by_ref().take(count as usize) {
+            sample.dts = dts;
+            sample.cts = dts;
+            dts += delta as u64;
+        }
+    }
+
+    if let Ok(mut ctts) = full_box(stbl, b"ctts") {
+        let num_entries = ctts.read_u32()?;
+        let mut timestamps = samples.iter_mut();
+        for _ in 0..num_entries {
+            let count = ctts.read_u32()?;
+            // Offsets are signed in version 1, and unsigned values above i32::MAX are not expected in version 0.
+            let offset = ctts.read_u32()? as i32 as i64;
+            for sample in timestamps.by_ref().take(count as usize) {
+                sample.cts = (sample.dts as i64 + offset).max(0) as u64;
+            }
+        }
+    }
+
+    // All samples are sync samples if there is no sync sample box.
+    if let Ok(mut stss) = full_box(stbl, b"stss") {
+        let num_entries = stss.read_u32()?;
+        let mut sync_samples = HashSet::new();
+        for _ in 0..num_entries {
+            sync_samples.insert(stss.read_u32()? as usize);
+        }
+        for (index, sample) in samples.iter_mut().enumerate() {
+            sample.is_sync = sync_samples.contains(&(index + 1));
+        }
+    }
+
+    Ok(samples)
+}
+
+// The number of samples is checked before allocating: samples of a constant size must fit in the
+// file, and other sizes in the box.
+fn parse_sample_sizes(stbl: &[u8], file_size: u64) -> anyhow::Result<Vec<u32>> {
+    if let Ok(mut stsz) = full_box(stbl, b"stsz") {
+        let sample_size = stsz.read_u32()?;
+        let num_samples = stsz.read_u32()?;
+        if sample_size != 0 {
+            if sample_size as u64 * num_samples as u64 > file_size {
+                bail!("MP4 samples exceed the file size");
+            }
+            return Ok(vec![sample_size; num_samples as usize]);
+        }
+        if num_samples as usize > stsz.remaining().len() / 4 {
+            bail!("invalid stsz box");
+        }
+        return (0..num_samples).map(|_| stsz.read_u32()).collect();
+    }
+
+    let mut stz2 = full_box(stbl, b"stz2")?;
+    stz2.skip(3)?;
+    let field_size = stz2.read_u8()?;
+    let num_samples = stz2.read_u32()? as usize;
+    match field_size {
+        4 => {
+            let fields = stz2.read_bytes(num_samples.div_ceil(2))?;
+            Ok((0..num_samples)
+                .map(|index| (fields[index / 2] >> (4 * (1 - index % 2)) & 0xf) as u32)
+                .collect())
+        }
+        8 => (0..num_samples)
+            .map(|_| Ok(stz2.read_u8()? as u32))
+            .collect(),
+        16 => (0..num_samples)
+            .map(|_| Ok(stz2.read_u16()? as u32))
+            .collect(),
+        _ => bail!("invalid stz2 field size {field_size}"),
+    }
+}
+
+fn parse_chunk_offsets(stbl: &[u8]) -> anyhow::Result<Vec<u64>> {
+    if let Ok(mut stco) = full_box(stbl, b"stco") {
+        let num_chunks = stco.read_u32()?;
+        return (0..num_chunks)
+            .map(|_| Ok(stco.read_u32()? as u64))
+            .collect();
+    }
+
+    let mut co64 = full_box(stbl, b"co64")?;
+    let num_chunks = co64.read_u32()?;
+    (0..num_chunks).map(|_| co64.read_u64()).collect()
+}
+
+// Returns a reader over a full box of the sample table, following its version and flags.
+fn full_box<'a>(stbl: &'a [u8], box_type: &FourCc) -> anyhow::Result<ByteReader<'a>> {
+    let payload = find_box(stbl, &[box_type])
+        .with_context(|| format!("missing {} box", String::from_utf8_lossy(box_type)))?;
+    let mut reader = ByteReader::new(payload);
+    reader.skip(4)?;
+    Ok(reader)
+}
+
+// Finds a box by following a path of box types from the children of a box.
+fn find_box<'a>(data: &'a [u8], path: &[&FourCc]) -> Option<&'a [u8]> {
+    let (box_type, rest) = path.split_first()?;
+    let payload = BoxIter::new(data)
+        .map_while(Result::ok)
+        .find_map(|(child_type, payload)| (&child_type == *box_type).then_some(payload))?;
+    if rest.is_empty() {
+        Some(payload)
+    } else {
+        find_box(payload, rest)
+    }
+}
+
+// Iterator over the boxes of a payload, yielding their type and payload.
+struct BoxIter<'a> {
+    reader: ByteReader<'a>,
+}
+
+impl<'a> BoxIter<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            reader: ByteReader::new(data),
+        }
+    }
+
+    fn read_box(&mut self) -> anyhow::Result<(FourCc, &'a [u8])> {
+        let mut size = self.reader.read_u32()? as u64;
+        let box_type: FourCc = self.reader.read_bytes(4)?.try_into()?;
+        let mut header_size = 8;
+        if size == 1 {
+            size = self.reader.read_u64()?;
+            header_size += 8;
+        } else if size == 0 {
+            size = self.reader.remaining().len() as u64 + header_size;
+        }
+        let len = size.checked_sub(header_size).context("invalid box size")?;
+        Ok((box_type, self.reader.read_bytes(len.try_into()?)?))
+    }
+}
+
+impl<'a> Iterator for BoxIter<'a> {
+    type Item = anyhow::Result<(FourCc, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reader.remaining().is_empty() {
+            return None;
+        }
+        let result = self.read_box();
+        if result.is_err() {
+            // Stop after an invalid box.
+            self.reader = ByteReader::new(&[]);
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use vvdec::nal::{strip_start_code, AnnexBSplitter};
+
+    use super::*;
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+
+    fn mp4_box(box_type: &FourCc, payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn mp4_full_box(box_type: &FourCc, values: &[u32]) -> Vec<u8> {
+        let mut payload = vec![0; 4];
+        for value in values {
+            payload.extend_from_slice(&value.to_be_bytes());
+        }
+        mp4_box(box_type, &payload)
+    }
+
+    // MP4 file with the IDR picture and the two STSA pictures of short.vvc as samples, in 2 chunks.
+    fn mp4_file() -> Vec<u8> {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).map(strip_start_code).collect();
+        let (parameter_sets, pictures) = nal_units.split_at(3);
+
+        let record = DecoderConfigurationRecord {
+            length_size: 4,
+            stream_info: None,
+            nal_units: parameter_sets
+                .iter()
+                .map(|nal_unit| nal_unit.to_vec())
+                .collect(),
+        };
+        // Version and flags of the vvcC box.
+        let mut vvcc = vec![0; 4];
+        vvcc.extend(record.to_bytes());
+        let mut sample_entry = vec![0; 78];
+        sample_entry.extend(mp4_box(b"vvcC", &vvcc));
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(mp4_box(b"vvc1", &sample_entry));
+
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        let mut mdat = Vec::new();
+        let mut sizes = Vec::new();
+        for picture in pictures {
+            mdat.extend_from_slice(&(picture.len() as u32).to_be_bytes());
+            mdat.extend_from_slice(picture);
+            sizes.push(picture.len() as u32 + 4);
+        }
+        let first_offset = ftyp.len() as u32 + 8;
+        let second_offset = first_offset + sizes[0] + sizes[1];
+
+        let stbl = [
+            mp4_box(b"stsd", &stsd),
+            mp4_full_box(b"stts", &[1, 3, 2]),
+            mp4_full_box(b"ctts", &[3, 1, 2, 1, 4, 1, 0]),
+            mp4_full_box(b"stsc", &[2, 1, 2, 1, 2, 1, 1]),
+            mp4_full_box(b"stsz", &[0, 3, sizes[0], sizes[1], sizes[2]]),
+            mp4_full_box(b"stco", &[2, first_offset, second_offset]),
+            mp4_full_box(b"stss", &[1, 1]),
+        ]
+        .concat();
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stbl));
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &minf));
+
+        [ftyp, mp4_box(b"mdat", &mdat), mp4_box(b"moov", &trak)].concat()
+    }
+
+    #[test]
+    fn test_probe() {
+        assert!(probe(&mp4_file()));
+        assert!(!probe(DATA));
+    }
+
+    #[test]
+    fn test_demux() {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).map(strip_start_code).collect();
+        let access_units = Mp4Demuxer::new(Cursor::new(mp4_file()))
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(access_units.len(), 3);
+
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| (access_unit.dts, access_unit.cts))
+            .collect();
+        assert_eq!(
+            timestamps,
+            [(Some(0), Some(2)), (Some(2), Some(6)), (Some(4), Some(4))]
+        );
+        let random_access_points: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.is_random_access_point)
+            .collect();
+        assert_eq!(random_access_points, [true, false, false]);
+
+        // The parameter sets of the vvcC box are inserted before the IDR picture.
+        let first: Vec<_> = AnnexBSplitter::new(&access_units[0].payload)
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(first, nal_units[..4]);
+        for (access_unit, nal_unit) in access_units[1..].iter().zip(&nal_units[4..]) {
+            assert_eq!(strip_start_code(&access_unit.payload), *nal_unit);
+        }
+    }
+
+    #[test]
+    fn test_sample_sizes() {
+        let stsz = mp4_full_box(b"stsz", &[100, 3]);
+        assert_eq!(parse_sample_sizes(&stsz, 1000).unwrap(), [100; 3]);
+        let stsz = mp4_full_box(b"stsz", &[100, u32::MAX]);
+        assert!(parse_sample_sizes(&stsz, 1000).is_err());
+        let stsz = mp4_full_box(b"stsz", &[0, u32::MAX, 100]);
+        assert!(parse_sample_sizes(&stsz, 1000).is_err());
+    }
+
+    #[test]
+    fn test_sample_beyond_file() {
+        // Move the second chunk past the end of the file.
+        let mut data = mp4_file();
+        let stco = data
+            .windows(4)
+            .position(|window| window == b"stco")
+            .unwrap();
+        data[stco + 16..stco + 20].copy_from_slice(&(u32::MAX - 100).to_be_bytes());
+
+        let mut demuxer = Mp4Demuxer::new(Cursor::new(data)).unwrap();
+        assert!(demuxer.next().unwrap().is_ok());
+        assert!(demuxer.next().unwrap().is_ok());
+        assert!(demuxer.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_chunk_offset_overflow() {
+        // Replace the chunk offsets with a single 64-bit offset, so that the samples of the
+        // chunk overflow.
+        let mut data = mp4_file();
+        let stco = data
+            .windows(4)
+            .position(|window| window == b"stco")
+            .unwrap();
+        data[stco..stco + 4].copy_from_slice(b"co64");
+        data[stco + 8..stco + 12].copy_from_slice(&1u32.to_be_bytes());
+        data[stco + 12..stco + 20].copy_from_slice(&(u64::MAX - 100).to_be_bytes());
+
+        assert!(Mp4Demuxer::new(Cursor::new(data)).is_err());
+    }
+
+    #[test]
+    fn test_no_vvc_track() {
+        let data = [mp4_box(b"ftyp", b"isom\0\0\0\0"), mp4_box(b"moov", &[])].concat();
+        assert!(Mp4Demuxer::new(Cursor::new(data)).is_err());
+        assert!(Mp4Demuxer::new(Cursor::new(DATA)).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+
+use anyhow::bail;
+use clap::ValueEnum;
+use vvdec::{ColorFormat, Frame, Plane, PlaneComponent};
+use y4m::{Colorspace, Encoder};
+
+// Frame rate written to Y4M headers when the stream does not signal one.
+const DEFAULT_FRAME_RATE: y4m::Ratio = y4m::Ratio { num: 25, den: 1 };
+
+/// Output file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// YUV4MPEG2 file, with the picture size, frame rate and colorspace in its header.
+    Y4m,
+    /// Raw planar YUV, as written by the VTM and vvdecapp, with samples of more than 8 bits in
+    /// 16-bit little-endian.
+    Yuv,
+}
+
+/// Writer of decoded frames in the output format.
+pub struct FrameWriter {
+    writer: Box<dyn Write>,
+    format: OutputFormat,
+    // Bit depth of the written samples, or that of each frame if unset.
+    bit_depth: Option<u32>,
+    y4m_encoder: Option<Encoder<Box<dyn Write>>>,
+}
+
+impl FrameWriter {
+    pub fn new(
+        writer: Box<dyn Write>,
+        format: OutputFormat,
+        bit_depth: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        if format == OutputFormat::Y4m
+            && bit_depth.is_some_and(|bit_depth| !matches!(bit_depth, 8 | 10 | 12))
+        {
+            bail!("Y4M output only supports a bit depth of 8, 10 or 12");
+        }
+        Ok(Self {
+            writer,
+            format,
+            bit_depth,
+            y4m_encoder: None,
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: Frame) -> anyhow::Result<()> {
+        let bit_depth = self.bit_depth.unwrap_or(frame.bit_depth());
+        let planes: Vec<_> = [PlaneComponent::Y, PlaneComponent::U, PlaneComponent::V]
+            .into_iter()
+            .filter_map(|component| frame.plane(component))
+            .map(|plane| pack_samples(&plane, frame.bit_depth(), bit_depth))
+            .collect();
+
+        match self.format {
+            OutputFormat::Y4m => {
+                let y4m_encoder = match &mut self.y4m_encoder {
+                    Some(y4m_encoder) => y4m_encoder,
+                    None => {
+                        let writer = std::mem::replace(&mut self.writer, Box::new(io::sink()));
+                        self.y4m_encoder
+                            .insert(create_y4m_encoder(&frame, bit_depth, writer)?)
+                    }
+                };
+                let plane = |index: usize| planes.get(index).map_or(&[][..], Vec::as_slice);
+                y4m_encoder.write_frame(&y4m::Frame::new([plane(0), plane(1), plane(2)], None))?;
+            }
+            OutputFormat::Yuv => {
+                for plane in &planes {
+                    self.writer.write_all(plane)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_y4m_encoder<W: Write>(
+    frame: &Frame,
+    bit_depth: u32,
+    writer: W,
+) -> anyhow::Result<Encoder<W>> {
+    let frame_rate = frame
+        .picture_attributes()
+        .and_then(|picture_attributes| picture_attributes.hrd)
+        .and_then(|hrd| {
+            // Pictures last one clock tick unless the highest sublayer signals otherwise.
+            let elemental_duration_in_tc = hrd
+                .sublayers
+                .last()
+                .and_then(|sublayer| sublayer.elemental_duration_in_tc)
+                .unwrap_or(1);
+            hrd.frame_rate(elemental_duration_in_tc)
+        })
+        .map_or(DEFAULT_FRAME_RATE, |(num, den)| y4m::Ratio {
+            num: num as usize,
+            den: den as usize,
+        });
+    let encoder = y4m::encode(frame.width() as usize, frame.height() as usize, frame_rate)
+        .with_colorspace(convert_colorspace(frame.color_format(), bit_depth)?)
+        .write_header(writer)?;
+    Ok(encoder)
+}
+
+fn convert_colorspace(color_format: ColorFormat, bit_depth: u32) -> anyhow::Result<Colorspace> {
+    let colorspace = match (color_format, bit_depth) {
+        (ColorFormat::Yuv400Planar, 8) => Colorspace::Cmono,
+        (ColorFormat::Yuv400Planar, 12) => Colorspace::Cmono12,
+        (ColorFormat::Yuv420Planar, 8) => Colorspace::C420,
+        (ColorFormat::Yuv420Planar, 10) => Colorspace::C420p10,
+        (ColorFormat::Yuv420Planar, 12) => Colorspace::C420p12,
+        (ColorFormat::Yuv422Planar, 8) => Colorspace::C422,
+        (ColorFormat::Yuv422Planar, 10) => Colorspace::C422p10,
+        (ColorFormat::Yuv422Planar, 12) => Colorspace::C422p12,
+        (ColorFormat::Yuv444Planar, 8) => Colorspace::C444,
+        (ColorFormat::Yuv444Planar, 10) => Colorspace::C444p10,
+        (ColorFormat::Yuv444Planar, 12) => Colorspace::C444p12,
+        _ => bail!("Y4M output does not support {color_format:?} with a bit depth of {bit_depth}"),
+    };
+    Ok(colorspace)
+}
+
+// Packs the samples of a plane without padding, in bytes for 8 bits or less and in 16-bit
+// little-endian otherwise.
+fn pack_samples(plane: &Plane, bit_depth: u32, output_bit_depth: u32) -> Vec<u8> {
+    let bytes_per_sample = plane.bytes_per_sample() as usize;
+    let output_bytes_per_sample = if output_bit_depth > 8 { 2 } else { 1 };
+    if bit_depth == output_bit_depth
+        && bytes_per_sample == output_bytes_per_sample
+        && (bytes_per_sample == 1 || cfg!(target_endian = "little"))
+    {
+        return plane.to_contiguous();
+    }
+
+    let mut data =
+        Vec::with_capacity((plane.width() * plane.height()) as usize * output_bytes_per_sample);
+    for row in plane.rows() {
+        for sample in row.chunks_exact(bytes_per_sample) {
+            let sample = match *sample {
+                [sample] => sample as u16,
+                [first, second] => u16::from_ne_bytes([first, second]),
+                _ => unreachable!(),
+            };
+            let sample = convert_sample(sample, bit_depth, output_bit_depth);
+            if output_bytes_per_sample == 2 {
+                data.extend_from_slice(&sample.to_le_bytes());
+            } else {
+                data.push(sample as u8);
+            }
+        }
+    }
+    data
+}
+
+// Scales a sample to another bit depth, rounding and clipping like the VTM when reducing it.
+fn convert_sample(sample: u16, bit_depth: u32, output_bit_depth: u32) -> u16 {
+    if output_bit_depth >= bit_depth {
+        return sample << (output_bit_depth - bit_depth);
+    }
+    let shift = bit_depth - output_bit_depth;
+    let max = (1 << output_bit_depth) - 1;
+    ((sample as u32 + (1 << (shift - 1))) >> shift).min(max) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_colorspace() {
+        assert!(matches!(
+            convert_colorspace(ColorFormat::Yuv400Planar, 8),
+            Ok(Colorspace::Cmono)
+        ));
+        assert!(matches!(
+            convert_colorspace(ColorFormat::Yuv420Planar, 10),
+            Ok(Colorspace::C420p10)
+        ));
+        assert!(matches!(
+            convert_colorspace(ColorFormat::Yuv444Planar, 12),
+            Ok(Colorspace::C444p12)
+        ));
+        assert!(convert_colorspace(ColorFormat::Yuv400Planar, 10).is_err());
+        assert!(convert_colorspace(ColorFormat::Yuv420Planar, 16).is_err());
+    }
+
+    #[test]
+    fn test_convert_sample() {
+        assert_eq!(convert_sample(512, 10, 10), 512);
+        assert_eq!(convert_sample(200, 8, 10), 800);
+        assert_eq!(convert_sample(255, 8, 16), 0xff00);
+        assert_eq!(convert_sample(513, 10, 8), 128);
+        assert_eq!(convert_sample(514, 10, 8), 129);
+        assert_eq!(convert_sample(1023, 10, 8), 255);
+        assert_eq!(convert_sample(0xffff, 16, 8), 255);
+    }
+}
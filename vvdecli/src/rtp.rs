@@ -0,0 +1,461 @@
+use std::io::{self, Read};
+
+use anyhow::bail;
+use vvdec::{
+    nal::{RtpDepacketizer, RtpPacket},
+    AccessUnit,
+};
+
+use crate::input::StreamSelector;
+
+const PCAP_MAGICS: [u32; 2] = [0xa1b2_c3d4, 0xa1b2_3c4d];
+const PCAPNG_MAGIC: u32 = 0x0a0d_0d0a;
+const RTPDUMP_MAGIC: &[u8] = b"#!rtpplay1.0 ";
+// Largest captured length accepted by libpcap.
+const MAX_CAPTURED_LENGTH: usize = 262_144;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: [u16; 2] = [0x8100, 0x88a8];
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Check whether data looks like the start of a pcap or pcapng file.
+pub fn probe_pcap(data: &[u8]) -> bool {
+    let Some(magic) = data.get(..4) else {
+        return false;
+    };
+    let magic = [magic[0], magic[1], magic[2], magic[3]];
+    [u32::from_le_bytes(magic), u32::from_be_bytes(magic)]
+        .iter()
+        .any(|magic| PCAP_MAGICS.contains(magic) || *magic == PCAPNG_MAGIC)
+}
+
+/// Check whether data looks like the start of an rtpdump file.
+pub fn probe_rtpdump(data: &[u8]) -> bool {
+    data.starts_with(RTPDUMP_MAGIC)
+}
+
+/// Source of RTP packets.
+pub trait PacketReader {
+    /// Read the next packet, or None at the end of the input.
+    fn next_packet(&mut self) -> anyhow::Result<Option<Vec<u8>>>;
+}
+
+/// Reader of the UDP payloads of a pcap file.
+///
+/// IPv4 and IPv6 packets over Ethernet, Linux cooked captures, raw IP and loopback link types are
+/// supported. Fragmented IP packets are skipped.
+pub struct PcapReader<R> {
+    reader: R,
+    big_endian: bool,
+    link_type: u32,
+    port: Option<u16>,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Create a reader of the UDP payloads sent to `port`, or to any port if unset.
+    pub fn new(mut reader: R, port: Option<u16>) -> anyhow::Result<Self> {
+        let mut header = [0; 24];
+        if !read_exact_or_eof(&mut reader, &mut header)? {
+            bail!("invalid pcap file");
+        }
+        let magic = [header[0], header[1], header[2], header[3]];
+        let big_endian = if PCAP_MAGICS.contains(&u32::from_le_bytes(magic)) {
+            false
+        } else if PCAP_MAGICS.contains(&u32::from_be_bytes(magic)) {
+            true
+        } else if u32::from_le_bytes(magic) == PCAPNG_MAGIC {
+            bail!("pcapng files are not supported, convert them to pcap first");
+        } else {
+            bail!("invalid pcap file");
+        };
+
+        Ok(Self {
+            reader,
+            big_endian,
+            // The upper bits may carry the FCS length.
+            link_type: read_u32(&header[20..24], big_endian) & 0x0fff_ffff,
+            port,
+        })
+    }
+
+    fn udp_payload<'a>(&self, frame: &'a [u8]) -> Option<&'a [u8]> {
+        let (mut ethertype, mut packet) = match self.link_type {
+            LINKTYPE_ETHERNET => (read_u16(frame, 12)?, frame.get(14..)?),
+            LINKTYPE_LINUX_SLL => (read_u16(frame, 14)?, frame.get(16..)?),
+            LINKTYPE_LINUX_SLL2 => (read_u16(frame, 0)?, frame.get(20..)?),
+            // The IP version is found in the packet itself.
+            LINKTYPE_NULL => (0, frame.get(4..)?),
+            LINKTYPE_RAW => (0, frame),
+            _ => return None,
+        };
+        while ETHERTYPE_VLAN.contains(&ethertype) {
+            ethertype = read_u16(packet, 2)?;
+            packet = packet.get(4..)?;
+        }
+        if ethertype == 0 {
+            ethertype = match packet.first()? >> 4 {
+                4 => ETHERTYPE_IPV4,
+                6 => ETHERTYPE_IPV6,
+                _ => return None,
+            };
+        }
+
+        let datagram = match ethertype {
+            ETHERTYPE_IPV4 => {
+                let header_length = (packet.first()? & 0xf) as usize * 4;
+                let total_length = read_u16(packet, 2)? as usize;
+                // Fragments have the more fragments flag or an offset set.
+                let is_fragment = read_u16(packet, 6)? & 0x3fff != 0;
+                if *packet.get(9)? != IP_PROTOCOL_UDP || is_fragment {
+                    return None;
+                }
+                packet.get(header_length..total_length)?
+            }
+            // Extension headers are not supported.
+            ETHERTYPE_IPV6 => {
+                let payload_length = read_u16(packet, 4)? as usize;
+                if *packet.get(6)? != IP_PROTOCOL_UDP {
+                    return None;
+                }
+                packet.get(40..40 + payload_length)?
+            }
+            _ => return None,
+        };
+
+        let port = read_u16(datagram, 2)?;
+        let length = read_u16(datagram, 4)? as usize;
+        if self.port.is_some_and(|expected| expected != port) {
+            return None;
+        }
+        datagram.get(8..length)
+    }
+}
+
+impl<R: Read> PacketReader for PcapReader<R> {
+    fn next_packet(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        loop {
+            let mut header = [0; 16];
+            if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+            let captured_length = read_u32(&header[8..12], self.big_endian) as usize;
+            if captured_length > MAX_CAPTURED_LENGTH {
+                bail!("invalid pcap packet length {captured_length}");
+            }
+            let mut frame = vec![0; captured_length];
+            if !read_exact_or_eof(&mut self.reader, &mut frame)? {
+                return Ok(None);
+            }
+            if let Some(payload) = self.udp_payload(&frame) {
+                return Ok(Some(payload.to_vec()));
+            }
+        }
+    }
+}
+
+/// Reader of the RTP packets of an rtpdump file, skipping RTCP packets.
+pub struct RtpdumpReader<R> {
+    reader: R,
+}
+
+impl<R: Read> RtpdumpReader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        // A text line with the source address is followed by a binary header of 16 bytes.
+        let mut line = Vec::new();
+        let mut byte = [0];
+        while line.last() != Some(&b'\n') && line.len() < 256 {
+            if !read_exact_or_eof(&mut reader, &mut byte)? {
+                break;
+            }
+            line.push(byte[0]);
+        }
+        let mut header = [0; 16];
+        if !line.starts_with(RTPDUMP_MAGIC)
+            || line.last() != Some(&b'\n')
+            || !read_exact_or_eof(&mut reader, &mut header)?
+        {
+            bail!("invalid rtpdump file");
+        }
+        Ok(Self { reader })
+    }
+}
+
+impl<R: Read> PacketReader for RtpdumpReader<R> {
+    fn next_packet(&mut self) -> anyhow::Result<Option<Vec<u8>>> {
+        loop {
+            // Length of the record with this header, length of the RTP packet and time offset.
+            let mut header = [0; 8];
+            if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+            let length = u16::from_be_bytes([header[0], header[1]]) as usize;
+            let packet_length = u16::from_be_bytes([header[2], header[3]]) as usize;
+            let mut packet = vec![0; length.saturating_sub(header.len())];
+            if !read_exact_or_eof(&mut self.reader, &mut packet)? {
+                return Ok(None);
+            }
+            // RTCP packets have a packet length of 0.
+            if packet_length > 0 {
+                packet.truncate(packet_length);
+                return Ok(Some(packet));
+            }
+        }
+    }
+}
+
+/// Demuxer of a VVC RTP stream.
+///
+/// Packets of other payload types or synchronization sources than the selected stream are skipped,
+/// as well as RTCP packets. Invalid packets are treated as lost.
+pub struct RtpDemuxer<P> {
+    reader: P,
+    payload_type: Option<u8>,
+    ssrc: Option<u32>,
+    depacketizer: RtpDepacketizer,
+    eof: bool,
+}
+
+impl<P: PacketReader> RtpDemuxer<P> {
+    pub fn new(reader: P, selector: StreamSelector) -> Self {
+        Self {
+            reader,
+            payload_type: selector.payload_type,
+            ssrc: None,
+            depacketizer: RtpDepacketizer::new(),
+            eof: false,
+        }
+    }
+
+    fn accepts(&mut self, packet: &RtpPacket) -> bool {
+        // RTCP packet types 200 to 204 read as payload types 72 to 76 with the marker bit set.
+        if (72..=76).contains(&packet.payload_type) {
+            return false;
+        }
+        if *self.payload_type.get_or_insert(packet.payload_type) != packet.payload_type {
+            return false;
+        }
+        *self.ssrc.get_or_insert(packet.ssrc) == packet.ssrc
+    }
+}
+
+impl<P: PacketReader> Iterator for RtpDemuxer<P> {
+    type Item = anyhow::Result<AccessUnit<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.eof {
+            let packet = match self.reader.next_packet() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => {
+                    self.eof = true;
+                    return self.depacketizer.flush().map(Ok);
+                }
+                Err(err) => return Some(Err(err)),
+            };
+            if !RtpPacket::parse(&packet).is_ok_and(|rtp_packet| self.accepts(&rtp_packet)) {
+                continue;
+            }
+            if let Ok(Some(access_unit)) = self.depacketizer.push(&packet) {
+                return Some(Ok(access_unit));
+            }
+        }
+        None
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+// Returns false if the reader ends before the buffer is filled.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vvdec::nal::{strip_start_code, AnnexBSplitter};
+
+    use super::*;
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+    const PORT: u16 = 5004;
+
+    // RTP packets of short.vvc in single NAL unit packets, with the parameter sets and the IDR
+    // slice in the first access unit.
+    fn rtp_packets(payload_type: u8) -> Vec<Vec<u8>> {
+        let timestamps = [3000u32, 3000, 3000, 3000, 6000, 9000];
+        AnnexBSplitter::new(DATA)
+            .map(strip_start_code)
+            .zip(timestamps)
+            .enumerate()
+            .map(|(index, (nal_unit, timestamp))| {
+                let marker = timestamps.get(index + 1) != Some(&timestamp);
+                let mut packet = vec![0x80, (marker as u8) << 7 | payload_type];
+                packet.extend_from_slice(&(index as u16).to_be_bytes());
+                packet.extend_from_slice(&timestamp.to_be_bytes());
+                packet.extend_from_slice(&0x1234_5678u32.to_be_bytes());
+                packet.extend_from_slice(nal_unit);
+                packet
+            })
+            .collect()
+    }
+
+    fn ethernet_frame(port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+
+        let total_length = 20 + 8 + payload.len() as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, IP_PROTOCOL_UDP, 0, 0]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+
+        frame.extend_from_slice(&PORT.to_be_bytes());
+        frame.extend_from_slice(&port.to_be_bytes());
+        frame.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    // Little-endian pcap file with the video on PORT and other packets on another port.
+    fn pcap_file() -> Vec<u8> {
+        let mut pcap = 0xa1b2_c3d4u32.to_le_bytes().to_vec();
+        pcap.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0]);
+        pcap.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+
+        let other = ethernet_frame(PORT + 2, &rtp_packets(97)[0]);
+        for packet in rtp_packets(96) {
+            for frame in [ethernet_frame(PORT, &packet), other.clone()] {
+                pcap.extend_from_slice(&[0; 8]);
+                pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+                pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+                pcap.extend_from_slice(&frame);
+            }
+        }
+        pcap
+    }
+
+    fn rtpdump_file() -> Vec<u8> {
+        let mut rtpdump = b"#!rtpplay1.0 10.0.0.2/5004\n".to_vec();
+        rtpdump.extend_from_slice(&[0; 16]);
+
+        let rtcp = [0x80, 200, 0, 6, 0, 0, 0, 0];
+        for packet in rtp_packets(96).iter().map(Vec::as_slice).chain([&rtcp[..]]) {
+            let packet_length = if packet[1] == 200 { 0 } else { packet.len() };
+            rtpdump.extend_from_slice(&(8 + packet.len() as u16).to_be_bytes());
+            rtpdump.extend_from_slice(&(packet_length as u16).to_be_bytes());
+            rtpdump.extend_from_slice(&[0; 4]);
+            rtpdump.extend_from_slice(packet);
+        }
+        rtpdump
+    }
+
+    fn demux(reader: impl PacketReader, selector: StreamSelector) -> Vec<AccessUnit<Vec<u8>>> {
+        RtpDemuxer::new(reader, selector)
+            .collect::<anyhow::Result<_>>()
+            .unwrap()
+    }
+
+    fn assert_access_units(access_units: &[AccessUnit<Vec<u8>>]) {
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| access_unit.cts)
+            .collect();
+        // The depacketizer extends RTP timestamps from 2^32.
+        assert_eq!(
+            timestamps,
+            [3000, 6000, 9000].map(|timestamp| Some((1 << 32) + timestamp))
+        );
+        assert!(access_units[0].is_random_access_point);
+        assert!(access_units
+            .iter()
+            .all(|access_unit| !access_unit.is_damaged));
+
+        let nal_units: Vec<_> = access_units
+            .iter()
+            .flat_map(|access_unit| AnnexBSplitter::new(&access_unit.payload))
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(
+            nal_units,
+            AnnexBSplitter::new(DATA)
+                .map(strip_start_code)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_probe() {
+        assert!(probe_pcap(&pcap_file()));
+        assert!(probe_rtpdump(&rtpdump_file()));
+        assert!(!probe_pcap(DATA));
+        assert!(!probe_rtpdump(DATA));
+    }
+
+    #[test]
+    fn test_pcap() {
+        let pcap = pcap_file();
+        let reader = PcapReader::new(pcap.as_slice(), Some(PORT)).unwrap();
+        assert_access_units(&demux(reader, StreamSelector::default()));
+
+        // Both streams have the same SSRC, so the payload type tells them apart.
+        let reader = PcapReader::new(pcap.as_slice(), None).unwrap();
+        let selector = StreamSelector {
+            payload_type: Some(96),
+            ..Default::default()
+        };
+        assert_access_units(&demux(reader, selector));
+
+        let reader = PcapReader::new(pcap.as_slice(), Some(PORT + 1)).unwrap();
+        assert!(demux(reader, StreamSelector::default()).is_empty());
+    }
+
+    #[test]
+    fn test_pcap_invalid_length() {
+        let mut pcap = pcap_file()[..24].to_vec();
+        pcap.extend_from_slice(&[0; 8]);
+        pcap.extend_from_slice(&[0xff; 8]);
+        let mut reader = PcapReader::new(pcap.as_slice(), None).unwrap();
+        assert!(reader.next_packet().is_err());
+    }
+
+    #[test]
+    fn test_pcapng() {
+        let mut pcapng = [0; 24];
+        pcapng[..4].copy_from_slice(&PCAPNG_MAGIC.to_le_bytes());
+        assert!(probe_pcap(&pcapng));
+        let err = PcapReader::new(pcapng.as_slice(), None).err().unwrap();
+        assert!(err.to_string().contains("pcapng"));
+    }
+
+    #[test]
+    fn test_rtpdump() {
+        let rtpdump = rtpdump_file();
+        let reader = RtpdumpReader::new(rtpdump.as_slice()).unwrap();
+        assert_access_units(&demux(reader, StreamSelector::default()));
+
+        assert!(RtpdumpReader::new(DATA).is_err());
+    }
+}
@@ -0,0 +1,566 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Read},
+    mem,
+};
+
+use anyhow::anyhow;
+use vvdec::{
+    nal::{AccessUnitAssembler, AnnexBSplitter},
+    AccessUnit,
+};
+
+use crate::input::StreamSelector;
+
+/// Stream type of VVC elementary streams.
+pub const VVC_STREAM_TYPE: u8 = 0x33;
+
+const PACKET_SIZE: usize = 188;
+const SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0;
+
+/// Check whether data looks like the start of an MPEG-2 transport stream.
+pub fn probe(data: &[u8]) -> bool {
+    data.len() >= PACKET_SIZE
+        && data
+            .iter()
+            .step_by(PACKET_SIZE)
+            .take(3)
+            .all(|&byte| byte == SYNC_BYTE)
+}
+
+type Timestamps = (Option<u64>, Option<u64>);
+
+/// Demuxer of a VVC elementary stream from an MPEG-2 transport stream.
+///
+/// PES packets are reassembled and split into Annex-B access units, with NAL units that span
+/// several PES packets joined back together. Each access unit carries the PTS and DTS of the PES
+/// packet it starts in, in the 90 kHz clock, as its composition and decoding timestamps. When
+/// packets are lost, the incomplete PES packet is discarded and the next access unit is flagged as
+/// damaged. An error is returned at the end of the stream if no PES packet of the selected stream
+/// was found.
+pub struct TsDemuxer<R> {
+    reader: R,
+    selector: StreamSelector,
+    // PMT PIDs of the selected programs.
+    pmt_pids: HashSet<u16>,
+    // Partial PSI sections by PID.
+    sections: HashMap<u16, Vec<u8>>,
+    pid: Option<u16>,
+    continuity_counter: Option<u8>,
+    pes: Option<Vec<u8>>,
+    // Whether a PES packet of the selected stream was started.
+    has_pes: bool,
+    // Last NAL unit of the previous PES packet, which may continue in the next one, with the
+    // timestamps of its PES packet if they were not given to an access unit yet.
+    nal_tail: Option<(Vec<u8>, Option<Timestamps>)>,
+    // Whether packets were lost since the last access unit was output.
+    damaged: bool,
+    assembler: AccessUnitAssembler,
+    // Timestamps of the access unit being assembled, if any NAL unit was pushed.
+    timestamps: Option<Timestamps>,
+    access_units: VecDeque<AccessUnit<Vec<u8>>>,
+    eof: bool,
+}
+
+impl<R: Read> TsDemuxer<R> {
+    pub fn new(reader: R, selector: StreamSelector) -> Self {
+        Self {
+            reader,
+            selector,
+            pmt_pids: HashSet::new(),
+            sections: HashMap::new(),
+            pid: selector.pid,
+            continuity_counter: None,
+            pes: None,
+            has_pes: false,
+            nal_tail: None,
+            damaged: false,
+            assembler: AccessUnitAssembler::new(),
+            timestamps: None,
+            access_units: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    // Reads the next packet, resynchronizing on the sync byte if needed. Returns None at the end of
+    // the stream.
+    fn read_packet(&mut self) -> io::Result<Option<[u8; PACKET_SIZE]>> {
+        let mut packet = [0; PACKET_SIZE];
+        let mut len = 0;
+        loop {
+            while len < PACKET_SIZE {
+                match self.reader.read(&mut packet[len..]) {
+                    Ok(0) => return Ok(None),
+                    Ok(n) => len += n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            match packet.iter().position(|&byte| byte == SYNC_BYTE) {
+                Some(0) => return Ok(Some(packet)),
+                Some(offset) => {
+                    packet.copy_within(offset.., 0);
+                    len -= offset;
+                }
+                None => len = 0,
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: &[u8; PACKET_SIZE]) {
+        let transport_error = packet[1] & 0x80 != 0;
+        let payload_unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1f, packet[2]]);
+        let adaptation_field_control = packet[3] >> 4 & 0b11;
+        let continuity_counter = packet[3] & 0xf;
+        if transport_error || adaptation_field_control & 0b01 == 0 {
+            return;
+        }
+
+        let payload_start = if adaptation_field_control & 0b10 != 0 {
+            5 + packet[4] as usize
+        } else {
+            4
+        };
+        let Some(payload) = packet.get(payload_start..) else {
+            return;
+        };
+
+        if Some(pid) == self.pid {
+            self.handle_pes_payload(payload, payload_unit_start, continuity_counter);
+        } else if self.pid.is_none() && (pid == PAT_PID || self.pmt_pids.contains(&pid)) {
+            if let Some(section) = self.push_section(pid, payload, payload_unit_start) {
+                if pid == PAT_PID {
+                    self.parse_pat(&section);
+                } else {
+                    self.parse_pmt(&section);
+                }
+            }
+        }
+    }
+
+    // Returns the section once complete, without its CRC.
+    fn push_section(
+        &mut self,
+        pid: u16,
+        payload: &[u8],
+        payload_unit_start: bool,
+    ) -> Option<Vec<u8>> {
+        if payload_unit_start {
+            let pointer = *payload.first()? as usize;
+            self.sections
+                .insert(pid, payload.get(1 + pointer..)?.to_vec());
+        } else {
+            self.sections.get_mut(&pid)?.extend_from_slice(payload);
+        }
+
+        let section = self.sections.get(&pid)?;
+        let section_length =
+            u16::from_be_bytes([*section.get(1)? & 0xf, *section.get(2)?]) as usize;
+        if section.len() < 3 + section_length || section_length < 4 {
+            return None;
+        }
+        let mut section = self.sections.remove(&pid)?;
+        section.truncate(3 + section_length - 4);
+        Some(section)
+    }
+
+    fn parse_pat(&mut self, section: &[u8]) {
+        if section[0] != 0x00 {
+            return;
+        }
+        for entry in section.get(8..).unwrap_or_default().chunks_exact(4) {
+            let program = u16::from_be_bytes([entry[0], entry[1]]);
+            let pmt_pid = u16::from_be_bytes([entry[2] & 0x1f, entry[3]]);
+            // Program 0 refers to the network information table.
+            if program != 0 && self.selector.program.unwrap_or(program) == program {
+                self.pmt_pids.insert(pmt_pid);
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, section: &[u8]) {
+        if section[0] != 0x02 || section.len() < 12 {
+            return;
+        }
+        let program_info_length = u16::from_be_bytes([section[10] & 0xf, section[11]]) as usize;
+        let mut streams = section.get(12 + program_info_length..).unwrap_or_default();
+        while let [stream_type, pid_high, pid_low, info_length_high, info_length_low, rest @ ..] =
+            streams
+        {
+            if *stream_type == VVC_STREAM_TYPE {
+                self.pid = Some(u16::from_be_bytes([pid_high & 0x1f, *pid_low]));
+                return;
+            }
+            let es_info_length =
+                u16::from_be_bytes([info_length_high & 0xf, *info_length_low]) as usize;
+            streams = rest.get(es_info_length..).unwrap_or_default();
+        }
+    }
+
+    fn handle_pes_payload(
+        &mut self,
+        payload: &[u8],
+        payload_unit_start: bool,
+        continuity_counter: u8,
+    ) {
+        if let Some(previous) = self.continuity_counter.replace(continuity_counter) {
+            if continuity_counter == previous {
+                // Duplicate packet.
+                return;
+            }
+            if continuity_counter != (previous + 1) & 0xf {
+                // Packets were lost, so the current PES packet and the NAL unit that may continue in
+                // it are incomplete.
+                self.pes = None;
+                self.nal_tail = None;
+                self.damaged = true;
+            }
+        }
+
+        if payload_unit_start {
+            self.has_pes = true;
+            if let Some(pes) = self.pes.replace(payload.to_vec()) {
+                self.handle_pes(&pes);
+            }
+        } else if let Some(pes) = &mut self.pes {
+            pes.extend_from_slice(payload);
+        }
+    }
+
+    fn handle_pes(&mut self, pes: &[u8]) {
+        let [0, 0, 1, _stream_id, _, _, _, flags, header_length, header @ ..] = pes else {
+            return;
+        };
+        let Some(payload) = header.get(*header_length as usize..) else {
+            return;
+        };
+        let pts = (flags & 0x80 != 0)
+            .then(|| header.get(..5).map(read_timestamp))
+            .flatten();
+        let dts = (flags & 0xc0 == 0xc0)
+            .then(|| header.get(5..10).map(read_timestamp))
+            .flatten();
+
+        let (mut data, mut tail_timestamps) = self.nal_tail.take().unwrap_or_default();
+        let has_tail = !data.is_empty();
+        data.extend_from_slice(payload);
+
+        let mut timestamps = Some((pts, dts));
+        let mut nal_units = AnnexBSplitter::new(&data).enumerate().peekable();
+        while let Some((index, nal_unit)) = nal_units.next() {
+            let timestamps = if has_tail && index == 0 {
+                &mut tail_timestamps
+            } else {
+                &mut timestamps
+            };
+            if nal_units.peek().is_some() {
+                self.push_nal_unit(nal_unit, timestamps);
+            } else {
+                // Keep the trailing zero bytes, which may be part of the NAL unit or of the next
+                // start code.
+                let start = nal_unit.as_ptr() as usize - data.as_ptr() as usize;
+                self.nal_tail = Some((data[start..].to_vec(), timestamps.take()));
+            }
+        }
+    }
+
+    fn push_nal_unit(&mut self, nal_unit: &[u8], timestamps: &mut Option<Timestamps>) {
+        let previous = self.assembler.push(nal_unit);
+        let starts_access_unit = previous.is_some() || self.timestamps.is_none();
+        if let Some(access_unit) = previous {
+            self.push_access_unit(access_unit);
+        }
+        // Only the first access unit starting in the PES packet gets its timestamps.
+        if starts_access_unit {
+            self.timestamps = Some(timestamps.take().unwrap_or_default());
+        }
+    }
+
+    fn push_access_unit(&mut self, mut access_unit: AccessUnit<Vec<u8>>) {
+        (access_unit.cts, access_unit.dts) = self.timestamps.take().unwrap_or_default();
+        access_unit.is_damaged = mem::take(&mut self.damaged);
+        self.access_units.push_back(access_unit);
+    }
+
+    fn finish(&mut self) {
+        if let Some(pes) = self.pes.take() {
+            self.handle_pes(&pes);
+        }
+        if let Some((nal_unit, mut timestamps)) = self.nal_tail.take() {
+            self.push_nal_unit(&nal_unit, &mut timestamps);
+        }
+        if let Some(access_unit) = self.assembler.flush() {
+            self.push_access_unit(access_unit);
+        }
+        self.eof = true;
+    }
+}
+
+impl<R: Read> Iterator for TsDemuxer<R> {
+    type Item = anyhow::Result<AccessUnit<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(access_unit) = self.access_units.pop_front() {
+                return Some(Ok(access_unit));
+            }
+            if self.eof {
+                return None;
+            }
+            match self.read_packet() {
+                Ok(Some(packet)) => self.handle_packet(&packet),
+                Ok(None) => {
+                    self.finish();
+                    if !self.has_pes {
+                        return Some(Err(match self.pid {
+                            Some(pid) => anyhow!("no PES packet found with PID {pid:#x}"),
+                            None => anyhow!("no VVC stream found in the transport stream"),
+                        }));
+                    }
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+// Reads a 33-bit PTS or DTS, split by marker bits over 5 bytes.
+fn read_timestamp(bytes: &[u8]) -> u64 {
+    (bytes[0] as u64 >> 1 & 0x7) << 30
+        | (bytes[1] as u64) << 22
+        | (bytes[2] as u64 >> 1) << 15
+        | (bytes[3] as u64) << 7
+        | bytes[4] as u64 >> 1
+}
+
+#[cfg(test)]
+mod tests {
+    use vvdec::nal::strip_start_code;
+
+    use super::*;
+
+    const DATA: &[u8] = include_bytes!("../../tests/short.vvc");
+    const PMT_PID: u16 = 0x100;
+    const VIDEO_PID: u16 = 0x101;
+
+    fn packetize(pid: u16, data: &[u8], continuity_counter: &mut u8) -> Vec<u8> {
+        let mut packets = Vec::new();
+        for (index, chunk) in data.chunks(PACKET_SIZE - 4).enumerate() {
+            let start = if index == 0 { 0x40 } else { 0 };
+            packets.extend_from_slice(&[SYNC_BYTE, start | (pid >> 8) as u8, pid as u8]);
+            if chunk.len() < PACKET_SIZE - 4 {
+                // Stuffing in the adaptation field.
+                let adaptation_field_length = PACKET_SIZE - 5 - chunk.len();
+                packets.push(0x30 | *continuity_counter);
+                packets.push(adaptation_field_length as u8);
+                if adaptation_field_length > 0 {
+                    packets.push(0);
+                    packets.resize(packets.len() + adaptation_field_length - 1, 0xff);
+                }
+            } else {
+                packets.push(0x10 | *continuity_counter);
+            }
+            packets.extend_from_slice(chunk);
+            *continuity_counter = (*continuity_counter + 1) & 0xf;
+        }
+        packets
+    }
+
+    fn section(table_id: u8, id: u16, body: &[u8]) -> Vec<u8> {
+        let section_length = 5 + body.len() + 4;
+        let mut section = vec![
+            0,
+            table_id,
+            0xb0 | (section_length >> 8) as u8,
+            section_length as u8,
+        ];
+        section.extend_from_slice(&id.to_be_bytes());
+        section.extend_from_slice(&[0xc1, 0, 0]);
+        section.extend_from_slice(body);
+        // The CRC is not checked.
+        section.extend_from_slice(&[0; 4]);
+        section
+    }
+
+    fn timestamp(prefix: u8, value: u64) -> [u8; 5] {
+        [
+            prefix << 4 | ((value >> 29) & 0xe) as u8 | 1,
+            (value >> 22) as u8,
+            ((value >> 14) & 0xfe) as u8 | 1,
+            (value >> 7) as u8,
+            ((value << 1) & 0xfe) as u8 | 1,
+        ]
+    }
+
+    fn pes(data: &[u8], pts: u64, dts: u64) -> Vec<u8> {
+        let mut pes = vec![0, 0, 1, 0xe0, 0, 0, 0x80, 0xc0, 10];
+        pes.extend_from_slice(&timestamp(3, pts));
+        pes.extend_from_slice(&timestamp(1, dts));
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    // Transport stream with the access units of short.vvc, each in a PES packet, with an audio
+    // stream listed first.
+    fn ts_file() -> Vec<u8> {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).collect();
+        ts_file_with_pes(&[
+            (nal_units[..4].concat(), 7200, 3600),
+            (nal_units[4].to_vec(), 18000, 7200),
+            (nal_units[5].to_vec(), 10800, 10800),
+        ])
+    }
+
+    // Transport stream with the given PES packet payloads and their PTS and DTS.
+    fn ts_file_with_pes(pes_packets: &[(Vec<u8>, u64, u64)]) -> Vec<u8> {
+        let pat = section(0x00, 1, &[0, 1, 0xe0 | (PMT_PID >> 8) as u8, PMT_PID as u8]);
+        let video_pid = [0xe0 | (VIDEO_PID >> 8) as u8, VIDEO_PID as u8];
+        let pmt_body = [
+            // PCR PID and program info length.
+            &video_pid[..],
+            &[0xf0, 0],
+            &[0x0f, 0xe1, 0x02, 0xf0, 0],
+            &[VVC_STREAM_TYPE],
+            &video_pid,
+            &[0xf0, 0],
+        ]
+        .concat();
+        let pmt = section(0x02, 1, &pmt_body);
+
+        let mut continuity_counters = [0; 3];
+        let mut ts = [
+            packetize(PAT_PID, &pat, &mut continuity_counters[0]),
+            packetize(PMT_PID, &pmt, &mut continuity_counters[1]),
+        ]
+        .concat();
+        for (payload, pts, dts) in pes_packets {
+            ts.extend(packetize(
+                VIDEO_PID,
+                &pes(payload, *pts, *dts),
+                &mut continuity_counters[2],
+            ));
+        }
+        ts
+    }
+
+    #[test]
+    fn test_probe() {
+        assert!(probe(&ts_file()));
+        assert!(!probe(DATA));
+    }
+
+    fn demux(data: &[u8]) -> Vec<AccessUnit<Vec<u8>>> {
+        TsDemuxer::new(data, StreamSelector::default())
+            .collect::<anyhow::Result<_>>()
+            .unwrap()
+    }
+
+    fn assert_nal_units(access_units: &[AccessUnit<Vec<u8>>]) {
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).map(strip_start_code).collect();
+        let demuxed: Vec<_> = access_units
+            .iter()
+            .flat_map(|access_unit| AnnexBSplitter::new(&access_unit.payload))
+            .map(strip_start_code)
+            .collect();
+        assert_eq!(demuxed, nal_units);
+    }
+
+    fn assert_timestamps(access_units: &[AccessUnit<Vec<u8>>]) {
+        let timestamps: Vec<_> = access_units
+            .iter()
+            .map(|access_unit| (access_unit.cts, access_unit.dts))
+            .collect();
+        assert_eq!(
+            timestamps,
+            [
+                (Some(7200), Some(3600)),
+                (Some(18000), Some(7200)),
+                (Some(10800), Some(10800))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_demux() {
+        let access_units = demux(&ts_file());
+        assert_eq!(access_units.len(), 3);
+        assert_timestamps(&access_units);
+        assert!(access_units[0].is_random_access_point);
+        assert!(access_units
+            .iter()
+            .all(|access_unit| !access_unit.is_damaged));
+        assert_nal_units(&access_units);
+    }
+
+    #[test]
+    fn test_nal_unit_across_pes_packets() {
+        // The first access unit is split in two PES packets within the start code of its slice.
+        let nal_units: Vec<_> = AnnexBSplitter::new(DATA).collect();
+        let first_access_unit = nal_units[..4].concat();
+        let split = first_access_unit.len() - nal_units[3].len() + 2;
+        let ts = ts_file_with_pes(&[
+            (first_access_unit[..split].to_vec(), 7200, 3600),
+            (first_access_unit[split..].to_vec(), 7200, 3600),
+            (nal_units[4].to_vec(), 18000, 7200),
+            (nal_units[5].to_vec(), 10800, 10800),
+        ]);
+
+        let access_units = demux(&ts);
+        assert_eq!(access_units.len(), 3);
+        assert_timestamps(&access_units);
+        assert_nal_units(&access_units);
+    }
+
+    #[test]
+    fn test_select() {
+        let demux_selected = |program, pid| {
+            let selector = StreamSelector {
+                program,
+                pid,
+                ..Default::default()
+            };
+            TsDemuxer::new(ts_file().as_slice(), selector).collect::<anyhow::Result<Vec<_>>>()
+        };
+        assert_eq!(demux_selected(Some(1), None).unwrap().len(), 3);
+        assert!(demux_selected(Some(2), None).is_err());
+        assert_eq!(demux_selected(None, Some(VIDEO_PID)).unwrap().len(), 3);
+        assert!(demux_selected(None, Some(0x102)).is_err());
+    }
+
+    #[test]
+    fn test_resync_and_duplicates() {
+        let ts = ts_file();
+        // Garbage before the first packet, and the first video packet duplicated.
+        let mut data = vec![0xaa; 10];
+        data.extend_from_slice(&ts[..3 * PACKET_SIZE]);
+        data.extend_from_slice(&ts[2 * PACKET_SIZE..]);
+
+        let access_units = demux(&data);
+        assert_eq!(access_units.len(), 3);
+        assert_nal_units(&access_units);
+    }
+
+    #[test]
+    fn test_lost_packet() {
+        let ts = ts_file();
+        // The first video packet is dropped, so the first PES packet is discarded.
+        let mut data = ts[..2 * PACKET_SIZE].to_vec();
+        data.extend_from_slice(&ts[3 * PACKET_SIZE..]);
+
+        let access_units = demux(&data);
+        assert_eq!(access_units.len(), 2);
+        assert_eq!(access_units[0].cts, Some(18000));
+
+        // The second video packet is dropped, so the next access unit is flagged as damaged.
+        let mut data = ts[..3 * PACKET_SIZE].to_vec();
+        data.extend_from_slice(&ts[4 * PACKET_SIZE..]);
+
+        let access_units = demux(&data);
+        assert_eq!(access_units.len(), 2);
+        assert_eq!(access_units[0].cts, Some(18000));
+        assert!(access_units[0].is_damaged);
+        assert!(!access_units[1].is_damaged);
+    }
+}
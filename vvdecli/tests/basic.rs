@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use vvdec::{Decoder, PlaneComponent};
 
 #[test]
 fn basic() {
@@ -28,3 +29,105 @@ fn basic() {
         .assert()
         .success();
 }
+
+// Decodes the input with the library and packs the samples of each frame like the VTM writes raw
+// YUV files, in 16-bit little-endian or rounded to 8 bits.
+fn reference_yuv(input: &[u8], output_bit_depth: u32) -> Vec<u8> {
+    let mut decoder = Decoder::new().unwrap();
+    let _ = decoder.decode(input);
+
+    let mut data = Vec::new();
+    while let Some(frame) = decoder.flush().unwrap() {
+        let shift = frame.bit_depth() - output_bit_depth;
+        for component in [PlaneComponent::Y, PlaneComponent::U, PlaneComponent::V] {
+            let plane = frame.plane(component).unwrap();
+            for row in plane.rows() {
+                for sample in row.chunks_exact(2) {
+                    let sample = u16::from_ne_bytes([sample[0], sample[1]]) as u32;
+                    if shift == 0 {
+                        data.extend_from_slice(&(sample as u16).to_le_bytes());
+                    } else {
+                        let max = (1 << output_bit_depth) - 1;
+                        data.push(((sample + (1 << (shift - 1))) >> shift).min(max) as u8);
+                    }
+                }
+            }
+        }
+    }
+    data
+}
+
+#[test]
+fn yuv_output() {
+    let input = std::env::current_dir()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("tests")
+        .join("short.vvc");
+    let data = std::fs::read(&input).unwrap();
+    let output = tempfile::NamedTempFile::new().unwrap();
+
+    // 3 frames of 320x240 in 4:2:0, with 10-bit samples in 2 bytes, then converted to 8 bits.
+    for (args, bit_depth, frame_size) in [
+        (&[][..], 10, 320 * 240 * 3),
+        (&["--output-bitdepth", "8"][..], 8, 320 * 240 * 3 / 2),
+    ] {
+        Command::cargo_bin("vvdecli")
+            .unwrap()
+            .args([
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                output.path().to_str().unwrap(),
+            ])
+            .args(["--format", "yuv"])
+            .args(args)
+            .assert()
+            .success();
+        let yuv = std::fs::read(output.path()).unwrap();
+        assert_eq!(yuv.len(), 3 * frame_size);
+        assert!(yuv == reference_yuv(&data, bit_depth));
+    }
+}
+
+#[test]
+fn damaged_input() {
+    let data = std::fs::read(
+        std::env::current_dir()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("tests")
+            .join("short.vvc"),
+    )
+    .unwrap();
+
+    // Truncate the slice of the last picture in decoding order, then restart from the IDR picture.
+    let nal_units: Vec<_> = vvdec::nal::AnnexBSplitter::new(&data).collect();
+    let mut damaged = nal_units[..5].concat();
+    damaged.extend_from_slice(&[0, 0, 1]);
+    damaged.extend_from_slice(&vvdec::nal::strip_start_code(nal_units[5])[..4]);
+    damaged.extend_from_slice(&data);
+    let input = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(input.path(), damaged).unwrap();
+
+    let output = Command::cargo_bin("vvdecli")
+        .unwrap()
+        .args([
+            "-i",
+            input.path().to_str().unwrap(),
+            "-o",
+            tempfile::NamedTempFile::new()
+                .unwrap()
+                .path()
+                .to_str()
+                .unwrap(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("frames may be corrupted"), "{stderr}");
+}